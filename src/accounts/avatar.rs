@@ -0,0 +1,237 @@
+//! # 🖼️ Avatar upload kontroller
+//!
+//! Allows a logged in account to upload an avatar image. The image is
+//! sent as the `avatar` field of a multipart form, its type is
+//! detected from the uploaded data (PNG, JPEG, GIF or WebP) and it is
+//! cropped and resized to a fixed-size square PNG thumbnail. The
+//! upload is only decoded and stored once the account is checked, and
+//! images wider or higher than 8192 pixels are refused.
+//!
+//! Thumbnails are stored in the kontroller's `upload_root` directory
+//! under a generated name, and their URL (`url_prefix` + file name) is
+//! exposed through the account's [`super::PublicAccount`]. The
+//! previous avatar file is removed when an avatar is replaced.
+//!
+//! > To use the avatar kontroller, enable it with the `avatar` Cargo
+//! > feature.
+
+use super::database::Database;
+use crate::error::KontrollerError;
+use image::{imageops::FilterType, ImageFormat};
+use kong::krypto::kpassport::Kpassport;
+use kong::{json, json_from_str, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use rand::{distributions::Alphanumeric, Rng};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Get the username of the account a kpassport acts as, the
+/// impersonated account for an impersonation kpassport
+#[cfg(feature = "login")]
+fn kpassport_username(
+    k: &Kpassport,
+    database: Arc<Mutex<Database>>,
+) -> Result<String, KontrollerError> {
+    crate::login::session_username(k, database)
+}
+
+/// Get the username of the account a kpassport acts as, accounts can
+/// only be impersonated with the `login` feature
+#[cfg(not(feature = "login"))]
+fn kpassport_username(
+    k: &Kpassport,
+    _database: Arc<Mutex<Database>>,
+) -> Result<String, KontrollerError> {
+    Ok(k.content.username.clone())
+}
+
+/// ## 🖼️ Avatar upload kontroller
+pub struct UploadAvatarKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Directory where avatars are stored, eg `./www/uploads/avatars`
+    pub upload_root: String,
+    /// URL path the upload root is served at, eg `/uploads/avatars`
+    pub url_prefix: String,
+    /// Largest accepted upload, in bytes
+    pub max_size: usize,
+    /// Width and height of the thumbnail, in pixels
+    pub size: u32,
+}
+
+impl UploadAvatarKontroller {
+    /// Image formats accepted as avatars
+    const FORMATS: [ImageFormat; 4] = [
+        ImageFormat::Png,
+        ImageFormat::Jpeg,
+        ImageFormat::Gif,
+        ImageFormat::WebP,
+    ];
+
+    /// Largest width and height of an uploaded image, in pixels
+    const MAX_DIMENSION: u32 = 8192;
+
+    /// Get the path of a stored avatar from its file name, file names
+    /// containing a path are refused
+    fn avatar_path(&self, file_name: &str) -> Option<PathBuf> {
+        let is_plain_name = Path::new(file_name)
+            .file_name()
+            .is_some_and(|name| name == file_name);
+
+        if is_plain_name {
+            Some(Path::new(&self.upload_root).join(file_name))
+        } else {
+            None
+        }
+    }
+
+    /// Validate the uploaded image and store its thumbnail, returns the
+    /// thumbnail's file name
+    fn store_thumbnail(&self, data: &[u8]) -> Result<String, ()> {
+        if data.len() > self.max_size {
+            return Err(());
+        }
+
+        // Detect the type from the data, not from what the client claims
+        let format = image::guess_format(data).map_err(|_| ())?;
+        if !Self::FORMATS.contains(&format) {
+            return Err(());
+        }
+
+        let mut limits = image::io::Limits::default();
+        limits.max_image_width = Some(Self::MAX_DIMENSION);
+        limits.max_image_height = Some(Self::MAX_DIMENSION);
+
+        let mut reader = image::io::Reader::with_format(Cursor::new(data), format);
+        reader.limits(limits);
+        let thumbnail = reader.decode().map_err(|_| ())?.resize_to_fill(
+            self.size,
+            self.size,
+            FilterType::Lanczos3,
+        );
+
+        let name: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(24)
+            .map(char::from)
+            .collect();
+        let file_name = format!("{name}.png");
+
+        std::fs::create_dir_all(&self.upload_root).map_err(|_| ())?;
+        let path = self.avatar_path(&file_name).ok_or(())?;
+        thumbnail
+            .save_with_format(path, ImageFormat::Png)
+            .map_err(|_| ())?;
+
+        Ok(file_name)
+    }
+
+    /// Remove a stored avatar by its URL
+    fn remove_avatar(&self, url: &str) {
+        if let Some(file_name) = url
+            .strip_prefix(&self.url_prefix)
+            .map(|name| name.trim_start_matches('/'))
+        {
+            if let Some(path) = self.avatar_path(file_name) {
+                // the avatar may already have been removed
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+impl Kontrol for UploadAvatarKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get the uploaded image, its thumbnail is stored once the account
+    /// is checked
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        if let Ok(input) = server::post_input!(request, {
+            avatar: server::input::post::BufferedFile,
+        }) {
+            // Uploads that are too large are not buffered
+            if input.avatar.data.is_empty() || input.avatar.data.len() > self.max_size {
+                return None;
+            }
+
+            Some(json!({ "avatar": input.avatar.data }))
+        } else {
+            None
+        }
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match &input {
+            Some(value) if value["avatar"].is_array() => Ok(input),
+            _ => Err(()),
+        }
+    }
+
+    /// Store the thumbnail and set the account's avatar
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let k = if let Some(k) = &kong.kpassport {
+            k
+        } else {
+            return ErrorResponse::unauthorized();
+        };
+
+        let username = match kpassport_username(k, self.database.clone()) {
+            Ok(username) => username,
+            Err(KontrollerError::Kpassport) => return ErrorResponse::unauthorized(),
+            Err(_) => return ErrorResponse::internal(),
+        };
+
+        let data: Vec<u8> = match kong
+            .input
+            .as_ref()
+            .and_then(|input| json_from_str(&input["avatar"].to_string()).ok())
+        {
+            Some(data) => data,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let file_name = match self.store_thumbnail(&data) {
+            Ok(file_name) => file_name,
+            Err(()) => return ErrorResponse::bad_request(),
+        };
+
+        let url = format!("{}/{file_name}", self.url_prefix.trim_end_matches('/'));
+
+        let res = self
+            .database
+            .lock()
+            .unwrap()
+            .update_avatar(&username, Some(&url));
+
+        match res {
+            Ok(previous) => {
+                if let Some(previous) = previous {
+                    self.remove_avatar(&previous);
+                }
+
+                server::Response::json(&json!({ "avatar": url })).with_status_code(200)
+            }
+            Err(err) => {
+                self.remove_avatar(&url);
+
+                match err {
+                    KontrollerError::DbField => ErrorResponse::not_found(),
+                    _ => ErrorResponse::internal(),
+                }
+            }
+        }
+    }
+}
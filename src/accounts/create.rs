@@ -6,14 +6,26 @@
 //! database.
 //!
 //! ## 👮‍♂️ Admin accounts
-//! If the input from the request uses the admin email that is in
-//! the `konfig` file, the account that is created is an admin
-//! account. Admin accounts simply means an account stored in the
-//! database with it's `account_type` field as `admin`, from this
-//! you can build up your own higher level abstractions.
+//! While there are no admin accounts, an account created with the
+//! admin email that is in the `konfig` file is an admin account, this
+//! bootstraps the first admin. Admin accounts simply means an account
+//! stored in the database with it's `account_type` field as `admin`,
+//! from this you can build up your own higher level abstractions.
+//! More admins can then be added with the [`super::role`]
+//! kontrollers. An existing account can be made the first admin with
+//! [`Database::bootstrap_admin`].
+//!
+//! The account's password must follow the kontroller's
+//! [`PasswordPolicy`].
+//!
+//! ## 🚪 Registration modes
+//! Non-admin accounts are created according to the kontroller's
+//! [`RegistrationMode`]: immediately, only with a valid invite code,
+//! queued for admin approval, or not at all.
 
 use super::database::Database;
-use super::{Account, AccountCreationInput, PublicAccount};
+use super::policy::PasswordPolicy;
+use super::{Account, AccountCreationInput, PublicAccount, RegistrationMode, RegistrationRequest};
 use crate::error::KontrollerError;
 use kong::{inputs::UserInput, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
 use std::sync::{Arc, Mutex};
@@ -27,6 +39,10 @@ pub struct CreateAccountKontroller {
     pub method: Method,
     /// SQLite database handle
     pub database: Arc<Mutex<Database>>,
+    /// Who is allowed to create an account
+    pub registration: RegistrationMode,
+    /// Rules the account's password must follow
+    pub password_policy: PasswordPolicy,
 }
 
 impl Kontrol for CreateAccountKontroller {
@@ -63,7 +79,13 @@ impl Kontrol for CreateAccountKontroller {
 
             match input {
                 Ok(input) => {
-                    if input.is_valid().is_ok() {
+                    let policy = self.password_policy.check(
+                        &input.password,
+                        &input.username,
+                        input.email.as_deref(),
+                    );
+
+                    if input.is_valid().is_ok() && policy.is_ok() {
                         Ok(Some(input.as_json()))
                     } else {
                         Err(())
@@ -84,32 +106,74 @@ impl Kontrol for CreateAccountKontroller {
                 Ok(input) => {
                     let mut account: Account = input.clone().into();
 
-                    // create admin account
-                    if input.email == kong.config.admin_email {
+                    // bootstrap the first admin account, the account is
+                    // created as a normal account if there is an admin
+                    if input.email.is_some() && input.email == kong.config.admin_email {
                         account.account_type = Some("admin".to_string());
-                        match self.database.lock().unwrap().create_admin_account(&account) {
-                            Ok(_) => {
+                        match self
+                            .database
+                            .lock()
+                            .unwrap()
+                            .create_first_admin_account(&account)
+                        {
+                            Ok(true) => {
                                 let public_account: PublicAccount = account.into();
-                                server::Response::json(&public_account).with_status_code(201)
+                                return server::Response::json(&public_account)
+                                    .with_status_code(201);
                             }
-                            Err(err) => match err {
-                                KontrollerError::DbField => ErrorResponse::bad_request(),
-                                _ => ErrorResponse::internal(),
-                            },
-                        }
-                    } else {
-                        // Create normal account
-                        match self.database.lock().unwrap().create_account(&account) {
-                            Ok(_) => {
-                                let public_account: PublicAccount = account.into();
-                                server::Response::json(&public_account).with_status_code(201)
+                            Ok(false) => account.account_type = None,
+                            Err(err) => {
+                                return match err {
+                                    KontrollerError::DbField => ErrorResponse::bad_request(),
+                                    _ => ErrorResponse::internal(),
+                                }
                             }
+                        }
+                    }
+
+                    // Create normal account
+                    let res = match self.registration {
+                        RegistrationMode::Open => {
+                            self.database.lock().unwrap().create_account(&account)
+                        }
+                        RegistrationMode::InviteOnly => match &input.invite {
+                            Some(code) => self
+                                .database
+                                .lock()
+                                .unwrap()
+                                .create_account_with_invite(&account, code),
+                            None => return ErrorResponse::unauthorized(),
+                        },
+                        RegistrationMode::AdminApproval => {
+                            // Queue the account until an admin approves it
+                            let request: RegistrationRequest = account.into();
+                            return match self
+                                .database
+                                .lock()
+                                .unwrap()
+                                .create_registration_request(&request)
+                            {
+                                Ok(_) => server::Response::json(&request).with_status_code(202),
+                                Err(err) => match err {
+                                    KontrollerError::DbField => ErrorResponse::bad_request(),
+                                    _ => ErrorResponse::internal(),
+                                },
+                            };
+                        }
+                        RegistrationMode::Closed => return ErrorResponse::unauthorized(),
+                    };
 
-                            Err(err) => match err {
-                                KontrollerError::DbField => ErrorResponse::bad_request(),
-                                _ => ErrorResponse::internal(),
-                            },
+                    match res {
+                        Ok(_) => {
+                            let public_account: PublicAccount = account.into();
+                            server::Response::json(&public_account).with_status_code(201)
                         }
+
+                        Err(err) => match err {
+                            KontrollerError::DbField => ErrorResponse::bad_request(),
+                            KontrollerError::Invite => ErrorResponse::unauthorized(),
+                            _ => ErrorResponse::internal(),
+                        },
                     }
                 }
 
@@ -120,3 +184,41 @@ impl Kontrol for CreateAccountKontroller {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDatabase;
+
+    fn account(username: &str) -> Account {
+        AccountCreationInput {
+            username: username.to_string(),
+            password: "correct horse battery".to_string(),
+            email: Some("admin@example.com".to_string()),
+            invite: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_first_admin() {
+        let db_file = TempDatabase::new("ACCOUNTS_FIRST_ADMIN_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+
+        // Only one account becomes the first admin
+        let mut first = account("first");
+        first.account_type = Some("admin".to_string());
+        assert!(db.create_first_admin_account(&first).unwrap());
+        let mut second = account("second");
+        second.account_type = Some("admin".to_string());
+        assert!(!db.create_first_admin_account(&second).unwrap());
+        assert_eq!(db.count_admins().unwrap(), 1);
+        assert!(db
+            .private_get_account_by_username("second")
+            .unwrap()
+            .is_none());
+
+        assert!(!db.bootstrap_admin("admin@example.com").unwrap());
+    }
+}
@@ -1,8 +1,12 @@
 //! # 🗄️ Accounts database management
 //!
-use super::{Account, PublicAccount};
+use super::{
+    Account, AuditEntry, Impersonation, Invite, PasswordReset, PublicAccount, RegistrationRequest,
+};
 use crate::error::KontrollerError;
-use rusqlite::{params, Connection};
+use crate::migration;
+use chrono::{DateTime, Utc};
+use rusqlite::{named_params, params, Connection, OptionalExtension, TransactionBehavior};
 
 /// SQL statements and queries
 pub mod sql {
@@ -25,11 +29,15 @@ pub mod sql {
         website TEXT,                                -- Account owner's web-address
         description TEXT,                            -- Short bio of Account
         last_login TEXT,                              -- Date account last logged in
-        account_type TEXT)                           -- Type of account, eg `admin`";
+        account_type TEXT,                           -- Type of account, eg `admin`
+        avatar TEXT)                                 -- URL of the account's avatar image";
 
     /// Get account by username
     pub const GET_ACCOUNT_BY_USERNAME: &str = "SELECT * FROM accounts WHERE username = :username;";
 
+    /// Get the usernames of all accounts
+    pub const GET_USERNAMES: &str = "SELECT username FROM accounts ORDER BY username;";
+
     /// Get account by email
     pub const GET_ACCOUNT_BY_EMAIL: &str = "SELECT * FROM accounts WHERE email = :email;";
 
@@ -53,6 +61,179 @@ pub mod sql {
         account_type
        )
       VALUES (?1, ?2, ?3, ?4, ?5)";
+
+    /// Set the avatar of an account
+    pub const SET_AVATAR: &str = "UPDATE accounts SET avatar = ?1 WHERE username = ?2;";
+
+    /// Set the password hash of an account
+    pub const SET_PASSWORD: &str = "UPDATE accounts SET password = ?1 WHERE username = ?2;";
+
+    /// Count admin accounts
+    pub const COUNT_ADMINS: &str = "SELECT COUNT(*) FROM accounts WHERE account_type = 'admin';";
+
+    /// Set the type of an account
+    pub const SET_ACCOUNT_TYPE: &str = "UPDATE accounts SET account_type = ?1 WHERE username = ?2;";
+
+    /// Make the account with an email an admin account
+    pub const SET_ADMIN_BY_EMAIL: &str =
+        "UPDATE accounts SET account_type = 'admin' WHERE email = ?1;";
+
+    /// Create audit log table
+    pub const CREATE_AUDIT_LOG_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS audit_log (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the entry, the Rust Type is `i64`
+        actor TEXT NOT NULL,                         -- Username of the account that performed the action
+        action TEXT NOT NULL,                        -- The action that was performed, eg `impersonation_start`
+        subject TEXT,                                -- Username of the account the action was performed on
+        date TEXT NOT NULL)                          -- The date when the action was performed";
+
+    /// Insert an entry in the audit log
+    pub const CREATE_AUDIT_ENTRY: &str = "
+      INSERT INTO audit_log (
+        actor,
+        action,
+        subject,
+        date
+       )
+      VALUES (?1, ?2, ?3, ?4)";
+
+    /// Get all audit log entries, newest first
+    pub const GET_AUDIT_LOG: &str = "SELECT * FROM audit_log ORDER BY id DESC;";
+
+    /// Create impersonations table
+    pub const CREATE_IMPERSONATIONS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS impersonations (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the impersonation, the Rust Type is `i64`
+        admin TEXT NOT NULL,                         -- Username of the admin doing the impersonation
+        target TEXT NOT NULL,                        -- Username of the impersonated account
+        started TEXT NOT NULL,                       -- The date when the impersonation started
+        expires TEXT NOT NULL,                       -- The date when the impersonation expires
+        ended TEXT,                                  -- The date when the impersonation was ended
+        token TEXT)                                  -- The token the impersonation kpassport is issued for";
+
+    /// Insert an impersonation
+    pub const CREATE_IMPERSONATION: &str = "
+      INSERT INTO impersonations (
+        admin,
+        target,
+        started,
+        expires,
+        token
+       )
+      VALUES (?1, ?2, ?3, ?4, ?5)";
+
+    /// Get the impersonation issued for a token, if it has not ended or expired
+    pub const GET_ACTIVE_IMPERSONATION: &str = "
+      SELECT * FROM impersonations
+      WHERE token = :token AND ended IS NULL AND expires > :now
+      LIMIT 1;";
+
+    /// End all running impersonations of an account
+    pub const END_IMPERSONATION: &str =
+        "UPDATE impersonations SET ended = ?1 WHERE target = ?2 AND ended IS NULL;";
+
+    /// End the impersonation issued for a token
+    pub const END_IMPERSONATION_BY_TOKEN: &str =
+        "UPDATE impersonations SET ended = ?1 WHERE token = ?2 AND ended IS NULL;";
+
+    /// Create registration invites table
+    pub const CREATE_INVITES_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS invites (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the invite, the Rust Type is `i64`
+        code TEXT UNIQUE NOT NULL,                   -- The invite code
+        email TEXT,                                  -- Email address the invite is bound to
+        max_uses INTEGER,                            -- How many accounts can be created with the invite
+        uses INTEGER NOT NULL DEFAULT 0,             -- How many accounts have been created with the invite
+        expires TEXT,                                -- The date when the invite expires
+        created_by TEXT NOT NULL,                    -- Username of the admin that created the invite
+        created TEXT NOT NULL)                       -- The date when the invite was created";
+
+    /// Insert an invite
+    pub const CREATE_INVITE: &str = "
+      INSERT INTO invites (
+        code,
+        email,
+        max_uses,
+        uses,
+        expires,
+        created_by,
+        created
+       )
+      VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+
+    /// Get all invites, newest first
+    pub const GET_INVITES: &str = "SELECT * FROM invites ORDER BY id DESC;";
+
+    /// Delete an invite
+    pub const DELETE_INVITE: &str = "DELETE FROM invites WHERE code = :code;";
+
+    /// Create password resets table
+    pub const CREATE_PASSWORD_RESETS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS password_resets (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the reset, the Rust Type is `i64`
+        code TEXT UNIQUE NOT NULL,                   -- The reset code
+        username TEXT NOT NULL,                      -- Username of the account whose password is reset
+        expires TEXT NOT NULL,                       -- The date when the code expires
+        created_by TEXT NOT NULL,                    -- Username of the admin that created the code
+        created TEXT NOT NULL)                       -- The date when the code was created";
+
+    /// Insert a password reset
+    pub const CREATE_PASSWORD_RESET: &str = "
+      INSERT INTO password_resets (
+        code,
+        username,
+        expires,
+        created_by,
+        created
+       )
+      VALUES (?1, ?2, ?3, ?4, ?5)";
+
+    /// Get a password reset that has not expired
+    pub const GET_PASSWORD_RESET: &str = "
+      SELECT code, username, expires, created_by, created FROM password_resets
+      WHERE code = :code AND expires > :now;";
+
+    /// Delete the password resets of an account
+    pub const DELETE_PASSWORD_RESETS: &str = "DELETE FROM password_resets WHERE username = ?1;";
+
+    /// Count one use of an invite, if it is still usable by the email
+    pub const USE_INVITE: &str = "
+      UPDATE invites
+      SET uses = uses + 1
+      WHERE code = ?1
+        AND (max_uses IS NULL OR uses < max_uses)
+        AND (expires IS NULL OR expires > ?2)
+        AND (email IS NULL OR email = ?3);";
+
+    /// Create registration requests table
+    pub const CREATE_REGISTRATION_REQUESTS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS registration_requests (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the request, the Rust Type is `i64`
+        username TEXT UNIQUE NOT NULL,               -- The requested username
+        email TEXT UNIQUE,                           -- The requested email address
+        password TEXT NOT NULL,                      -- The user's login password
+        created TEXT NOT NULL)                       -- The date when the registration was requested";
+
+    /// Insert a registration request
+    pub const CREATE_REGISTRATION_REQUEST: &str = "
+      INSERT INTO registration_requests (
+        username,
+        email,
+        password,
+        created
+       )
+      VALUES (?1, ?2, ?3, ?4)";
+
+    /// Get all registration requests, oldest first
+    pub const GET_REGISTRATION_REQUESTS: &str = "SELECT * FROM registration_requests ORDER BY id;";
+
+    /// Get registration request by username
+    pub const GET_REGISTRATION_REQUEST: &str =
+        "SELECT * FROM registration_requests WHERE username = :username;";
+
+    /// Delete a registration request
+    pub const DELETE_REGISTRATION_REQUEST: &str =
+        "DELETE FROM registration_requests WHERE username = :username;";
 }
 
 /// Database management system
@@ -89,6 +270,24 @@ impl Database {
                 tx.execute(sql::CREATE_ACCOUNTS_TABLE, ())
                     .map_err(|_| KontrollerError::DbTableCreation)?;
 
+                // Columns added after the accounts table was first released
+                migration::add_column(&tx, "accounts", "avatar", "TEXT")?;
+
+                tx.execute(sql::CREATE_AUDIT_LOG_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_IMPERSONATIONS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_INVITES_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_REGISTRATION_REQUESTS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_PASSWORD_RESETS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
                 tx.commit().map_err(|_| KontrollerError::DbTableCreation)?;
 
                 Ok(())
@@ -139,6 +338,66 @@ impl Database {
         }
     }
 
+    /// Create the first admin account, returns `false` without creating
+    /// the account if there is an admin account already. Admins are
+    /// counted in the same transaction as the account is created, so
+    /// only one first admin can be created.
+    pub fn create_first_admin_account(
+        &mut self,
+        account: &Account,
+    ) -> Result<bool, KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                let admins: i64 = tx
+                    .query_row(sql::COUNT_ADMINS, [], |row| row.get(0))
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                if admins > 0 {
+                    return Ok(false);
+                }
+
+                tx.execute(
+                    sql::CREATE_ADMIN_ACCOUNT,
+                    params![
+                        &account.username,
+                        &account.email,
+                        account.password,
+                        account.created,
+                        &account.account_type
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(true)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get the usernames of all accounts
+    pub fn get_usernames(&self) -> Result<Vec<String>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::GET_USERNAMES)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let usernames = stmt
+                    .query_map([], |row| row.get(0))
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                usernames
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
     /// Get an account's public data by its username
     pub fn public_get_account_by_username(
         &self,
@@ -154,7 +413,8 @@ impl Database {
                     .map_err(|_| KontrollerError::DbSQL)?;
                 match rows.next().map_err(|_| KontrollerError::DbSQL)? {
                     Some(s) => Ok(Some(PublicAccount {
-                        username: s.get(2).map_err(|_| KontrollerError::DbField)?,
+                        username: s.get(1).map_err(|_| KontrollerError::DbField)?,
+                        avatar: s.get(17).map_err(|_| KontrollerError::DbField)?,
                     })),
                     None => Ok(None),
                 }
@@ -178,7 +438,8 @@ impl Database {
                     .map_err(|_| KontrollerError::DbSQL)?;
                 match rows.next().map_err(|_| KontrollerError::DbSQL)? {
                     Some(s) => Ok(Some(PublicAccount {
-                        username: s.get(2).map_err(|_| KontrollerError::DbField)?,
+                        username: s.get(1).map_err(|_| KontrollerError::DbField)?,
+                        avatar: s.get(17).map_err(|_| KontrollerError::DbField)?,
                     })),
                     None => Ok(None),
                 }
@@ -218,6 +479,7 @@ impl Database {
                         description: s.get(14).map_err(|_| KontrollerError::DbField)?,
                         last_login: s.get(15).map_err(|_| KontrollerError::DbField)?,
                         account_type: s.get(16).map_err(|_| KontrollerError::DbField)?,
+                        avatar: s.get(17).map_err(|_| KontrollerError::DbField)?,
                     })),
                     None => Ok(None),
                 }
@@ -257,6 +519,266 @@ impl Database {
                         description: s.get(14).map_err(|_| KontrollerError::DbField)?,
                         last_login: s.get(15).map_err(|_| KontrollerError::DbField)?,
                         account_type: s.get(16).map_err(|_| KontrollerError::DbField)?,
+                        avatar: s.get(17).map_err(|_| KontrollerError::DbField)?,
+                    })),
+                    None => Ok(None),
+                }
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Set the avatar URL of an account, returns the previous avatar URL
+    pub fn update_avatar(
+        &self,
+        username: &str,
+        avatar: Option<&str>,
+    ) -> Result<Option<String>, KontrollerError> {
+        let previous = match self.private_get_account_by_username(username)? {
+            Some(account) => account.avatar,
+            None => return Err(KontrollerError::DbField),
+        };
+
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(sql::SET_AVATAR, params![avatar, username])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(previous)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Replace the password hash of an account
+    pub fn update_password(&self, username: &str, password: &str) -> Result<(), KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(sql::SET_PASSWORD, params![password, username])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Count the admin accounts
+    pub fn count_admins(&self) -> Result<i64, KontrollerError> {
+        match &self.conn {
+            Some(conn) => conn
+                .query_row(sql::COUNT_ADMINS, [], |row| row.get(0))
+                .map_err(|_| KontrollerError::DbSQL),
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Bootstrap the first admin. If there are no admin accounts, the
+    /// account with the email is made an admin account. Returns `true`
+    /// if an account was promoted.
+    pub fn bootstrap_admin(&mut self, email: &str) -> Result<bool, KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                let admins: i64 = tx
+                    .query_row(sql::COUNT_ADMINS, [], |row| row.get(0))
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                if admins > 0 {
+                    return Ok(false);
+                }
+
+                let promoted = tx
+                    .execute(sql::SET_ADMIN_BY_EMAIL, params![email])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(promoted > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Make an account an admin account, returns `false` if the
+    /// account does not exist
+    pub fn promote_admin(&self, username: &str) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let promoted = conn
+                    .execute(sql::SET_ACCOUNT_TYPE, params!["admin", username])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(promoted > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Set the type of an account, returns `false` if the account does
+    /// not exist. Admin accounts are promoted and demoted with
+    /// [`Database::promote_admin`] and [`Database::demote_admin`].
+    pub fn set_account_type(
+        &self,
+        username: &str,
+        account_type: Option<&str>,
+    ) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let updated = conn
+                    .execute(sql::SET_ACCOUNT_TYPE, params![account_type, username])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(updated > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Make an admin account a normal account, returns `false` if the
+    /// account does not exist or is not an admin. The last admin
+    /// account can not be demoted.
+    pub fn demote_admin(&mut self, username: &str) -> Result<bool, KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                let admins: i64 = tx
+                    .query_row(sql::COUNT_ADMINS, [], |row| row.get(0))
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                let account_type: Option<Option<String>> = tx
+                    .query_row(
+                        "SELECT account_type FROM accounts WHERE username = ?1",
+                        params![username],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                if account_type.flatten().as_deref() != Some("admin") {
+                    return Ok(false);
+                }
+
+                if admins <= 1 {
+                    return Err(KontrollerError::LastAdmin);
+                }
+
+                tx.execute(sql::SET_ACCOUNT_TYPE, params![None::<String>, username])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(true)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Write an entry to the audit log
+    pub fn create_audit_entry(&self, entry: &AuditEntry) -> Result<(), KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(
+                    sql::CREATE_AUDIT_ENTRY,
+                    params![&entry.actor, &entry.action, &entry.subject, entry.date],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get all audit log entries, newest first
+    pub fn get_audit_log(&self) -> Result<Vec<AuditEntry>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut entries: Vec<AuditEntry> = vec![];
+                let mut stmt = conn
+                    .prepare(sql::GET_AUDIT_LOG)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let entries_iter = stmt
+                    .query_map([], |row| {
+                        Ok(AuditEntry {
+                            actor: row.get(1)?,
+                            action: row.get(2)?,
+                            subject: row.get(3)?,
+                            date: row.get(4)?,
+                        })
+                    })
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                for entry in entries_iter {
+                    entries.push(entry.map_err(|_| KontrollerError::DbField)?);
+                }
+
+                Ok(entries)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Start impersonating an account, any running impersonation of
+    /// the same account is ended first
+    pub fn create_impersonation(
+        &mut self,
+        impersonation: &Impersonation,
+    ) -> Result<(), KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                tx.execute(
+                    sql::END_IMPERSONATION,
+                    params![impersonation.started, &impersonation.target],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+
+                tx.execute(
+                    sql::CREATE_IMPERSONATION,
+                    params![
+                        &impersonation.admin,
+                        &impersonation.target,
+                        impersonation.started,
+                        impersonation.expires,
+                        &impersonation.token
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get the running impersonation issued for a token, if any
+    pub fn get_active_impersonation(
+        &self,
+        token: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Impersonation>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::GET_ACTIVE_IMPERSONATION)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let mut rows = stmt
+                    .query(rusqlite::named_params! { ":token": token, ":now": now })
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                match rows.next().map_err(|_| KontrollerError::DbSQL)? {
+                    Some(s) => Ok(Some(Impersonation {
+                        token: s.get(6).map_err(|_| KontrollerError::DbField)?,
+                        admin: s.get(1).map_err(|_| KontrollerError::DbField)?,
+                        target: s.get(2).map_err(|_| KontrollerError::DbField)?,
+                        started: s.get(3).map_err(|_| KontrollerError::DbField)?,
+                        expires: s.get(4).map_err(|_| KontrollerError::DbField)?,
+                        ended: s.get(5).map_err(|_| KontrollerError::DbField)?,
                     })),
                     None => Ok(None),
                 }
@@ -264,6 +786,353 @@ impl Database {
             None => Err(KontrollerError::DbConnection),
         }
     }
+
+    /// End the running impersonation issued for a token
+    pub fn end_impersonation(
+        &self,
+        token: &str,
+        ended: DateTime<Utc>,
+    ) -> Result<(), KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(sql::END_IMPERSONATION_BY_TOKEN, params![ended, token])
+                    .map_err(|_| KontrollerError::DbField)?;
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Create a new account using an invite code. The invite is only
+    /// used up if the account is created.
+    pub fn create_account_with_invite(
+        &mut self,
+        account: &Account,
+        code: &str,
+    ) -> Result<(), KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                let used = tx
+                    .execute(
+                        sql::USE_INVITE,
+                        params![code, account.created, &account.email],
+                    )
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                if used == 0 {
+                    return Err(KontrollerError::Invite);
+                }
+
+                tx.execute(
+                    sql::CREATE_ACCOUNT,
+                    params![
+                        &account.username,
+                        &account.email,
+                        account.password,
+                        account.created
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Create a new registration invite
+    pub fn create_invite(&self, invite: &Invite) -> Result<(), KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(
+                    sql::CREATE_INVITE,
+                    params![
+                        &invite.code,
+                        &invite.email,
+                        invite.max_uses,
+                        invite.uses,
+                        invite.expires,
+                        &invite.created_by,
+                        invite.created
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get all registration invites, newest first
+    pub fn get_invites(&self) -> Result<Vec<Invite>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut invites: Vec<Invite> = vec![];
+                let mut stmt = conn
+                    .prepare(sql::GET_INVITES)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let invites_iter = stmt
+                    .query_map([], |row| {
+                        Ok(Invite {
+                            code: row.get(1)?,
+                            email: row.get(2)?,
+                            max_uses: row.get(3)?,
+                            uses: row.get(4)?,
+                            expires: row.get(5)?,
+                            created_by: row.get(6)?,
+                            created: row.get(7)?,
+                        })
+                    })
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                for invite in invites_iter {
+                    invites.push(invite.map_err(|_| KontrollerError::DbField)?);
+                }
+
+                Ok(invites)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Delete a registration invite, returns `false` if it did not exist
+    pub fn delete_invite(&self, code: &str) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let deleted = conn
+                    .execute(sql::DELETE_INVITE, &[(":code", code)])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(deleted > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Create a new password reset code
+    pub fn create_password_reset(&self, reset: &PasswordReset) -> Result<(), KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(
+                    sql::CREATE_PASSWORD_RESET,
+                    params![
+                        &reset.code,
+                        &reset.username,
+                        reset.expires,
+                        &reset.created_by,
+                        reset.created
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get a password reset by its code, `None` if it does not exist or
+    /// has expired
+    pub fn get_password_reset(
+        &self,
+        code: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<PasswordReset>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => conn
+                .query_row(
+                    sql::GET_PASSWORD_RESET,
+                    named_params! { ":code": code, ":now": now },
+                    |row| {
+                        Ok(PasswordReset {
+                            code: row.get(0)?,
+                            username: row.get(1)?,
+                            expires: row.get(2)?,
+                            created_by: row.get(3)?,
+                            created: row.get(4)?,
+                        })
+                    },
+                )
+                .optional()
+                .map_err(|_| KontrollerError::DbField),
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Replace the password hash of the account a reset code was
+    /// created for. All the reset codes of the account are used up,
+    /// fails with [`KontrollerError::PasswordReset`] if the code does
+    /// not exist or has expired.
+    pub fn reset_password(
+        &mut self,
+        code: &str,
+        password: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                let username: String = tx
+                    .query_row(
+                        sql::GET_PASSWORD_RESET,
+                        named_params! { ":code": code, ":now": now },
+                        |row| row.get(1),
+                    )
+                    .optional()
+                    .map_err(|_| KontrollerError::DbField)?
+                    .ok_or(KontrollerError::PasswordReset)?;
+
+                tx.execute(sql::SET_PASSWORD, params![password, &username])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                tx.execute(sql::DELETE_PASSWORD_RESETS, params![&username])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Queue a new account for admin approval
+    pub fn create_registration_request(
+        &self,
+        request: &RegistrationRequest,
+    ) -> Result<(), KontrollerError> {
+        // The username and email should not be used by an existing account
+        if self
+            .private_get_account_by_username(&request.username)?
+            .is_some()
+        {
+            return Err(KontrollerError::DbField);
+        }
+
+        if let Some(email) = &request.email {
+            if self.private_get_account_by_email(email)?.is_some() {
+                return Err(KontrollerError::DbField);
+            }
+        }
+
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(
+                    sql::CREATE_REGISTRATION_REQUEST,
+                    params![
+                        &request.username,
+                        &request.email,
+                        &request.password,
+                        request.created
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get all registration requests waiting for approval, oldest first
+    pub fn get_registration_requests(&self) -> Result<Vec<RegistrationRequest>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut requests: Vec<RegistrationRequest> = vec![];
+                let mut stmt = conn
+                    .prepare(sql::GET_REGISTRATION_REQUESTS)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let requests_iter = stmt
+                    .query_map([], |row| {
+                        Ok(RegistrationRequest {
+                            username: row.get(1)?,
+                            email: row.get(2)?,
+                            password: row.get(3)?,
+                            created: row.get(4)?,
+                        })
+                    })
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                for request in requests_iter {
+                    requests.push(request.map_err(|_| KontrollerError::DbField)?);
+                }
+
+                Ok(requests)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Approve a registration request, the requested account is
+    /// created. Returns `None` if there is no request for the username.
+    pub fn approve_registration_request(
+        &mut self,
+        username: &str,
+    ) -> Result<Option<Account>, KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                let request = {
+                    let mut stmt = tx
+                        .prepare(sql::GET_REGISTRATION_REQUEST)
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                    let mut rows = stmt
+                        .query(&[(":username", username)])
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                    match rows.next().map_err(|_| KontrollerError::DbSQL)? {
+                        Some(s) => RegistrationRequest {
+                            username: s.get(1).map_err(|_| KontrollerError::DbField)?,
+                            email: s.get(2).map_err(|_| KontrollerError::DbField)?,
+                            password: s.get(3).map_err(|_| KontrollerError::DbField)?,
+                            created: s.get(4).map_err(|_| KontrollerError::DbField)?,
+                        },
+                        None => return Ok(None),
+                    }
+                };
+
+                tx.execute(
+                    sql::CREATE_ACCOUNT,
+                    params![
+                        &request.username,
+                        &request.email,
+                        &request.password,
+                        request.created
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+
+                tx.execute(sql::DELETE_REGISTRATION_REQUEST, &[(":username", username)])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(Some(request.into()))
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Reject a registration request, returns `false` if there is no
+    /// request for the username
+    pub fn reject_registration_request(&self, username: &str) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let deleted = conn
+                    .execute(sql::DELETE_REGISTRATION_REQUEST, &[(":username", username)])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(deleted > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
 }
 
 // #[cfg(test)]
@@ -2,6 +2,8 @@
 //!
 //! User input data, that is used to create a new account.
 
+use super::IMPERSONATION_SEPARATOR;
+use chrono::{DateTime, Utc};
 use kong::{
     inputs::UserInput,
     json, json_from_str,
@@ -21,6 +23,8 @@ pub struct AccountCreationInput {
     pub email: Option<String>,
     /// Account master key
     pub password: String,
+    /// Invite code, required when registration is invite-only
+    pub invite: Option<String>,
 }
 
 impl AccountCreationInput {
@@ -29,7 +33,8 @@ impl AccountCreationInput {
         json!({
             "username": self.username,
             "email": self.email,
-            "password": self.password
+            "password": self.password,
+            "invite": self.invite
         })
     }
 
@@ -41,7 +46,8 @@ impl AccountCreationInput {
 }
 impl UserInput for AccountCreationInput {
     fn is_valid(&self) -> Result<(), ValidationError> {
-        if !Validate::username(&self.username) {
+        // The separator would make the username look like an impersonation
+        if !Validate::username(&self.username) || self.username.contains(IMPERSONATION_SEPARATOR) {
             return Err(ValidationError::Username);
         }
 
@@ -58,3 +64,120 @@ impl UserInput for AccountCreationInput {
         Ok(())
     }
 }
+
+/// ## 🎟️ Invite creation input
+///
+/// Data used by an admin to create a new registration invite.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InviteCreationInput {
+    /// Bind the invite to this email address
+    pub email: Option<String>,
+    /// How many accounts can be created with the invite
+    pub max_uses: Option<i64>,
+    /// Date when the invite expires
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl InviteCreationInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "email": self.email,
+            "max_uses": self.max_uses,
+            "expires": self.expires
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<InviteCreationInput, JsonError> {
+        let a: InviteCreationInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+}
+
+impl UserInput for InviteCreationInput {
+    fn is_valid(&self) -> Result<(), ValidationError> {
+        if let Some(email) = &self.email {
+            if !Validate::email(email) {
+                return Err(ValidationError::Email);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## 🔑 Password change input
+///
+/// Data used by a logged in account to change its password.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PasswordChangeInput {
+    /// The account's current master key
+    pub password: String,
+    /// The new master key
+    pub new_password: String,
+}
+
+impl PasswordChangeInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "password": self.password,
+            "new_password": self.new_password
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<PasswordChangeInput, JsonError> {
+        let a: PasswordChangeInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+}
+
+impl UserInput for PasswordChangeInput {
+    fn is_valid(&self) -> Result<(), ValidationError> {
+        if !Validate::password(&self.new_password) {
+            return Err(ValidationError::Password);
+        }
+
+        Ok(())
+    }
+}
+
+/// ## 🗝️ Password reset input
+///
+/// Data used by an account holder to set a new password with a reset
+/// code.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PasswordResetInput {
+    /// The reset code
+    pub code: String,
+    /// The new master key
+    pub new_password: String,
+}
+
+impl PasswordResetInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "code": self.code,
+            "new_password": self.new_password
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<PasswordResetInput, JsonError> {
+        let a: PasswordResetInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+}
+
+impl UserInput for PasswordResetInput {
+    fn is_valid(&self) -> Result<(), ValidationError> {
+        if !Validate::password(&self.new_password) {
+            return Err(ValidationError::Password);
+        }
+
+        Ok(())
+    }
+}
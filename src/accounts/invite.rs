@@ -0,0 +1,317 @@
+//! # 🎟️ Registration invite kontrollers
+//!
+//! These __kontrollers__ allow admin accounts to create, list and
+//! revoke the invite codes used to create accounts when registration
+//! is [`super::RegistrationMode::InviteOnly`].
+//!
+//! An invite can be bound to an email address, limited to a number of
+//! uses and given an expiry date.
+
+use super::database::Database;
+use super::inputs::InviteCreationInput;
+use super::Invite;
+use crate::error::KontrollerError;
+use crate::login::{is_admin, kpassport_error, real_username};
+use chrono::Utc;
+use kong::{inputs::UserInput, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ## 🎟️ Invite creation kontroller
+pub struct CreateInviteKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for CreateInviteKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get user input from HTTP request
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<InviteCreationInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        if let Ok(input) = input {
+            Some(input.as_json())
+        } else {
+            None
+        }
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        if let Some(input) = input {
+            match InviteCreationInput::from_json_str(input.to_string()) {
+                Ok(input) => {
+                    let max_uses_valid = !matches!(input.max_uses, Some(max_uses) if max_uses < 1);
+
+                    if input.is_valid().is_ok() && max_uses_valid {
+                        Ok(Some(input.as_json()))
+                    } else {
+                        Err(())
+                    }
+                }
+                Err(_) => Err(()),
+            }
+        } else {
+            Err(())
+        }
+    }
+
+    /// Create an invite
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let k = if let Some(k) = &kong.kpassport {
+            k
+        } else {
+            return ErrorResponse::unauthorized();
+        };
+
+        match is_admin(k, self.database.clone()) {
+            Ok(true) => (),
+            Ok(false) => return ErrorResponse::unauthorized(),
+            Err(err) => return kpassport_error(err),
+        }
+
+        let created_by = match real_username(k, self.database.clone()) {
+            Ok(username) => username,
+            Err(err) => return kpassport_error(err),
+        };
+
+        if let Some(input) = &kong.input {
+            let input = match InviteCreationInput::from_json_str(input.to_string()) {
+                Ok(input) => input,
+                Err(_) => return ErrorResponse::bad_request(),
+            };
+
+            let invite = Invite {
+                code: Invite::generate_code(),
+                email: input.email,
+                max_uses: input.max_uses,
+                uses: 0,
+                expires: input.expires,
+                created_by,
+                created: Utc::now(),
+            };
+
+            match self.database.lock().unwrap().create_invite(&invite) {
+                Ok(_) => server::Response::json(&invite).with_status_code(201),
+                Err(err) => match err {
+                    KontrollerError::DbField => ErrorResponse::bad_request(),
+                    _ => ErrorResponse::internal(),
+                },
+            }
+        } else {
+            ErrorResponse::bad_request()
+        }
+    }
+}
+
+/// ## 📋 Get all invites kontroller
+pub struct GetInvitesKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for GetInvitesKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get all invites
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Some(k) = &kong.kpassport {
+            match is_admin(k, self.database.clone()) {
+                Ok(true) => match self.database.lock().unwrap().get_invites() {
+                    Ok(invites) => server::Response::json(&invites).with_status_code(200),
+                    Err(_) => ErrorResponse::internal(),
+                },
+                Ok(false) => ErrorResponse::unauthorized(),
+                Err(err) => kpassport_error(err),
+            }
+        } else {
+            ErrorResponse::unauthorized()
+        }
+    }
+}
+
+/// ## 🗑️ Revoke invite kontroller
+///
+/// Deletes the invite whose code is in the `code` url parameter.
+pub struct RevokeInviteKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for RevokeInviteKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Delete invite
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Some(k) = &kong.kpassport {
+            match is_admin(k, self.database.clone()) {
+                Ok(true) => (),
+                Ok(false) => return ErrorResponse::unauthorized(),
+                Err(err) => return kpassport_error(err),
+            }
+
+            if let Some(url_params) = &kong.url_parameters {
+                if let Some(code) = url_params.find("code") {
+                    match self.database.lock().unwrap().delete_invite(code) {
+                        Ok(true) => {
+                            server::Response::text("Invite has been revoked.").with_status_code(200)
+                        }
+                        Ok(false) => ErrorResponse::not_found(),
+                        Err(_) => ErrorResponse::internal(),
+                    }
+                } else {
+                    ErrorResponse::bad_request()
+                }
+            } else {
+                ErrorResponse::bad_request()
+            }
+        } else {
+            ErrorResponse::unauthorized()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::accounts::{inputs::AccountCreationInput, Account};
+    use crate::test_utils::TempDatabase;
+    use chrono::Duration;
+
+    fn account(username: &str, email: Option<&str>) -> Account {
+        AccountCreationInput {
+            username: username.to_string(),
+            password: "correct horse battery".to_string(),
+            email: email.map(str::to_string),
+            invite: None,
+        }
+        .into()
+    }
+
+    fn invite(code: &str) -> Invite {
+        Invite {
+            code: code.to_string(),
+            email: None,
+            max_uses: None,
+            uses: 0,
+            expires: None,
+            created_by: "admin".to_string(),
+            created: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_invite_use() {
+        let db_file = TempDatabase::new("ACCOUNTS_INVITE_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+
+        // Invites are used up
+        db.create_invite(&Invite {
+            max_uses: Some(1),
+            ..invite("once")
+        })
+        .unwrap();
+        db.create_account_with_invite(&account("alice", None), "once")
+            .unwrap();
+        assert!(matches!(
+            db.create_account_with_invite(&account("bob", None), "once"),
+            Err(KontrollerError::Invite)
+        ));
+
+        // Expired invites can not be used
+        db.create_invite(&Invite {
+            expires: Some(Utc::now() - Duration::hours(1)),
+            ..invite("expired")
+        })
+        .unwrap();
+        assert!(matches!(
+            db.create_account_with_invite(&account("carol", None), "expired"),
+            Err(KontrollerError::Invite)
+        ));
+
+        // Invites for an email can only be used with that email
+        db.create_invite(&Invite {
+            email: Some("dave@example.com".to_string()),
+            ..invite("dave")
+        })
+        .unwrap();
+        assert!(matches!(
+            db.create_account_with_invite(&account("eve", Some("eve@example.com")), "dave"),
+            Err(KontrollerError::Invite)
+        ));
+        assert!(matches!(
+            db.create_account_with_invite(&account("eve", None), "dave"),
+            Err(KontrollerError::Invite)
+        ));
+        db.create_account_with_invite(&account("dave", Some("dave@example.com")), "dave")
+            .unwrap();
+
+        // Unknown invites can not be used
+        assert!(matches!(
+            db.create_account_with_invite(&account("frank", None), "unknown"),
+            Err(KontrollerError::Invite)
+        ));
+
+        // Only the accounts with a valid invite are created
+        for username in ["alice", "dave"] {
+            assert!(db
+                .private_get_account_by_username(username)
+                .unwrap()
+                .is_some());
+        }
+        for username in ["bob", "carol", "eve", "frank"] {
+            assert!(db
+                .private_get_account_by_username(username)
+                .unwrap()
+                .is_none());
+        }
+
+        let uses: Vec<(String, i64)> = db
+            .get_invites()
+            .unwrap()
+            .into_iter()
+            .map(|invite| (invite.code, invite.uses))
+            .collect();
+        assert!(uses.contains(&("once".to_string(), 1)));
+        assert!(uses.contains(&("expired".to_string(), 0)));
+        assert!(uses.contains(&("dave".to_string(), 1)));
+    }
+}
@@ -4,14 +4,38 @@
 //! It supports the following functions:
 //!
 //! - account creation
+//! - password policy, password changes and password resets, see
+//!   [`policy`]
+//! - public profiles and avatar uploads (avatars require the `avatar`
+//!   feature)
+//! - registration modes, see [`RegistrationMode`]
+//! - invite codes and the pending approval queue (requires the
+//!   `login` feature)
+//! - promoting and demoting admin accounts (requires the `login`
+//!   feature)
+//! - audit log of sensitive account actions, eg impersonation
 //! - ...
 //!
 //! > To use the accounts kontroller, enable it with the `accounts` Cargo
 //! > feature.
 
+#[cfg(feature = "avatar")]
+pub mod avatar;
 pub mod create;
 pub mod database;
 pub mod inputs;
+#[cfg(feature = "login")]
+pub mod invite;
+#[cfg(feature = "login")]
+pub mod password;
+pub mod policy;
+pub mod profile;
+#[cfg(feature = "login")]
+pub mod registration;
+#[cfg(feature = "login")]
+pub mod reset;
+#[cfg(feature = "login")]
+pub mod role;
 
 use chrono::prelude::*;
 use inputs::AccountCreationInput;
@@ -62,6 +86,8 @@ pub struct Account {
     pub last_login: Option<DateTime<Utc>>,
     /// Type of account, eg `admin`
     pub account_type: Option<String>,
+    /// URL of the account's avatar image
+    pub avatar: Option<String>,
 }
 
 impl From<AccountCreationInput> for Account {
@@ -85,6 +111,7 @@ impl From<AccountCreationInput> for Account {
             description: None,
             last_login: None,
             account_type: None,
+            avatar: None,
         }
     }
 }
@@ -97,6 +124,8 @@ impl From<AccountCreationInput> for Account {
 pub struct PublicAccount {
     /// The username of the user, also used as an unique identifier
     pub username: String,
+    /// URL of the account's avatar image
+    pub avatar: Option<String>,
 }
 
 impl PublicAccount {
@@ -104,6 +133,7 @@ impl PublicAccount {
     pub fn as_json(&self) -> JsonValue {
         json!({
             "username": self.username,
+            "avatar": self.avatar,
         })
     }
 }
@@ -111,6 +141,191 @@ impl From<Account> for PublicAccount {
     fn from(account: Account) -> Self {
         PublicAccount {
             username: account.username,
+            avatar: account.avatar,
+        }
+    }
+}
+
+/// ## 📜 Audit log entry
+///
+/// A record of a sensitive action performed by an account, such as an
+/// admin impersonating another account.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct AuditEntry {
+    /// Username of the account that performed the action
+    pub actor: String,
+    /// The action that was performed, eg `impersonation_start`
+    pub action: String,
+    /// Username of the account the action was performed on
+    pub subject: Option<String>,
+    /// Date when the action was performed
+    pub date: DateTime<Utc>,
+}
+
+/// Separates the impersonated username from the impersonation token in
+/// the subject of an impersonation `kpassport`, new usernames can not
+/// contain it. Accounts created before keep their username.
+pub const IMPERSONATION_SEPARATOR: char = '#';
+
+/// ## 🎭 Account impersonation
+///
+/// An admin acting as another account for a limited time.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct Impersonation {
+    /// Random token the impersonation `kpassport` is issued for, it is
+    /// never sent in responses
+    #[serde(skip)]
+    pub token: String,
+    /// Username of the admin doing the impersonation
+    pub admin: String,
+    /// Username of the impersonated account
+    pub target: String,
+    /// Date when the impersonation started
+    pub started: DateTime<Utc>,
+    /// Date when the impersonation expires
+    pub expires: DateTime<Utc>,
+    /// Date when the impersonation was ended by the admin
+    pub ended: Option<DateTime<Utc>>,
+}
+
+/// ## 🚪 Registration mode
+///
+/// Controls who can create an account with the
+/// [`create::CreateAccountKontroller`]. The account using the admin
+/// email from the `konfig` file can always be created.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Copy, Default)]
+pub enum RegistrationMode {
+    /// Anyone can create an account
+    #[default]
+    Open,
+    /// An account can only be created with a valid invite code
+    InviteOnly,
+    /// Accounts are queued until an admin approves them
+    AdminApproval,
+    /// No new accounts can be created
+    Closed,
+}
+
+/// ## 🎟️ Registration invite
+///
+/// An invite code created by an admin, used to create accounts when
+/// the registration mode is [`RegistrationMode::InviteOnly`].
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct Invite {
+    /// The invite code
+    pub code: String,
+    /// If set, the invite can only be used with this email address
+    pub email: Option<String>,
+    /// How many accounts can be created with the invite, unlimited if `None`
+    pub max_uses: Option<i64>,
+    /// How many accounts have been created with the invite
+    pub uses: i64,
+    /// Date when the invite expires, never if `None`
+    pub expires: Option<DateTime<Utc>>,
+    /// Username of the admin that created the invite
+    pub created_by: String,
+    /// Date when the invite was created
+    pub created: DateTime<Utc>,
+}
+
+impl Invite {
+    /// Length of generated invite codes
+    pub const CODE_LENGTH: usize = 16;
+
+    /// Generate a new random invite code
+    pub fn generate_code() -> String {
+        use rand::{distributions::Alphanumeric, Rng};
+
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(Self::CODE_LENGTH)
+            .map(char::from)
+            .collect()
+    }
+}
+
+/// ## 🗝️ Password reset
+///
+/// A single use code created by an admin, used by the account holder
+/// to set a new password without the current one.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct PasswordReset {
+    /// The reset code
+    pub code: String,
+    /// Username of the account whose password is reset
+    pub username: String,
+    /// Date when the code expires
+    pub expires: DateTime<Utc>,
+    /// Username of the admin that created the code
+    pub created_by: String,
+    /// Date when the code was created
+    pub created: DateTime<Utc>,
+}
+
+impl PasswordReset {
+    /// Length of generated reset codes
+    pub const CODE_LENGTH: usize = 32;
+
+    /// Generate a new random reset code
+    pub fn generate_code() -> String {
+        use rand::{distributions::Alphanumeric, Rng};
+
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(Self::CODE_LENGTH)
+            .map(char::from)
+            .collect()
+    }
+}
+
+/// ## ⏳ Registration request
+///
+/// An account waiting for admin approval, when the registration mode
+/// is [`RegistrationMode::AdminApproval`].
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct RegistrationRequest {
+    /// Requested username
+    pub username: String,
+    /// Requested email address
+    pub email: Option<String>,
+    /// Hash of the account's master key
+    #[serde(skip_serializing)]
+    pub password: String,
+    /// Date when the registration was requested
+    pub created: DateTime<Utc>,
+}
+
+impl From<Account> for RegistrationRequest {
+    fn from(account: Account) -> Self {
+        RegistrationRequest {
+            username: account.username,
+            email: account.email,
+            password: account.password,
+            created: account.created,
+        }
+    }
+}
+
+impl From<RegistrationRequest> for Account {
+    fn from(request: RegistrationRequest) -> Account {
+        Account {
+            username: request.username,
+            password: request.password,
+            created: request.created,
+            fullname: None,
+            date_of_birth: None,
+            id_number: None,
+            gender: None,
+            current_school_name: None,
+            student_number: None,
+            bussiness_name: None,
+            email: request.email,
+            mobile_number: None,
+            website: None,
+            description: None,
+            last_login: None,
+            account_type: None,
+            avatar: None,
         }
     }
 }
@@ -0,0 +1,142 @@
+//! # 🔑 Password change kontroller
+//!
+//! Allows a logged in account to change its password, by providing
+//! its current password and a new one. The new password must follow
+//! the kontroller's [`PasswordPolicy`].
+//!
+//! > Admins impersonating an account can not change its password.
+
+use super::database::Database;
+use super::inputs::PasswordChangeInput;
+use super::policy::PasswordPolicy;
+use crate::login::{kpassport_error, real_username, session_username};
+use kong::{inputs::UserInput, krypto, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ## 🔑 Password change kontroller
+pub struct ChangePasswordKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Rules the new password must follow
+    pub password_policy: PasswordPolicy,
+}
+
+impl Kontrol for ChangePasswordKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get user input from HTTP request
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<PasswordChangeInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        if let Ok(input) = input {
+            Some(input.as_json())
+        } else {
+            None
+        }
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        if let Some(input) = input {
+            match PasswordChangeInput::from_json_str(input.to_string()) {
+                Ok(input) => {
+                    if input.is_valid().is_ok() {
+                        Ok(Some(input.as_json()))
+                    } else {
+                        Err(())
+                    }
+                }
+                Err(_) => Err(()),
+            }
+        } else {
+            Err(())
+        }
+    }
+
+    /// Change password
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let k = if let Some(k) = &kong.kpassport {
+            k
+        } else {
+            return ErrorResponse::unauthorized();
+        };
+
+        // Only the account holder can change the password
+        let username = match session_username(k, self.database.clone()) {
+            Ok(username) => username,
+            Err(err) => return kpassport_error(err),
+        };
+        match real_username(k, self.database.clone()) {
+            Ok(real_username) if real_username == username => (),
+            Ok(_) => return ErrorResponse::unauthorized(),
+            Err(err) => return kpassport_error(err),
+        }
+
+        let input = if let Some(input) = &kong.input {
+            match PasswordChangeInput::from_json_str(input.to_string()) {
+                Ok(input) => input,
+                Err(_) => return ErrorResponse::bad_request(),
+            }
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        let account = match self
+            .database
+            .lock()
+            .unwrap()
+            .private_get_account_by_username(&username)
+        {
+            Ok(Some(account)) => account,
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        };
+
+        // Verify current password
+        match krypto::password::verify(&account.password, &input.password) {
+            Ok(true) => (),
+            Ok(false) => return ErrorResponse::unauthorized(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        if self
+            .password_policy
+            .check(
+                &input.new_password,
+                &account.username,
+                account.email.as_deref(),
+            )
+            .is_err()
+        {
+            return ErrorResponse::bad_request();
+        }
+
+        let hash = match krypto::password::hash(&input.new_password) {
+            Ok(hash) => hash,
+            Err(_) => return ErrorResponse::internal(),
+        };
+
+        match self
+            .database
+            .lock()
+            .unwrap()
+            .update_password(&username, &hash)
+        {
+            Ok(()) => server::Response::text("Password has been changed.").with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! # 🔐 Password policy
+//!
+//! A configurable set of rules new passwords must follow. The policy
+//! is checked when an account is created, when a password is changed
+//! and when it is reset, see [`super::reset`], it is checked in
+//! addition to kong's own password validation.
+//!
+//! A list of common passwords can be loaded from a local file, with
+//! one password per line. Passwords from the list are rejected.
+//!
+//! ## ♻️ Hash upgrades
+//! [`needs_rehash`] compares the parameters of a stored hash with the
+//! ones kong currently hashes with, so hashes can be upgraded when an
+//! account logs in.
+
+use kong::krypto;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// ## 🔐 Password policy
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// Minimum number of characters
+    pub min_length: usize,
+    /// Require at least one lowercase letter
+    pub require_lowercase: bool,
+    /// Require at least one uppercase letter
+    pub require_uppercase: bool,
+    /// Require at least one digit
+    pub require_digit: bool,
+    /// Require at least one character that is not a letter or digit
+    pub require_symbol: bool,
+    /// Reject passwords containing the account's username or email
+    pub reject_personal_info: bool,
+    /// Lowercased common passwords that are rejected
+    pub common_passwords: HashSet<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 8,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            reject_personal_info: true,
+            common_passwords: HashSet::new(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Load the common password list from a file, one password per line
+    pub fn with_common_passwords_file(mut self, path: &str) -> std::io::Result<Self> {
+        let list = std::fs::read_to_string(path)?;
+
+        self.common_passwords = list
+            .lines()
+            .map(|line| line.trim().to_lowercase())
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        Ok(self)
+    }
+
+    /// Check if a password follows the policy
+    pub fn check(
+        &self,
+        password: &str,
+        username: &str,
+        email: Option<&str>,
+    ) -> Result<(), PasswordPolicyError> {
+        if password.chars().count() < self.min_length {
+            return Err(PasswordPolicyError::TooShort);
+        }
+
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            return Err(PasswordPolicyError::MissingLowercase);
+        }
+
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            return Err(PasswordPolicyError::MissingUppercase);
+        }
+
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            return Err(PasswordPolicyError::MissingDigit);
+        }
+
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            return Err(PasswordPolicyError::MissingSymbol);
+        }
+
+        let lowercase = password.to_lowercase();
+
+        if self.common_passwords.contains(&lowercase) {
+            return Err(PasswordPolicyError::Common);
+        }
+
+        if self.reject_personal_info {
+            let username = username.to_lowercase();
+            if !username.is_empty() && lowercase.contains(&username) {
+                return Err(PasswordPolicyError::PersonalInfo);
+            }
+
+            if let Some(email) = email {
+                let email = email.to_lowercase();
+                // check both the whole address and the part before the `@`
+                let local_part = email.split('@').next().unwrap_or_default();
+
+                if lowercase.contains(&email)
+                    || (!local_part.is_empty() && lowercase.contains(local_part))
+                {
+                    return Err(PasswordPolicyError::PersonalInfo);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## 🚨 Password policy errors
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum PasswordPolicyError {
+    /// Password is shorter than the minimum length
+    TooShort,
+    /// Password has no lowercase letter
+    MissingLowercase,
+    /// Password has no uppercase letter
+    MissingUppercase,
+    /// Password has no digit
+    MissingDigit,
+    /// Password has no symbol
+    MissingSymbol,
+    /// Password is in the common password list
+    Common,
+    /// Password contains the account's username or email
+    PersonalInfo,
+}
+
+impl std::error::Error for PasswordPolicyError {}
+
+impl fmt::Display for PasswordPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort => write!(f, "Password is too short"),
+            Self::MissingLowercase => write!(f, "Password should contain a lowercase letter"),
+            Self::MissingUppercase => write!(f, "Password should contain an uppercase letter"),
+            Self::MissingDigit => write!(f, "Password should contain a digit"),
+            Self::MissingSymbol => write!(f, "Password should contain a symbol"),
+            Self::Common => write!(f, "Password is too common"),
+            Self::PersonalInfo => write!(f, "Password should not contain the username or email"),
+        }
+    }
+}
+
+/// Get the parameters of a PHC formatted hash, eg
+/// `$argon2id$v=19$m=4096,t=3,p=1` from
+/// `$argon2id$v=19$m=4096,t=3,p=1$<salt>$<hash>`
+fn hash_parameters(hash: &str) -> Option<&str> {
+    let mut parts = hash.rsplitn(3, '$');
+    let _hash = parts.next()?;
+    let _salt = parts.next()?;
+    parts.next().filter(|parameters| !parameters.is_empty())
+}
+
+/// Check if a stored password hash was created with different hashing
+/// parameters than the ones currently used
+pub fn needs_rehash(hash: &str) -> bool {
+    static CURRENT: OnceLock<Option<String>> = OnceLock::new();
+
+    let current = CURRENT.get_or_init(|| {
+        krypto::password::hash("parameters")
+            .ok()
+            .and_then(|hash| hash_parameters(&hash).map(String::from))
+    });
+
+    match (current, hash_parameters(hash)) {
+        (Some(current), Some(stored)) => current != stored,
+        // Unknown hash format, leave it as it is
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_password_policy() {
+        let policy = PasswordPolicy {
+            require_uppercase: true,
+            require_digit: true,
+            common_passwords: ["Password123".to_lowercase()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.check("short", "john", None),
+            Err(PasswordPolicyError::TooShort)
+        );
+        assert_eq!(
+            policy.check("longpassword1", "john", None),
+            Err(PasswordPolicyError::MissingUppercase)
+        );
+        assert_eq!(
+            policy.check("PASSWORD123", "john", None),
+            Err(PasswordPolicyError::Common)
+        );
+        assert_eq!(
+            policy.check("Xjohnx12345", "john", None),
+            Err(PasswordPolicyError::PersonalInfo)
+        );
+        assert_eq!(
+            policy.check("Xmail12345x", "john", Some("mail@example.com")),
+            Err(PasswordPolicyError::PersonalInfo)
+        );
+        assert_eq!(policy.check("Correct1Horse", "john", None), Ok(()));
+    }
+
+    #[test]
+    fn test_hash_parameters() {
+        assert_eq!(
+            hash_parameters("$argon2id$v=19$m=4096,t=3,p=1$c2FsdA$aGFzaA"),
+            Some("$argon2id$v=19$m=4096,t=3,p=1")
+        );
+        assert_eq!(hash_parameters("plainhash"), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! # 🥸 Public profile kontroller
+//!
+//! Gets the [`PublicAccount`] of the account whose username is in the
+//! `username` url parameter. Only public data is returned, such as
+//! the account's avatar.
+//!
+//! With the `sitemap` feature, the public profiles can be listed in the
+//! sitemap with a [`ProfileSitemap`].
+
+use super::database::Database;
+use super::PublicAccount;
+#[cfg(feature = "sitemap")]
+use crate::{
+    error::KontrollerError,
+    sitemap::{SitemapSource, SitemapUrl},
+};
+use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ## 🥸 Public profile kontroller
+pub struct GetPublicProfileKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for GetPublicProfileKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get public profile
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Some(url_params) = &kong.url_parameters {
+            if let Some(username) = url_params.find("username") {
+                let res: Result<Option<PublicAccount>, _> = self
+                    .database
+                    .lock()
+                    .unwrap()
+                    .public_get_account_by_username(username);
+
+                match res {
+                    Ok(Some(profile)) => server::Response::json(&profile).with_status_code(200),
+                    Ok(None) => ErrorResponse::not_found(),
+                    Err(_) => ErrorResponse::internal(),
+                }
+            } else {
+                ErrorResponse::bad_request()
+            }
+        } else {
+            ErrorResponse::bad_request()
+        }
+    }
+}
+
+/// ## 🗺️ Public profiles sitemap source
+#[cfg(feature = "sitemap")]
+pub struct ProfileSitemap {
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// URL path usernames are appended to, eg `/profile/`
+    pub profile_prefix: String,
+}
+
+#[cfg(feature = "sitemap")]
+impl SitemapSource for ProfileSitemap {
+    /// The public profiles of all accounts
+    fn sitemap_urls(&self) -> Result<Vec<SitemapUrl>, KontrollerError> {
+        let usernames = self.database.lock().unwrap().get_usernames()?;
+
+        Ok(usernames
+            .into_iter()
+            .map(|username| SitemapUrl {
+                path: format!("{}{username}", self.profile_prefix),
+                lastmod: None,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::accounts::{inputs::AccountCreationInput, Account};
+    use crate::test_utils::TempDatabase;
+
+    #[test]
+    fn test_public_account() {
+        let db_file = TempDatabase::new("ACCOUNTS_PUBLIC_ACCOUNT_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+
+        let account: Account = AccountCreationInput {
+            username: "alice".to_string(),
+            password: "correct horse battery".to_string(),
+            email: Some("alice@example.com".to_string()),
+            invite: None,
+        }
+        .into();
+        db.create_account(&account).unwrap();
+
+        // The username is read, never the password hash
+        let by_username = db.public_get_account_by_username("alice").unwrap().unwrap();
+        assert_eq!(by_username.username, "alice");
+        let by_email = db
+            .public_get_account_by_email("alice@example.com")
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_email.username, "alice");
+    }
+}
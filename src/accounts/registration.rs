@@ -0,0 +1,228 @@
+//! # ⏳ Registration approval kontrollers
+//!
+//! When registration is [`super::RegistrationMode::AdminApproval`],
+//! new accounts wait in a queue. These __kontrollers__ allow admin
+//! accounts to list the queue and accept or reject the requests.
+//! Every decision is written to the audit log.
+
+use super::database::Database;
+use super::{AuditEntry, PublicAccount};
+use crate::error::KontrollerError;
+use crate::login::admin_username;
+use chrono::Utc;
+use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// Write a registration decision to the audit log
+fn audit(
+    db: &Database,
+    admin: String,
+    action: &str,
+    username: &str,
+) -> Result<(), KontrollerError> {
+    db.create_audit_entry(&AuditEntry {
+        actor: admin,
+        action: action.to_string(),
+        subject: Some(username.to_string()),
+        date: Utc::now(),
+    })
+}
+
+/// ## 📋 Get pending registrations kontroller
+pub struct GetRegistrationRequestsKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for GetRegistrationRequestsKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get pending registrations
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.database.clone()) {
+            return response;
+        }
+
+        match self.database.lock().unwrap().get_registration_requests() {
+            Ok(requests) => server::Response::json(&requests).with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## ✅ Approve registration kontroller
+///
+/// Creates the account requested by the `username` url parameter.
+pub struct ApproveRegistrationKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for ApproveRegistrationKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Approve registration
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let admin = match admin_username(kong, self.database.clone()) {
+            Ok(admin) => admin,
+            Err(response) => return response,
+        };
+
+        if let Some(url_params) = &kong.url_parameters {
+            if let Some(username) = url_params.find("username") {
+                let mut db = self.database.lock().unwrap();
+
+                match db.approve_registration_request(username) {
+                    Ok(Some(account)) => {
+                        if audit(&db, admin, "registration_approve", username).is_err() {
+                            return ErrorResponse::internal();
+                        }
+
+                        let public_account: PublicAccount = account.into();
+                        server::Response::json(&public_account).with_status_code(201)
+                    }
+                    Ok(None) => ErrorResponse::not_found(),
+                    Err(err) => match err {
+                        KontrollerError::DbField => ErrorResponse::bad_request(),
+                        _ => ErrorResponse::internal(),
+                    },
+                }
+            } else {
+                ErrorResponse::bad_request()
+            }
+        } else {
+            ErrorResponse::bad_request()
+        }
+    }
+}
+
+/// ## ❌ Reject registration kontroller
+///
+/// Removes the request of the `username` url parameter from the queue.
+pub struct RejectRegistrationKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for RejectRegistrationKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Reject registration
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let admin = match admin_username(kong, self.database.clone()) {
+            Ok(admin) => admin,
+            Err(response) => return response,
+        };
+
+        if let Some(url_params) = &kong.url_parameters {
+            if let Some(username) = url_params.find("username") {
+                let db = self.database.lock().unwrap();
+
+                match db.reject_registration_request(username) {
+                    Ok(true) => {
+                        if audit(&db, admin, "registration_reject", username).is_err() {
+                            return ErrorResponse::internal();
+                        }
+
+                        server::Response::text("Registration has been rejected.")
+                            .with_status_code(200)
+                    }
+                    Ok(false) => ErrorResponse::not_found(),
+                    Err(_) => ErrorResponse::internal(),
+                }
+            } else {
+                ErrorResponse::bad_request()
+            }
+        } else {
+            ErrorResponse::bad_request()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::accounts::{inputs::AccountCreationInput, Account, RegistrationRequest};
+    use crate::test_utils::TempDatabase;
+    use kong::krypto;
+
+    fn request(username: &str) -> RegistrationRequest {
+        let account: Account = AccountCreationInput {
+            username: username.to_string(),
+            password: "correct horse battery".to_string(),
+            email: Some(format!("{username}@example.com")),
+            invite: None,
+        }
+        .into();
+        account.into()
+    }
+
+    #[test]
+    fn test_registration_requests() {
+        let db_file = TempDatabase::new("ACCOUNTS_REGISTRATION_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+
+        let alice = request("alice");
+        db.create_registration_request(&alice).unwrap();
+        db.create_registration_request(&request("bob")).unwrap();
+        assert_eq!(db.get_registration_requests().unwrap().len(), 2);
+
+        // Approving creates the account with the hashed password
+        let approved = db.approve_registration_request("alice").unwrap().unwrap();
+        assert_eq!(approved.username, "alice");
+        let account = db
+            .private_get_account_by_username("alice")
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.password, alice.password);
+        assert_ne!(account.password, "correct horse battery");
+        assert!(krypto::password::verify(&account.password, "correct horse battery").unwrap());
+        assert_eq!(account.email.as_deref(), Some("alice@example.com"));
+
+        // and removes the request
+        assert!(db.approve_registration_request("alice").unwrap().is_none());
+
+        // Rejecting removes the request without creating the account
+        assert!(db.reject_registration_request("bob").unwrap());
+        assert!(!db.reject_registration_request("bob").unwrap());
+        assert!(db.private_get_account_by_username("bob").unwrap().is_none());
+        assert!(db.get_registration_requests().unwrap().is_empty());
+    }
+}
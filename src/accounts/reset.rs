@@ -0,0 +1,254 @@
+//! # 🗝️ Password reset kontrollers
+//!
+//! These __kontrollers__ allow an account holder that lost their
+//! password to set a new one. An admin creates a reset code for the
+//! account, taken from the `username` url parameter, and hands it to
+//! the account holder, who sets a new password with it. Reset codes
+//! are not sent by email, delivering them is up to the app.
+//!
+//! A code expires after the kontroller's validity period and can only
+//! be used once, setting a new password uses up all the codes of the
+//! account. The new password must follow the kontroller's
+//! [`PasswordPolicy`]. Creating a code and resetting a password are
+//! written to the audit log.
+
+use super::database::Database;
+use super::inputs::PasswordResetInput;
+use super::policy::PasswordPolicy;
+use super::{AuditEntry, PasswordReset};
+use crate::error::KontrollerError;
+use crate::login::admin_username;
+use chrono::{Duration, Utc};
+use kong::{inputs::UserInput, krypto, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ## 🗝️ Password reset creation kontroller
+pub struct CreatePasswordResetKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// How long reset codes can be used
+    pub validity: Duration,
+}
+
+impl Kontrol for CreatePasswordResetKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Create a reset code
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let admin = match admin_username(kong, self.database.clone()) {
+            Ok(admin) => admin,
+            Err(response) => return response,
+        };
+
+        let username = match kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("username"))
+        {
+            Some(username) => username.to_string(),
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let db = self.database.lock().unwrap();
+
+        match db.private_get_account_by_username(&username) {
+            Ok(Some(_)) => (),
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        let created = Utc::now();
+        let reset = PasswordReset {
+            code: PasswordReset::generate_code(),
+            username: username.clone(),
+            expires: created + self.validity,
+            created_by: admin.clone(),
+            created,
+        };
+
+        let entry = AuditEntry {
+            actor: admin,
+            action: "password_reset_create".to_string(),
+            subject: Some(username),
+            date: created,
+        };
+
+        if db.create_password_reset(&reset).is_err() || db.create_audit_entry(&entry).is_err() {
+            return ErrorResponse::internal();
+        }
+
+        server::Response::json(&reset).with_status_code(201)
+    }
+}
+
+/// ## 🗝️ Password reset kontroller
+pub struct ResetPasswordKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Rules the new password must follow
+    pub password_policy: PasswordPolicy,
+}
+
+impl Kontrol for ResetPasswordKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get user input from HTTP request
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<PasswordResetInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        if let Ok(input) = input {
+            Some(input.as_json())
+        } else {
+            None
+        }
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        if let Some(input) = input {
+            match PasswordResetInput::from_json_str(input.to_string()) {
+                Ok(input) => {
+                    if input.is_valid().is_ok() {
+                        Ok(Some(input.as_json()))
+                    } else {
+                        Err(())
+                    }
+                }
+                Err(_) => Err(()),
+            }
+        } else {
+            Err(())
+        }
+    }
+
+    /// Reset password
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let input = if let Some(input) = &kong.input {
+            match PasswordResetInput::from_json_str(input.to_string()) {
+                Ok(input) => input,
+                Err(_) => return ErrorResponse::bad_request(),
+            }
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        let now = Utc::now();
+        let mut db = self.database.lock().unwrap();
+
+        let reset = match db.get_password_reset(&input.code, now) {
+            Ok(Some(reset)) => reset,
+            Ok(None) => return ErrorResponse::unauthorized(),
+            Err(_) => return ErrorResponse::internal(),
+        };
+
+        let account = match db.private_get_account_by_username(&reset.username) {
+            Ok(Some(account)) => account,
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        };
+
+        if self
+            .password_policy
+            .check(
+                &input.new_password,
+                &account.username,
+                account.email.as_deref(),
+            )
+            .is_err()
+        {
+            return ErrorResponse::bad_request();
+        }
+
+        let hash = match krypto::password::hash(&input.new_password) {
+            Ok(hash) => hash,
+            Err(_) => return ErrorResponse::internal(),
+        };
+
+        match db.reset_password(&input.code, &hash, now) {
+            Ok(()) => (),
+            Err(KontrollerError::PasswordReset) => return ErrorResponse::unauthorized(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        let entry = AuditEntry {
+            actor: account.username.clone(),
+            action: "password_reset".to_string(),
+            subject: Some(account.username),
+            date: now,
+        };
+
+        match db.create_audit_entry(&entry) {
+            Ok(()) => server::Response::text("Password has been reset.").with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDatabase;
+
+    #[test]
+    fn test_password_reset() {
+        let db_file = TempDatabase::new("ACCOUNTS_RESET_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+
+        let created = Utc::now();
+        let reset = PasswordReset {
+            code: PasswordReset::generate_code(),
+            username: "alice".to_string(),
+            expires: created + Duration::hours(1),
+            created_by: "admin".to_string(),
+            created,
+        };
+        db.create_password_reset(&reset).unwrap();
+        assert_eq!(reset.code.len(), PasswordReset::CODE_LENGTH);
+
+        // Expired and unknown codes can not be used
+        let expired = created + Duration::hours(2);
+        assert_eq!(db.get_password_reset(&reset.code, expired).unwrap(), None);
+        assert!(matches!(
+            db.reset_password(&reset.code, "hash", expired),
+            Err(KontrollerError::PasswordReset)
+        ));
+        assert!(matches!(
+            db.reset_password("guessed", "hash", created),
+            Err(KontrollerError::PasswordReset)
+        ));
+
+        // Codes are used once
+        assert_eq!(
+            db.get_password_reset(&reset.code, created).unwrap(),
+            Some(reset.clone())
+        );
+        db.reset_password(&reset.code, "hash", created).unwrap();
+        assert_eq!(db.get_password_reset(&reset.code, created).unwrap(), None);
+    }
+}
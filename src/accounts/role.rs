@@ -0,0 +1,139 @@
+//! # 👮‍♂️ Admin role kontrollers
+//!
+//! These __kontrollers__ allow admin accounts to promote other
+//! accounts to admin, or demote them back to normal accounts. The
+//! account is taken from the `username` url parameter.
+//!
+//! The last admin account can not be demoted, so there is always
+//! someone able to manage the site. Every change is written to the
+//! audit log.
+
+use super::database::Database;
+use super::AuditEntry;
+use crate::error::KontrollerError;
+use crate::login::admin_username;
+use chrono::Utc;
+use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ## ⬆️ Promote account kontroller
+pub struct PromoteAdminKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for PromoteAdminKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Make an account an admin
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let admin = match admin_username(kong, self.database.clone()) {
+            Ok(admin) => admin,
+            Err(response) => return response,
+        };
+
+        if let Some(url_params) = &kong.url_parameters {
+            if let Some(username) = url_params.find("username") {
+                let db = self.database.lock().unwrap();
+
+                match db.promote_admin(username) {
+                    Ok(true) => {
+                        let entry = AuditEntry {
+                            actor: admin,
+                            action: "admin_promote".to_string(),
+                            subject: Some(username.to_string()),
+                            date: Utc::now(),
+                        };
+
+                        if db.create_audit_entry(&entry).is_err() {
+                            return ErrorResponse::internal();
+                        }
+
+                        server::Response::text("Account is now an admin.").with_status_code(200)
+                    }
+                    Ok(false) => ErrorResponse::not_found(),
+                    Err(_) => ErrorResponse::internal(),
+                }
+            } else {
+                ErrorResponse::bad_request()
+            }
+        } else {
+            ErrorResponse::bad_request()
+        }
+    }
+}
+
+/// ## ⬇️ Demote account kontroller
+pub struct DemoteAdminKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for DemoteAdminKontroller {
+    /// kontroller address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by kontroller
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Make an admin account a normal account
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let admin = match admin_username(kong, self.database.clone()) {
+            Ok(admin) => admin,
+            Err(response) => return response,
+        };
+
+        if let Some(url_params) = &kong.url_parameters {
+            if let Some(username) = url_params.find("username") {
+                let mut db = self.database.lock().unwrap();
+
+                match db.demote_admin(username) {
+                    Ok(true) => {
+                        let entry = AuditEntry {
+                            actor: admin,
+                            action: "admin_demote".to_string(),
+                            subject: Some(username.to_string()),
+                            date: Utc::now(),
+                        };
+
+                        if db.create_audit_entry(&entry).is_err() {
+                            return ErrorResponse::internal();
+                        }
+
+                        server::Response::text("Account is no longer an admin.")
+                            .with_status_code(200)
+                    }
+                    Ok(false) => ErrorResponse::not_found(),
+                    Err(err) => match err {
+                        KontrollerError::LastAdmin => ErrorResponse::bad_request(),
+                        _ => ErrorResponse::internal(),
+                    },
+                }
+            } else {
+                ErrorResponse::bad_request()
+            }
+        } else {
+            ErrorResponse::bad_request()
+        }
+    }
+}
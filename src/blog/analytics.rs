@@ -0,0 +1,243 @@
+//! # 📈 Blog analytics kontrollers
+//!
+//! The single post kontrollers count the views of the public posts when
+//! they are given a `view_window`, see [`super::get`]. A visitor is
+//! known by a hash of their IP address and user agent, salted with a
+//! random value that changes every time the app starts, so the hash
+//! can not be traced back to the address. The hash is kept for the
+//! length of the window, a visitor reading a post again within the
+//! window is not counted again. Requests without a user agent or from
+//! bots are not counted, see [`is_bot`].
+//!
+//! Only the number of views of every post and day is kept. These
+//! __kontrollers__ allow admin accounts to list the most read public
+//! posts of a period, and the views of every day of a period, of all
+//! the posts or of one post. The period is read from the `from` and
+//! `to` query string parameters, see [`AnalyticsQuery`].
+
+use super::database::Database;
+use super::inputs::{AnalyticsQuery, PostVisit};
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::login::admin_username;
+use chrono::{Duration, Utc};
+use kong::{server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Parts of the user agents of crawlers, link previews, monitoring and
+/// command line tools, in lowercase
+const BOT_USER_AGENTS: &[&str] = &[
+    "bot",
+    "crawl",
+    "spider",
+    "slurp",
+    "facebookexternalhit",
+    "embedly",
+    "preview",
+    "monitor",
+    "headless",
+    "lighthouse",
+    "curl",
+    "wget",
+    "python",
+    "go-http-client",
+    "java/",
+    "okhttp",
+    "libwww",
+];
+
+/// Check if a user agent is a bot's
+pub fn is_bot(user_agent: &str) -> bool {
+    let user_agent = user_agent.to_lowercase();
+
+    user_agent.trim().is_empty() || BOT_USER_AGENTS.iter().any(|bot| user_agent.contains(bot))
+}
+
+/// Random salt of the visitor hashes, for as long as the app runs
+fn salt() -> &'static str {
+    static SALT: OnceLock<String> = OnceLock::new();
+
+    SALT.get_or_init(|| {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect()
+    })
+}
+
+/// Get the visit of the visitor behind a request, `None` for bots
+pub fn post_visit(request: &server::Request) -> Option<PostVisit> {
+    let user_agent = request
+        .header("User-Agent")
+        .filter(|agent| !is_bot(agent))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(salt());
+    hasher.update(request.remote_addr().ip().to_string());
+    hasher.update(user_agent);
+    let visitor = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    Some(PostVisit { visitor })
+}
+
+/// Count the view of a post by the visitor in the input of a request,
+/// if views are counted. Reading the post does not fail with the count.
+pub fn count_view(kong: &Kong, database: &mut Database, post_id: i64, window: Option<Duration>) {
+    let visit = kong
+        .input
+        .as_ref()
+        .and_then(|input| PostVisit::from_json_str(input.to_string()).ok());
+
+    if let (Some(visit), Some(window)) = (visit, window) {
+        let _ = database.record_view(post_id, &visit.visitor, Utc::now(), window);
+    }
+}
+
+/// Get the analytics query from the input of a request
+fn analytics_query(kong: &Kong) -> Option<AnalyticsQuery> {
+    match &kong.input {
+        Some(input) => AnalyticsQuery::from_json_str(input.to_string()).ok(),
+        None => Some(AnalyticsQuery::default()),
+    }
+}
+
+/// Validate the analytics query of a request
+fn validate_query(input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+    match input.map(|input| AnalyticsQuery::from_json_str(input.to_string())) {
+        Some(Ok(query)) if query.is_valid() => Ok(Some(query.as_json())),
+        _ => Err(()),
+    }
+}
+
+/// ## 🏆 Most read blog posts kontroller
+///
+/// Lists the posts with the most views in the period, the number of
+/// posts is given by the `limit` query string parameter.
+pub struct GetTopPostsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for GetTopPostsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        AnalyticsQuery::from_request(request).map(|query| query.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        validate_query(input)
+    }
+
+    /// Get the most read posts
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let query = match analytics_query(kong) {
+            Some(query) => query,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        match self
+            .database
+            .lock()
+            .unwrap()
+            .get_top_posts(&query, Utc::now())
+        {
+            Ok(posts) => server::Response::json(&posts).with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## 📅 Blog views over time kontroller
+///
+/// Lists the views of every day of the period that has views, of all
+/// the posts or of the post given by the `post_id` query string
+/// parameter.
+pub struct GetDailyViewsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for GetDailyViewsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        AnalyticsQuery::from_request(request).map(|query| query.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        validate_query(input)
+    }
+
+    /// Get the views over time
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let query = match analytics_query(kong) {
+            Some(query) => query,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        match self.database.lock().unwrap().get_daily_views(&query) {
+            Ok(days) => server::Response::json(&days).with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_bot() {
+        assert!(is_bot("Mozilla/5.0 (compatible; Googlebot/2.1)"));
+        assert!(is_bot("curl/8.4.0"));
+        assert!(is_bot(""));
+        assert!(!is_bot(
+            "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+//! # ✍️ Blog post authors
+//!
+//! Blog post authors and co-authors are accounts, referenced by their
+//! usernames. Posts read one at a time are served with the public
+//! profiles of their authors, see [`BlogPostWithAuthors`].
+//!
+//! The [`GetAuthorPostsKontroller`] lists the public posts an account
+//! wrote or co-wrote, the account is taken from the `username` url
+//! parameter.
+
+use super::database::Database;
+use super::{BlogPost, BlogPostQuery, BlogPostSummary, DatabaseBlogPostInput};
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::accounts::PublicAccount;
+use crate::error::KontrollerError;
+use chrono::Utc;
+use kong::{server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// ## ✍️ Blog post with the public profiles of its authors
+#[derive(Serialize)]
+pub struct BlogPostWithAuthors {
+    /// The blog post
+    #[serde(flatten)]
+    pub post: BlogPost,
+    /// The public profiles of the author and co-authors, in byline
+    /// order. Authors whose account no longer exists are left out.
+    pub authors: Vec<PublicAccount>,
+}
+
+impl BlogPostWithAuthors {
+    /// Find the public profiles of the authors of a blog post
+    pub fn new(
+        post: BlogPost,
+        accounts_db: Arc<Mutex<AccountsDatabase>>,
+    ) -> Result<Self, KontrollerError> {
+        let accounts_db = accounts_db.lock().unwrap();
+
+        let mut authors = vec![];
+        for username in post.author.iter().chain(&post.co_authors) {
+            if let Some(account) = accounts_db.public_get_account_by_username(username)? {
+                authors.push(account);
+            }
+        }
+
+        Ok(BlogPostWithAuthors { post, authors })
+    }
+}
+
+/// Check if the author and co-authors of a blog post input are all
+/// existing accounts
+pub fn authors_exist(
+    blog: &DatabaseBlogPostInput,
+    accounts_db: Arc<Mutex<AccountsDatabase>>,
+) -> Result<bool, KontrollerError> {
+    usernames_exist(
+        blog.author.iter().chain(blog.co_authors.iter().flatten()),
+        accounts_db,
+    )
+}
+
+/// Check if usernames are all existing accounts
+pub fn usernames_exist<'a>(
+    usernames: impl IntoIterator<Item = &'a String>,
+    accounts_db: Arc<Mutex<AccountsDatabase>>,
+) -> Result<bool, KontrollerError> {
+    let accounts_db = accounts_db.lock().unwrap();
+
+    for username in usernames {
+        if accounts_db
+            .public_get_account_by_username(username)?
+            .is_none()
+        {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// ## 📰 Get blog posts by author kontroller
+///
+/// Lists the public posts of the account in the `username` url
+/// parameter a page at a time, the page, filters and sort order are
+/// read from the query string like in
+/// [`super::get_all::GetAllBlogPostsKontroller`].
+pub struct GetAuthorPostsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for GetAuthorPostsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        BlogPostQuery::from_request(request).map(|query| query.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.map(|input| BlogPostQuery::from_json_str(input.to_string())) {
+            Some(Ok(query)) if query.is_valid() => Ok(Some(query.as_json())),
+            _ => Err(()),
+        }
+    }
+
+    /// Get the posts of an author
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let username = match kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("username"))
+        {
+            Some(username) => username.to_string(),
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let query = match &kong.input {
+            Some(input) => match BlogPostQuery::from_json_str(input.to_string()) {
+                Ok(query) => query,
+                Err(_) => return ErrorResponse::bad_request(),
+            },
+            None => BlogPostQuery::default(),
+        };
+        let query = BlogPostQuery {
+            author: Some(username.clone()),
+            ..query
+        };
+
+        let account = self
+            .accounts_database
+            .lock()
+            .unwrap()
+            .public_get_account_by_username(&username);
+        match account {
+            Ok(Some(_)) => (),
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        match self.database.lock().unwrap().get_page(&query, Utc::now()) {
+            Ok(page) => {
+                if query.summary.unwrap_or(false) {
+                    server::Response::json(&page.map(BlogPostSummary::from)).with_status_code(200)
+                } else {
+                    server::Response::json(&page).with_status_code(200)
+                }
+            }
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! # 🖼️ Blog cover photos
+//!
+//! The cover photos of the blog posts, and of their revisions, are
+//! media references: they are kept in the media storage as long as a
+//! post or a revision uses them, see [`crate::media`]. Covers that are
+//! no longer used when a post is updated or deleted are removed from
+//! the storage, along with their resized variants.
+//!
+//! Uploaded covers are only stored once the kontroller checked the
+//! account sending them, see [`store_cover`].
+
+use super::database::{Cover, Database};
+use crate::error::KontrollerError;
+use crate::media::variants::{store_image, StoredImage, VariantSize};
+use crate::media::{storage::MediaStorage, MediaPolicy, MediaReferences, Upload};
+use kong::{server, ErrorResponse};
+use std::sync::{Arc, Mutex};
+
+/// ## 🖼️ Blog cover photos media references
+pub struct BlogCovers {
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl MediaReferences for BlogCovers {
+    /// The covers of the blog posts and their revisions, with their
+    /// variants
+    fn media_urls(&self) -> Result<Vec<String>, KontrollerError> {
+        let covers = self.database.lock().unwrap().get_covers()?;
+
+        let mut urls = vec![];
+        for (cover, variants) in covers {
+            urls.push(cover);
+            urls.extend(variants.map(|variants| variants.urls()).unwrap_or_default());
+        }
+
+        Ok(urls)
+    }
+}
+
+/// Store an uploaded cover photo without its metadata, along with its
+/// variants, or get the error response to send if it is refused
+pub fn store_cover(
+    storage: &dyn MediaStorage,
+    policy: &MediaPolicy,
+    cover: &Upload,
+    sizes: &[VariantSize],
+) -> Result<StoredImage, server::Response> {
+    store_image(storage, policy, "covers", cover, sizes).map_err(|err| match err {
+        KontrollerError::MediaStorage => ErrorResponse::internal(),
+        _ => ErrorResponse::bad_request(),
+    })
+}
+
+/// Remove the covers that are no longer used by a post or a revision
+/// and their variants from the storage, files that are not in the
+/// storage are ignored
+pub fn remove_unused_covers(
+    database: &Database,
+    storage: &dyn MediaStorage,
+    covers: &[Cover],
+) -> Result<(), KontrollerError> {
+    for (cover, variants) in covers {
+        if database.cover_in_use(cover)? {
+            continue;
+        }
+
+        let variants = variants.as_ref().map(|variants| variants.urls());
+        for url in std::iter::once(cover).chain(variants.iter().flatten()) {
+            if let Some(key) = storage.key(url) {
+                storage.delete(&key)?;
+            }
+        }
+    }
+
+    Ok(())
+}
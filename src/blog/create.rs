@@ -4,12 +4,26 @@
 //! It gets the input from the HTTP request validates it
 //! and creates the blog post from the validated input. The blog post
 //! is stored in an SQLite database.
+//!
+//! Accounts with a blog role can create posts, see [`super::roles`].
+//! The author is the logged in account unless the username of another
+//! account is given, co-authors are a comma separated list of
+//! usernames. Posts created by authors are always drafts.
+//!
+//! The cover photo is checked against the kontroller's `cover_policy`
+//! and stored in its media storage without its metadata, along with
+//! variants of the `cover_sizes`, see [`crate::media::variants`].
 
+use super::authors::authors_exist;
+use super::covers::store_cover;
 use super::database::Database;
-use super::{CreateBlogInput, DatabaseBlogPostInput};
+use super::roles::{blog_role, BlogRole};
+use super::{ContentFormat, CreateBlogInput, DatabaseBlogPostInput, PostStatus};
 use crate::accounts::database::Database as AccountsDatabase;
 use crate::error::KontrollerError;
-use crate::login::is_admin;
+use crate::media::variants::VariantSize;
+use crate::media::{storage::MediaStorage, MediaPolicy};
+use chrono::{DateTime, Utc};
 use kong::{inputs::UserInput, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
 use std::sync::{Arc, Mutex};
 
@@ -23,36 +37,13 @@ pub struct CreateBlogPostKontroller {
     pub database: Arc<Mutex<Database>>,
     /// Accounts database
     pub accounts_database: Arc<Mutex<AccountsDatabase>>,
-}
-
-impl CreateBlogPostKontroller {
-    /// Store uploaded blog photos
-    fn store_cover_photo(
-        dir_name: &str,
-        photo: server::input::post::BufferedFile,
-    ) -> std::io::Result<String> {
-        let directory = format!("./www/uploads/blog_photos/{dir_name}");
-
-        // create upload directory if it does not exist
-        std::fs::create_dir_all(&directory)?;
-
-        let photo_name = if let Some(photo_name) = &photo.filename {
-            photo_name.clone()
-        } else {
-            "".to_string()
-        };
-        let timestamp = chrono::Utc::now().timestamp();
-        let file = format!("{directory}/{timestamp}-{photo_name}");
-
-        // Store photo in directory
-        std::fs::write(&file, &photo.data)?;
-
-        // remeber file path
-        let directory = format!("uploads/blog_photos/{dir_name}");
-        let file = format!("{directory}/{timestamp}-{photo_name}");
-
-        Ok(file)
-    }
+    /// Storage of the cover photos
+    pub media_storage: Arc<dyn MediaStorage>,
+    /// Types and largest size of the cover photos
+    pub cover_policy: MediaPolicy,
+    /// Sizes of the resized variants of the cover photos, see
+    /// [`VariantSize::defaults`]
+    pub cover_sizes: Vec<VariantSize>,
 }
 
 impl Kontrol for CreateBlogPostKontroller {
@@ -74,36 +65,50 @@ impl Kontrol for CreateBlogPostKontroller {
             author: Option<String>,
             cover: Option<server::input::post::BufferedFile>,
             content: String,
+            slug: Option<String>,
+            tags: Option<String>,
+            co_authors: Option<String>,
+            format: Option<String>,
+            status: Option<String>,
+            publish_at: Option<String>,
         }) {
-            // store cover image
-            if let Some(cover) = input.cover {
-                if let Ok(cover) = CreateBlogPostKontroller::store_cover_photo(&input.title, cover)
-                {
-                    let input = CreateBlogInput {
-                        title: input.title,
-                        subtitle: input.subtitle,
-                        overview: input.overview,
-                        author: input.author,
-                        cover: Some(cover),
-                        content: input.content,
-                    };
-
-                    Some(input.as_json())
-                } else {
-                    None
-                }
-            } else {
-                let input = CreateBlogInput {
-                    title: input.title,
-                    subtitle: input.subtitle,
-                    overview: input.overview,
-                    author: input.author,
-                    cover: None,
-                    content: input.content,
-                };
-
-                Some(input.as_json())
-            }
+            // Publishing fields are optional, but should be valid if provided
+            let status = match input.status.as_deref().filter(|s| !s.is_empty()) {
+                Some(status) => Some(PostStatus::from_name(status)?),
+                None => None,
+            };
+            let publish_at = match input.publish_at.as_deref().filter(|s| !s.is_empty()) {
+                Some(publish_at) => Some(
+                    DateTime::parse_from_rfc3339(publish_at)
+                        .ok()?
+                        .with_timezone(&Utc),
+                ),
+                None => None,
+            };
+            let format = match input.format.as_deref().filter(|s| !s.is_empty()) {
+                Some(format) => Some(ContentFormat::from_name(format)?),
+                None => None,
+            };
+
+            // The cover is stored once the account is checked
+            let cover = self.cover_policy.buffer(input.cover).ok()?;
+
+            let input = CreateBlogInput {
+                title: input.title,
+                subtitle: input.subtitle,
+                overview: input.overview,
+                author: input.author,
+                cover,
+                content: input.content,
+                slug: input.slug,
+                status,
+                publish_at,
+                tags: CreateBlogInput::names_from_list(input.tags),
+                co_authors: CreateBlogInput::names_from_list(input.co_authors),
+                format,
+            };
+
+            Some(input.as_json())
         } else {
             None
         }
@@ -113,7 +118,7 @@ impl Kontrol for CreateBlogPostKontroller {
     fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
         if let Some(input) = input {
             if let Ok(input) = CreateBlogInput::from_json_str(input.to_string()) {
-                if input.is_valid().is_ok() {
+                if input.is_valid().is_ok() && input.has_valid_tags() {
                     Ok(Some(input.as_json()))
                 } else {
                     // TODO: proper error handling
@@ -130,40 +135,80 @@ impl Kontrol for CreateBlogPostKontroller {
     }
     /// Add blog
     fn kontrol(&self, kong: &Kong) -> server::Response {
-        if let Some(k) = &kong.kpassport {
-            if let Ok(admin) = is_admin(k, self.accounts_database.clone()) {
-                if admin {
-                    if let Some(input) = &kong.input {
-                        let input = CreateBlogInput::from_json_str(input.to_string());
-
-                        // Derive blog from string
-                        let blog: DatabaseBlogPostInput = if let Ok(input) = input {
-                            input.into()
-                        } else {
-                            return ErrorResponse::bad_request();
-                        };
-
-                        // Store blog into the database
-                        let res = self.database.lock().unwrap().create_blog(&blog);
-
-                        match res {
-                            Ok(()) => server::Response::json(&blog).with_status_code(201),
-                            Err(err) => match err {
-                                KontrollerError::DbField => ErrorResponse::bad_request(),
-                                _ => ErrorResponse::internal(),
-                            },
-                        }
-                    } else {
-                        ErrorResponse::unauthorized()
-                    }
-                } else {
-                    ErrorResponse::unauthorized()
-                }
-            } else {
-                ErrorResponse::internal()
+        let (username, role) = match blog_role(kong, self.accounts_database.clone()) {
+            Ok(account) => account,
+            Err(response) => return response,
+        };
+
+        let input = match &kong.input {
+            Some(input) => CreateBlogInput::from_json_str(input.to_string()),
+            None => return ErrorResponse::bad_request(),
+        };
+
+        // Derive blog from string
+        let (cover, blog): (_, DatabaseBlogPostInput) = if let Ok(mut input) = input {
+            (input.cover.take(), input.into())
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        // The logged in account is the author unless another account
+        // is given, authors can only post as themselves
+        let author = match blog.author.filter(|author| !author.is_empty()) {
+            Some(author) if role == BlogRole::Author && author != username => {
+                return ErrorResponse::unauthorized()
+            }
+            Some(author) => author,
+            None => username,
+        };
+        let blog = DatabaseBlogPostInput {
+            author: Some(author),
+            ..blog
+        };
+
+        // Posts of authors are drafts until an editor publishes them
+        let blog = if role == BlogRole::Author {
+            DatabaseBlogPostInput {
+                status: PostStatus::Draft,
+                date: None,
+                ..blog
             }
         } else {
-            ErrorResponse::unauthorized()
+            blog
+        };
+
+        match authors_exist(&blog, self.accounts_database.clone()) {
+            Ok(true) => (),
+            Ok(false) => return ErrorResponse::bad_request(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        let blog = match cover {
+            Some(cover) => match store_cover(
+                &*self.media_storage,
+                &self.cover_policy,
+                &cover,
+                &self.cover_sizes,
+            ) {
+                Ok(cover) => DatabaseBlogPostInput {
+                    cover: Some(cover.url),
+                    cover_variants: Some(cover.variants),
+                    ..blog
+                },
+                Err(response) => return response,
+            },
+            None => blog,
+        };
+
+        // Store blog into the database
+        let res = self.database.lock().unwrap().create_blog(&blog);
+
+        match res {
+            Ok(post) => server::Response::json(&post).with_status_code(201),
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
         }
     }
 }
@@ -1,8 +1,23 @@
 //! # 🗄️ Blog posts database management
 
-use super::{BlogPost, DatabaseBlogPostInput};
+use super::inputs::{MediaFileInput, PatchBlogInput, UpdateMediaInput};
+use super::reading::ContentStats;
+use super::render::{render, sanitize};
+use super::search::{mark_html, MARK_END, MARK_START};
+use super::slug::{has_slug_characters, slugify, with_suffix};
+use super::{
+    AnalyticsQuery, BlogPost, BlogPostPage, BlogPostQuery, BlogPostRevision,
+    BlogPostRevisionSummary, BlogPostSummary, ContentFormat, DailyViews, DatabaseBlogPostInput,
+    MediaFile, MediaUsage, PostSort, PostStatus, PostViews, SearchQuery, SearchResult, Tag,
+    TagCount, TagKind,
+};
 use crate::error::KontrollerError;
-use rusqlite::{params, Connection};
+use crate::media::variants::ImageVariants;
+use crate::migration;
+use chrono::{DateTime, Duration, Utc};
+use kong::{json, json_from_str};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Type, ValueRef};
+use rusqlite::{named_params, params, params_from_iter, Connection, OptionalExtension, Row};
 
 /// SQL statements and queries
 pub mod sql {
@@ -15,8 +30,29 @@ pub mod sql {
         overview TEXT,                               -- The overview of the blog post
         author TEXT,                                 -- The username of the blog author
         cover TEXT,                                  -- The path to the cover image of the blog post        
+        cover_variants TEXT,                         -- The resized variants of the cover image, as JSON
         content TEXT NOT NULL,                       -- The actual content of the blog post
-        date TEXT)                                   -- The date when the blog post was published`";
+        date TEXT,                                   -- The date when the blog post was published`
+        slug TEXT,                                   -- The unique slug used in the blog post's permalink
+        status TEXT NOT NULL DEFAULT 'published',    -- The publishing status, eg `draft`
+        format TEXT NOT NULL DEFAULT 'html',         -- The format of the content, eg `markdown`
+        content_html TEXT,                           -- The content rendered to sanitized HTML
+        version INTEGER NOT NULL DEFAULT 1,          -- The version of the blog post, incremented by every update
+        deleted_at TEXT,                             -- When the blog post was moved to the trash
+        word_count INTEGER NOT NULL DEFAULT 0,       -- Number of words of the content
+        reading_time INTEGER NOT NULL DEFAULT 0,     -- Estimated reading time of the content, in minutes
+        excerpt TEXT,                                -- The beginning of the content as plain text
+        search_text TEXT)                            -- The content as plain text, for the search index";
+
+    /// Make blog post slugs unique
+    pub const CREATE_SLUG_INDEX: &str =
+        "CREATE UNIQUE INDEX IF NOT EXISTS blog_slug ON blog (slug);";
+
+    /// Create slug redirects table
+    pub const CREATE_SLUG_REDIRECTS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS blog_slug_redirects (
+        slug TEXT PRIMARY KEY,                       -- An old slug of the blog post
+        post_id INTEGER NOT NULL)                    -- The Identifier of the blog post";
 
     /// Insert a blog post in the blog table
     pub const CREATE: &str = "
@@ -27,15 +63,107 @@ pub mod sql {
         author,
         cover,
         content,
-        date
+        date,
+        slug,
+        status,
+        format,
+        content_html,
+        cover_variants,
+        word_count,
+        reading_time,
+        excerpt,
+        search_text
        )
-      VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+      VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)";
 
     /// Get blog by id
     pub const READ: &str = "SELECT * FROM blog WHERE id = :id;";
 
-    /// Get all blog posts
-    pub const READ_ALL: &str = "SELECT * FROM blog ORDER BY rowid DESC";
+    /// Get blog by slug
+    pub const READ_BY_SLUG: &str = "SELECT * FROM blog WHERE slug = :slug;";
+
+    /// Get the blog post an old slug redirects to
+    pub const READ_SLUG_REDIRECT: &str =
+        "SELECT post_id FROM blog_slug_redirects WHERE slug = :slug;";
+
+    /// Keep an old slug as a redirect to a blog post
+    pub const CREATE_SLUG_REDIRECT: &str =
+        "INSERT OR REPLACE INTO blog_slug_redirects (slug, post_id) VALUES (?1, ?2);";
+
+    /// Remove a redirect, used when a blog post gets its old slug back
+    pub const DELETE_SLUG_REDIRECT: &str = "DELETE FROM blog_slug_redirects WHERE slug = ?1;";
+
+    /// Check if a slug is used by another blog post
+    pub const SLUG_TAKEN: &str = "
+      SELECT EXISTS (SELECT 1 FROM blog WHERE slug = :slug AND id != :id)
+        OR EXISTS (SELECT 1 FROM blog_slug_redirects WHERE slug = :slug AND post_id != :id);";
+
+    /// Get blog posts without a slug, created before slugs existed
+    pub const READ_WITHOUT_SLUG: &str = "SELECT id, title FROM blog WHERE slug IS NULL;";
+
+    /// Set the slug of a blog post
+    pub const UPDATE_SLUG: &str = "UPDATE blog SET slug = ?1 WHERE id = ?2;";
+
+    /// Get blog posts without rendered content, created before content
+    /// was rendered
+    pub const READ_WITHOUT_RENDERED: &str =
+        "SELECT id, content, format FROM blog WHERE content_html IS NULL;";
+
+    /// Set the content of a blog post
+    pub const UPDATE_CONTENT: &str = "
+      UPDATE blog
+      SET content = ?1, format = ?2, content_html = ?3, word_count = ?4, reading_time = ?5, excerpt = ?6,
+        search_text = ?7
+      WHERE id = ?8;";
+
+    /// Set the rendered content of a blog post
+    pub const UPDATE_RENDERED: &str = "UPDATE blog SET content_html = ?1 WHERE id = ?2;";
+
+    /// Get blog posts without reading statistics or search text, created
+    /// before they were computed
+    pub const READ_WITHOUT_STATS: &str =
+        "SELECT id, content, format FROM blog WHERE excerpt IS NULL OR search_text IS NULL;";
+
+    /// Set the reading statistics and the search text of a blog post
+    pub const UPDATE_STATS: &str = "
+      UPDATE blog SET word_count = ?1, reading_time = ?2, excerpt = ?3, search_text = ?4
+      WHERE id = ?5;";
+
+    /// Get all public blog posts
+    pub const READ_ALL: &str = "
+      SELECT * FROM blog
+      WHERE status IN ('published', 'scheduled') AND (date IS NULL OR date <= :now)
+        AND deleted_at IS NULL
+      ORDER BY rowid DESC";
+
+    /// Get all blog posts that are not public yet or anymore
+    pub const READ_UNPUBLISHED: &str = "
+      SELECT * FROM blog
+      WHERE (status IN ('draft', 'archived') OR (status = 'scheduled' AND date > :now))
+        AND deleted_at IS NULL
+      ORDER BY rowid DESC";
+
+    /// Get all blog posts with a status
+    pub const READ_BY_STATUS: &str =
+        "SELECT * FROM blog WHERE status = :status AND deleted_at IS NULL ORDER BY rowid DESC";
+
+    /// Set the status and publish date of a blog post
+    pub const UPDATE_STATUS: &str =
+        "UPDATE blog SET status = ?1, date = ?2 WHERE id = ?3 AND deleted_at IS NULL;";
+
+    /// Move a blog post to the trash
+    pub const TRASH: &str = "UPDATE blog SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL;";
+
+    /// Take a blog post out of the trash
+    pub const RESTORE_FROM_TRASH: &str =
+        "UPDATE blog SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL;";
+
+    /// Get the blog posts in the trash, most recently deleted first
+    pub const READ_TRASH: &str =
+        "SELECT * FROM blog WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC";
+
+    /// Get the blog posts moved to the trash before a date
+    pub const READ_EXPIRED_TRASH: &str = "SELECT id FROM blog WHERE deleted_at < :before;";
 
     /// Update article
     pub const UPDATE: &str = "
@@ -48,8 +176,766 @@ pub mod sql {
         content = ?6
       WHERE id = ?7;";
 
+    /// Increment the version of a blog post
+    pub const INCREMENT_VERSION: &str = "UPDATE blog SET version = version + 1 WHERE id = ?1;";
+
     /// Delete a blog post
     pub const DELETE: &str = "DELETE FROM blog WHERE id = :id;";
+
+    /// Delete the slug redirects of a blog post
+    pub const DELETE_SLUG_REDIRECTS: &str = "DELETE FROM blog_slug_redirects WHERE post_id = :id;";
+
+    /// Create tags table
+    pub const CREATE_TAGS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS blog_tags (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the tag, the Rust Type is `i64`
+        name TEXT NOT NULL,                          -- The name of the tag
+        slug TEXT NOT NULL UNIQUE,                   -- The unique slug of the tag
+        kind TEXT NOT NULL DEFAULT 'tag')            -- Whether the tag is a `tag` or a `category`";
+
+    /// Create the table linking blog posts to their tags
+    pub const CREATE_POST_TAGS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS blog_post_tags (
+        post_id INTEGER NOT NULL,                    -- The Identifier of the blog post
+        tag_id INTEGER NOT NULL,                     -- The Identifier of the tag
+        PRIMARY KEY (post_id, tag_id))";
+
+    /// Insert a tag in the tags table
+    pub const CREATE_TAG: &str = "INSERT INTO blog_tags (name, slug, kind) VALUES (?1, ?2, ?3);";
+
+    /// Get tag by id
+    pub const READ_TAG: &str = "SELECT * FROM blog_tags WHERE id = :id;";
+
+    /// Get tag by slug
+    pub const READ_TAG_BY_SLUG: &str = "SELECT * FROM blog_tags WHERE slug = :slug;";
+
+    /// Get all tags, optionally of one kind, with their number of
+    /// public blog posts
+    pub const READ_TAGS: &str = "
+      SELECT blog_tags.*, COUNT(blog.id) AS posts
+      FROM blog_tags
+      LEFT JOIN blog_post_tags ON blog_post_tags.tag_id = blog_tags.id
+      LEFT JOIN blog ON blog.id = blog_post_tags.post_id
+        AND blog.status IN ('published', 'scheduled')
+        AND (blog.date IS NULL OR blog.date <= :now)
+        AND blog.deleted_at IS NULL
+      WHERE :kind IS NULL OR blog_tags.kind = :kind
+      GROUP BY blog_tags.id
+      ORDER BY blog_tags.name";
+
+    /// Get the tags of a blog post
+    pub const READ_POST_TAGS: &str = "
+      SELECT blog_tags.* FROM blog_tags
+      JOIN blog_post_tags ON blog_post_tags.tag_id = blog_tags.id
+      WHERE blog_post_tags.post_id = :id
+      ORDER BY blog_tags.name";
+
+    /// Rename a tag, and change its kind if one is provided
+    pub const UPDATE_TAG: &str =
+        "UPDATE blog_tags SET name = ?1, slug = ?2, kind = COALESCE(?3, kind) WHERE id = ?4;";
+
+    /// Add a tag to a blog post
+    pub const ADD_POST_TAG: &str =
+        "INSERT OR IGNORE INTO blog_post_tags (post_id, tag_id) VALUES (?1, ?2);";
+
+    /// Remove all tags from a blog post
+    pub const DELETE_POST_TAGS: &str = "DELETE FROM blog_post_tags WHERE post_id = :id;";
+
+    /// Create the table of the co-authors of blog posts
+    pub const CREATE_CO_AUTHORS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS blog_post_authors (
+        post_id INTEGER NOT NULL,                    -- The Identifier of the blog post
+        username TEXT NOT NULL,                      -- The username of the co-author's account
+        position INTEGER NOT NULL,                   -- The order of the co-author in the byline
+        PRIMARY KEY (post_id, username))";
+
+    /// Get the co-authors of a blog post
+    pub const READ_CO_AUTHORS: &str =
+        "SELECT username FROM blog_post_authors WHERE post_id = :id ORDER BY position;";
+
+    /// Add a co-author to a blog post
+    pub const ADD_CO_AUTHOR: &str =
+        "INSERT OR IGNORE INTO blog_post_authors (post_id, username, position) VALUES (?1, ?2, ?3);";
+
+    /// Remove all co-authors from a blog post
+    pub const DELETE_CO_AUTHORS: &str = "DELETE FROM blog_post_authors WHERE post_id = :id;";
+
+    /// Add a tag to all blog posts with another tag
+    pub const MERGE_POST_TAGS: &str = "
+      INSERT OR IGNORE INTO blog_post_tags (post_id, tag_id)
+      SELECT post_id, ?2 FROM blog_post_tags WHERE tag_id = ?1";
+
+    /// Remove a tag from all blog posts
+    pub const DELETE_TAG_POSTS: &str = "DELETE FROM blog_post_tags WHERE tag_id = ?1;";
+
+    /// Delete a tag
+    pub const DELETE_TAG: &str = "DELETE FROM blog_tags WHERE id = ?1;";
+
+    /// Create blog post revisions table
+    pub const CREATE_REVISIONS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS blog_revisions (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the revision, the Rust Type is `i64`
+        post_id INTEGER NOT NULL,                    -- The Identifier of the blog post
+        title TEXT NOT NULL,                         -- The title of the blog post
+        subtitle TEXT,                               -- The subtitle of the blog post
+        overview TEXT,                               -- The overview of the blog post
+        author TEXT,                                 -- The username of the blog author
+        cover TEXT,                                  -- The path to the cover image of the blog post
+        cover_variants TEXT,                         -- The resized variants of the cover image, as JSON
+        content TEXT NOT NULL,                       -- The actual content of the blog post
+        format TEXT NOT NULL DEFAULT 'html',         -- The format of the content
+        slug TEXT,                                   -- The slug of the blog post
+        editor TEXT,                                 -- The username of the account that made the revision
+        date TEXT NOT NULL,                          -- The date the revision was made
+        tags TEXT,                                   -- The names of the tags of the blog post, as JSON
+        co_authors TEXT,                             -- The usernames of the co-authors, as JSON
+        status TEXT)                                 -- The status of the blog post, eg `draft`";
+
+    /// Store the current version of a blog post as a revision
+    pub const CREATE_REVISION: &str = "
+      INSERT INTO blog_revisions (
+        post_id, title, subtitle, overview, author, cover, cover_variants, content, format, slug,
+        editor, date, tags, co_authors, status
+       )
+      SELECT id, title, subtitle, overview, author, cover, cover_variants, content, format, slug,
+        ?2, ?3, ?4, ?5, status
+      FROM blog WHERE id = ?1";
+
+    /// Count the revisions of a blog post
+    pub const COUNT_REVISIONS: &str = "SELECT COUNT(*) FROM blog_revisions WHERE post_id = ?1;";
+
+    /// Get the revisions of a blog post, newest first
+    pub const READ_REVISIONS: &str = "
+      SELECT id, post_id, title, editor, date FROM blog_revisions
+      WHERE post_id = :post_id
+      ORDER BY id DESC";
+
+    /// Get a revision of a blog post
+    pub const READ_REVISION: &str =
+        "SELECT * FROM blog_revisions WHERE id = :id AND post_id = :post_id;";
+
+    /// Set the optional fields of a blog post, used to restore revisions
+    pub const RESTORE_OPTIONAL_FIELDS: &str =
+        "UPDATE blog SET subtitle = ?1, overview = ?2, author = ?3, cover = ?4, cover_variants = ?5 WHERE id = ?6;";
+
+    /// Set the status of a blog post, used to restore revisions
+    pub const RESTORE_STATUS: &str = "UPDATE blog SET status = ?1 WHERE id = ?2;";
+
+    /// Get the slugs of the public blog posts, with their publish date
+    /// and the date of their latest revision
+    pub const READ_LAST_MODIFIED: &str = "
+      SELECT slug, date, (SELECT MAX(date) FROM blog_revisions WHERE post_id = blog.id)
+      FROM blog
+      WHERE status IN ('published', 'scheduled') AND (date IS NULL OR date <= :now)
+        AND deleted_at IS NULL
+      ORDER BY date DESC, id DESC";
+
+    /// Get the cover photos of all blog posts and their revisions
+    pub const READ_COVERS: &str = "
+      SELECT cover, cover_variants FROM blog WHERE cover IS NOT NULL
+      UNION SELECT cover, cover_variants FROM blog_revisions WHERE cover IS NOT NULL";
+
+    /// Get the cover photos of a blog post and its revisions
+    pub const READ_POST_COVERS: &str = "
+      SELECT cover, cover_variants FROM blog WHERE id = :id AND cover IS NOT NULL
+      UNION SELECT cover, cover_variants FROM blog_revisions
+      WHERE post_id = :id AND cover IS NOT NULL";
+
+    /// Check if a cover photo is used by a blog post or a revision
+    pub const COVER_IN_USE: &str = "
+      SELECT EXISTS (SELECT 1 FROM blog WHERE cover = :cover)
+        OR EXISTS (SELECT 1 FROM blog_revisions WHERE cover = :cover)";
+
+    /// Delete the revisions of a blog post
+    pub const DELETE_REVISIONS: &str = "DELETE FROM blog_revisions WHERE post_id = :id;";
+
+    /// Create media library table
+    pub const CREATE_MEDIA_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS blog_media (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the file, the Rust Type is `i64`
+        url TEXT NOT NULL UNIQUE,                    -- The URL of the file
+        name TEXT NOT NULL,                          -- The display name of the file
+        alt TEXT,                                    -- The alternative text of the image
+        media_type TEXT NOT NULL,                    -- The type of the file, eg `png`
+        width INTEGER NOT NULL,                      -- The width of the image, in pixels
+        height INTEGER NOT NULL,                     -- The height of the image, in pixels
+        size INTEGER NOT NULL,                       -- The size of the file, in bytes
+        variants TEXT,                               -- The resized variants of the image, as JSON
+        uploader TEXT,                               -- The username of the account that uploaded the file
+        date TEXT NOT NULL)                          -- The date the file was uploaded";
+
+    /// Add a file to the media library
+    pub const CREATE_MEDIA: &str = "
+      INSERT INTO blog_media (
+        url, name, alt, media_type, width, height, size, variants, uploader, date
+       )
+      VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)";
+
+    /// Get a file of the media library
+    pub const READ_MEDIA: &str = "SELECT * FROM blog_media WHERE id = :id;";
+
+    /// Get all files of the media library, newest first
+    pub const READ_ALL_MEDIA: &str = "SELECT * FROM blog_media ORDER BY id DESC;";
+
+    /// Set the name and alternative text of a media library file, an
+    /// empty alternative text removes it
+    pub const UPDATE_MEDIA: &str = "
+      UPDATE blog_media
+      SET name = COALESCE(?1, name),
+        alt = CASE WHEN ?2 IS NULL THEN alt ELSE NULLIF(?2, '') END
+      WHERE id = ?3";
+
+    /// Remove a file from the media library
+    pub const DELETE_MEDIA: &str = "DELETE FROM blog_media WHERE id = :id;";
+
+    /// Get the blog posts using a file as their cover or in their
+    /// content, in their current version or in one of their revisions
+    pub const READ_MEDIA_USAGE: &str = "
+      SELECT id, title, slug FROM blog
+      WHERE cover = :url OR instr(content, :url) > 0
+        OR id IN (
+          SELECT post_id FROM blog_revisions
+          WHERE cover = :url OR instr(content, :url) > 0
+        )
+      ORDER BY id";
+
+    /// Create daily views table
+    pub const CREATE_VIEWS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS blog_post_views (
+        post_id INTEGER NOT NULL,                    -- The Identifier of the blog post
+        date TEXT NOT NULL,                          -- The day of the views, in UTC
+        views INTEGER NOT NULL DEFAULT 0,            -- Number of views of the blog post on the day
+        PRIMARY KEY (post_id, date))";
+
+    /// Create recent visitors table, used to count a visitor once per
+    /// time window
+    pub const CREATE_VISITORS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS blog_post_visitors (
+        post_id INTEGER NOT NULL,                    -- The Identifier of the blog post
+        visitor TEXT NOT NULL,                       -- Hash of the visitor
+        date TEXT NOT NULL,                          -- When the visitor's view was counted
+        PRIMARY KEY (post_id, visitor))";
+
+    /// Forget the visitors whose time window has passed
+    pub const DELETE_EXPIRED_VISITORS: &str = "DELETE FROM blog_post_visitors WHERE date <= ?1;";
+
+    /// Remember a visitor of a blog post, ignored if the visitor is
+    /// already known
+    pub const CREATE_VISITOR: &str =
+        "INSERT OR IGNORE INTO blog_post_visitors (post_id, visitor, date) VALUES (?1, ?2, ?3);";
+
+    /// Count a view of a blog post
+    pub const COUNT_VIEW: &str = "
+      INSERT INTO blog_post_views (post_id, date, views) VALUES (?1, ?2, 1)
+      ON CONFLICT (post_id, date) DO UPDATE SET views = views + 1";
+
+    /// Get the most viewed public blog posts of a period
+    pub const READ_TOP_POSTS: &str = "
+      SELECT blog.id, blog.title, blog.slug, SUM(blog_post_views.views) AS views
+      FROM blog_post_views
+      JOIN blog ON blog.id = blog_post_views.post_id
+      WHERE blog.status IN ('published', 'scheduled')
+        AND (blog.date IS NULL OR blog.date <= :now)
+        AND blog.deleted_at IS NULL
+        AND (:from IS NULL OR blog_post_views.date >= :from)
+        AND (:to IS NULL OR blog_post_views.date <= :to)
+      GROUP BY blog.id
+      ORDER BY views DESC, blog.id ASC
+      LIMIT :limit";
+
+    /// Get the views of every day of a period, optionally of a single
+    /// blog post
+    pub const READ_DAILY_VIEWS: &str = "
+      SELECT date, SUM(views) FROM blog_post_views
+      WHERE (:post_id IS NULL OR post_id = :post_id)
+        AND (:from IS NULL OR date >= :from)
+        AND (:to IS NULL OR date <= :to)
+      GROUP BY date
+      ORDER BY date";
+
+    /// Delete the views of a blog post
+    pub const DELETE_POST_VIEWS: &str = "DELETE FROM blog_post_views WHERE post_id = :id;";
+
+    /// Delete the recent visitors of a blog post
+    pub const DELETE_POST_VISITORS: &str = "DELETE FROM blog_post_visitors WHERE post_id = :id;";
+
+    /// Check if the search index exists, and indexes the plain text of
+    /// the content
+    pub const SEARCH_TABLE_EXISTS: &str = "
+      SELECT COUNT(*) FROM sqlite_master
+      WHERE type = 'table' AND name = 'blog_search' AND sql LIKE '%search_text%';";
+
+    /// Remove the search index of the raw content, it is created again
+    /// over the plain text
+    pub const DROP_RAW_SEARCH: &str = "
+      DROP TRIGGER IF EXISTS blog_search_insert;
+      DROP TRIGGER IF EXISTS blog_search_delete;
+      DROP TRIGGER IF EXISTS blog_search_update;
+      DROP TABLE IF EXISTS blog_search;";
+
+    /// Create the full-text search index of the blog table, over the
+    /// plain text of the content
+    pub const CREATE_SEARCH_TABLE: &str = "
+      CREATE VIRTUAL TABLE IF NOT EXISTS blog_search USING fts5 (
+        title,
+        subtitle,
+        overview,
+        search_text,
+        content = 'blog',
+        content_rowid = 'id',
+        tokenize = 'unicode61 remove_diacritics 2');";
+
+    /// Index new blog posts
+    pub const CREATE_SEARCH_INSERT_TRIGGER: &str = "
+      CREATE TRIGGER IF NOT EXISTS blog_search_insert AFTER INSERT ON blog BEGIN
+        INSERT INTO blog_search (rowid, title, subtitle, overview, search_text)
+        VALUES (new.id, new.title, new.subtitle, new.overview, new.search_text);
+      END;";
+
+    /// Remove deleted blog posts from the index
+    pub const CREATE_SEARCH_DELETE_TRIGGER: &str = "
+      CREATE TRIGGER IF NOT EXISTS blog_search_delete AFTER DELETE ON blog BEGIN
+        INSERT INTO blog_search (blog_search, rowid, title, subtitle, overview, search_text)
+        VALUES ('delete', old.id, old.title, old.subtitle, old.overview, old.search_text);
+      END;";
+
+    /// Index the new version of updated blog posts
+    pub const CREATE_SEARCH_UPDATE_TRIGGER: &str = "
+      CREATE TRIGGER IF NOT EXISTS blog_search_update
+      AFTER UPDATE OF title, subtitle, overview, search_text ON blog BEGIN
+        INSERT INTO blog_search (blog_search, rowid, title, subtitle, overview, search_text)
+        VALUES ('delete', old.id, old.title, old.subtitle, old.overview, old.search_text);
+        INSERT INTO blog_search (rowid, title, subtitle, overview, search_text)
+        VALUES (new.id, new.title, new.subtitle, new.overview, new.search_text);
+      END;";
+
+    /// Index all the blog posts
+    pub const REBUILD_SEARCH: &str = "INSERT INTO blog_search (blog_search) VALUES ('rebuild');";
+}
+
+/// Find a slug not used by any other blog post, adding a collision
+/// suffix to `slug` if needed. `id` is the blog post the slug is for.
+fn unique_slug(conn: &Connection, slug: &str, id: i64) -> Result<String, KontrollerError> {
+    let mut candidate = slug.to_string();
+    let mut n = 2;
+
+    loop {
+        let taken: bool = conn
+            .query_row(
+                sql::SLUG_TAKEN,
+                named_params! { ":slug": candidate, ":id": id },
+                |row| row.get(0),
+            )
+            .map_err(|_| KontrollerError::DbSQL)?;
+
+        if !taken {
+            return Ok(candidate);
+        }
+
+        candidate = with_suffix(slug, n);
+        n += 1;
+    }
+}
+
+impl ToSql for PostStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for PostStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        PostStatus::from_name(value.as_str()?).ok_or(FromSqlError::InvalidType)
+    }
+}
+
+impl ToSql for ContentFormat {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for ContentFormat {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        ContentFormat::from_name(value.as_str()?).ok_or(FromSqlError::InvalidType)
+    }
+}
+
+/// Get the content of a blog post as stored, HTML content is sanitized
+fn stored_content(content: &str, format: ContentFormat) -> String {
+    match format {
+        ContentFormat::Markdown => content.to_string(),
+        ContentFormat::Html => sanitize(content),
+    }
+}
+
+impl ToSql for TagKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for TagKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        TagKind::from_name(value.as_str()?).ok_or(FromSqlError::InvalidType)
+    }
+}
+
+/// Read a file from a row of the media library table
+fn media_file_from_row(row: &Row) -> rusqlite::Result<MediaFile> {
+    Ok(MediaFile {
+        id: row.get("id")?,
+        url: row.get("url")?,
+        name: row.get("name")?,
+        alt: row.get("alt")?,
+        media_type: row.get("media_type")?,
+        width: row.get("width")?,
+        height: row.get("height")?,
+        size: row.get("size")?,
+        variants: row.get("variants")?,
+        uploader: row.get("uploader")?,
+        date: row.get("date")?,
+        used_by: vec![],
+    })
+}
+
+/// Read a tag from a row of the tags table
+fn tag_from_row(row: &Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        slug: row.get("slug")?,
+        kind: row.get("kind")?,
+    })
+}
+
+/// Get the tags of a blog post
+fn post_tags(conn: &Connection, id: i64) -> Result<Vec<Tag>, KontrollerError> {
+    let mut stmt = conn
+        .prepare(sql::READ_POST_TAGS)
+        .map_err(|_| KontrollerError::DbSQL)?;
+    let tags = stmt
+        .query_map(named_params! { ":id": id }, tag_from_row)
+        .map_err(|_| KontrollerError::DbField)?;
+
+    tags.collect::<Result<Vec<_>, _>>()
+        .map_err(|_| KontrollerError::DbField)
+}
+
+/// Get the usernames of the co-authors of a blog post
+fn post_co_authors(conn: &Connection, id: i64) -> Result<Vec<String>, KontrollerError> {
+    let mut stmt = conn
+        .prepare(sql::READ_CO_AUTHORS)
+        .map_err(|_| KontrollerError::DbSQL)?;
+    let usernames = stmt
+        .query_map(named_params! { ":id": id }, |row| row.get(0))
+        .map_err(|_| KontrollerError::DbField)?;
+
+    usernames
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| KontrollerError::DbField)
+}
+
+/// Replace the co-authors of a blog post
+fn set_post_co_authors(
+    conn: &Connection,
+    id: i64,
+    usernames: &[String],
+) -> Result<Vec<String>, KontrollerError> {
+    conn.execute(sql::DELETE_CO_AUTHORS, named_params! { ":id": id })
+        .map_err(|_| KontrollerError::DbSQL)?;
+
+    for (position, username) in usernames.iter().enumerate() {
+        conn.execute(sql::ADD_CO_AUTHOR, params![id, username, position as i64])
+            .map_err(|_| KontrollerError::DbSQL)?;
+    }
+
+    post_co_authors(conn, id)
+}
+
+/// Replace the tags of a blog post, tags are found by the slug of
+/// their name and created if they do not exist yet
+fn set_post_tags(
+    conn: &Connection,
+    id: i64,
+    names: &[String],
+) -> Result<Vec<Tag>, KontrollerError> {
+    conn.execute(sql::DELETE_POST_TAGS, named_params! { ":id": id })
+        .map_err(|_| KontrollerError::DbSQL)?;
+
+    // Names without any ASCII letters or digits have no slug
+    for name in names.iter().filter(|name| has_slug_characters(name)) {
+        let slug = slugify(name);
+        let tag_id: Option<i64> = conn
+            .query_row(
+                sql::READ_TAG_BY_SLUG,
+                named_params! { ":slug": slug },
+                |row| row.get("id"),
+            )
+            .optional()
+            .map_err(|_| KontrollerError::DbSQL)?;
+
+        let tag_id = match tag_id {
+            Some(tag_id) => tag_id,
+            None => {
+                conn.execute(sql::CREATE_TAG, params![name.trim(), slug, TagKind::Tag])
+                    .map_err(|_| KontrollerError::DbField)?;
+                conn.last_insert_rowid()
+            }
+        };
+
+        conn.execute(sql::ADD_POST_TAG, params![id, tag_id])
+            .map_err(|_| KontrollerError::DbSQL)?;
+    }
+
+    post_tags(conn, id)
+}
+
+/// Prepare the change of the slug of a blog post: the old slug is kept
+/// as a redirect. Returns the unique slug to store, `None` if the slug
+/// is unchanged.
+fn redirect_slug(
+    conn: &Connection,
+    id: i64,
+    old_slug: Option<&str>,
+    slug: &str,
+) -> Result<Option<String>, KontrollerError> {
+    if old_slug == Some(slug) {
+        return Ok(None);
+    }
+
+    let slug = unique_slug(conn, slug, id)?;
+
+    // Keep the old slug as a redirect
+    if let Some(old_slug) = old_slug {
+        conn.execute(sql::CREATE_SLUG_REDIRECT, params![old_slug, id])
+            .map_err(|_| KontrollerError::DbSQL)?;
+    }
+
+    conn.execute(sql::DELETE_SLUG_REDIRECT, params![slug])
+        .map_err(|_| KontrollerError::DbSQL)?;
+
+    Ok(Some(slug))
+}
+
+/// Update the fields of a blog post, a new slug is generated when
+/// the title changes and the old slug is kept as a redirect
+fn apply_update(
+    conn: &Connection,
+    id: i64,
+    blog: &DatabaseBlogPostInput,
+) -> Result<(), KontrollerError> {
+    let (old_title, old_slug): (String, Option<String>) = conn
+        .query_row(
+            "SELECT title, slug FROM blog WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|_| KontrollerError::DbField)?;
+
+    // A new slug is generated when the title changes, unless
+    // one is provided
+    let slug = match &blog.slug {
+        Some(slug) => Some(slugify(slug)),
+        None if blog.title != old_title => Some(slugify(&blog.title)),
+        None => None,
+    };
+
+    if let Some(slug) = slug {
+        if let Some(slug) = redirect_slug(conn, id, old_slug.as_deref(), &slug)? {
+            conn.execute(sql::UPDATE_SLUG, params![slug, id])
+                .map_err(|_| KontrollerError::DbSQL)?;
+        }
+    }
+
+    // Update title
+    conn.execute(
+        "UPDATE blog SET title = ?1 WHERE id = ?2",
+        &[&blog.title, &format!("{id}")],
+    )
+    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+    conn.execute(sql::INCREMENT_VERSION, params![id])
+        .map_err(|_| KontrollerError::DbSQL)?;
+
+    // Update content, in the format it is already in unless one is
+    // provided
+    let format = match blog.format {
+        Some(format) => format,
+        None => conn
+            .query_row(
+                "SELECT format FROM blog WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|_| KontrollerError::DbField)?,
+    };
+    let content = stored_content(&blog.content, format);
+    let stats = ContentStats::new(&content, format);
+    conn.execute(
+        sql::UPDATE_CONTENT,
+        params![
+            content,
+            format,
+            render(&content, format),
+            stats.word_count,
+            stats.reading_time,
+            stats.excerpt,
+            stats.text,
+            id
+        ],
+    )
+    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+    // Update subtitle
+    if let Some(subtitle) = &blog.subtitle {
+        conn.execute(
+            "UPDATE blog SET subtitle = ?1 WHERE id = ?2",
+            &[subtitle, &format!("{id}")],
+        )
+        .map_err(|_| KontrollerError::DbTableCreation)?;
+    }
+
+    // Update overview
+    if let Some(overview) = &blog.overview {
+        conn.execute(
+            "UPDATE blog SET overview = ?1 WHERE id = ?2",
+            &[overview, &format!("{id}")],
+        )
+        .map_err(|_| KontrollerError::DbTableCreation)?;
+    }
+
+    // Update author
+    if let Some(author) = &blog.author {
+        conn.execute(
+            "UPDATE blog SET author = ?1 WHERE id = ?2",
+            &[author, &format!("{id}")],
+        )
+        .map_err(|_| KontrollerError::DbTableCreation)?;
+    }
+
+    // Update cover, the variants of the old cover are replaced too
+    if let Some(cover) = &blog.cover {
+        conn.execute(
+            "UPDATE blog SET cover = ?1, cover_variants = ?2 WHERE id = ?3",
+            params![cover, blog.cover_variants, id],
+        )
+        .map_err(|_| KontrollerError::DbTableCreation)?;
+    }
+
+    // Update tags
+    if let Some(names) = &blog.tags {
+        set_post_tags(conn, id, names)?;
+    }
+
+    // Update co-authors
+    if let Some(usernames) = &blog.co_authors {
+        set_post_co_authors(conn, id, usernames)?;
+    }
+
+    Ok(())
+}
+
+/// Store the current version of a blog post as a revision, with the
+/// names of its tags and its co-authors
+fn store_revision(
+    conn: &Connection,
+    id: i64,
+    editor: Option<&str>,
+    date: DateTime<Utc>,
+) -> Result<(), KontrollerError> {
+    let tags: Vec<String> = post_tags(conn, id)?
+        .into_iter()
+        .map(|tag| tag.name)
+        .collect();
+    let co_authors = post_co_authors(conn, id)?;
+
+    conn.execute(
+        sql::CREATE_REVISION,
+        params![
+            id,
+            editor,
+            date,
+            json!(tags).to_string(),
+            json!(co_authors).to_string()
+        ],
+    )
+    .map_err(|_| KontrollerError::DbSQL)?;
+    Ok(())
+}
+
+/// Read a list stored as JSON, `None` for revisions stored before the
+/// list was
+fn json_list(row: &Row, column: &str) -> rusqlite::Result<Option<Vec<String>>> {
+    match row.get::<_, Option<String>>(column)? {
+        Some(list) => json_from_str(&list)
+            .map(Some)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(err))),
+        None => Ok(None),
+    }
+}
+
+/// Store the version of a blog post from before its first update, for
+/// posts created without a revision. The editor of the original
+/// version is unknown.
+fn store_original_revision(conn: &Connection, id: i64) -> Result<(), KontrollerError> {
+    let revisions: i64 = conn
+        .query_row(sql::COUNT_REVISIONS, params![id], |row| row.get(0))
+        .map_err(|_| KontrollerError::DbSQL)?;
+
+    if revisions == 0 {
+        let date: Option<DateTime<Utc>> = conn
+            .query_row("SELECT date FROM blog WHERE id = ?1", params![id], |row| {
+                row.get(0)
+            })
+            .map_err(|_| KontrollerError::DbField)?;
+
+        store_revision(conn, id, None, date.unwrap_or_else(Utc::now))?;
+    }
+
+    Ok(())
+}
+
+/// Excerpt of a blog post, its overview or the beginning of its content
+fn excerpt(overview: Option<&str>, content_excerpt: String) -> String {
+    match overview
+        .map(str::trim)
+        .filter(|overview| !overview.is_empty())
+    {
+        Some(overview) => overview.to_string(),
+        None => content_excerpt,
+    }
+}
+
+/// Read a blog post from a row of the blog table
+fn blog_post_from_row(row: &Row) -> rusqlite::Result<BlogPost> {
+    let overview: Option<String> = row.get("overview")?;
+    let content_excerpt: Option<String> = row.get("excerpt")?;
+
+    Ok(BlogPost {
+        id: row.get("id")?,
+        title: row.get("title")?,
+        subtitle: row.get("subtitle")?,
+        excerpt: excerpt(overview.as_deref(), content_excerpt.unwrap_or_default()),
+        overview,
+        author: row.get("author")?,
+        cover: row.get("cover")?,
+        cover_variants: row.get("cover_variants")?,
+        content: row.get("content")?,
+        format: row.get("format")?,
+        content_html: row
+            .get::<_, Option<String>>("content_html")?
+            .unwrap_or_default(),
+        date: row.get("date")?,
+        slug: row.get::<_, Option<String>>("slug")?.unwrap_or_default(),
+        status: row.get("status")?,
+        version: row.get("version")?,
+        deleted_at: row.get("deleted_at")?,
+        word_count: row.get("word_count")?,
+        reading_time: row.get("reading_time")?,
+        tags: vec![],
+        co_authors: vec![],
+    })
 }
 
 /// Database management system
@@ -60,6 +946,12 @@ pub struct Database {
     conn: Option<Connection>,
 }
 
+/// Slug of a blog post and the date it was last modified
+pub type LastModified = (String, Option<DateTime<Utc>>);
+
+/// Cover photo of a blog post and its resized variants
+pub type Cover = (String, Option<ImageVariants>);
+
 impl Database {
     /// Create a new database controller
     pub fn new(path: &str) -> Self {
@@ -86,6 +978,157 @@ impl Database {
                 tx.execute(sql::CREATE_TABLE, ())
                     .map_err(|_| KontrollerError::DbTableCreation)?;
 
+                // Columns added after the blog table was first released
+                migration::add_column(&tx, "blog", "slug", "TEXT")?;
+                migration::add_column(&tx, "blog", "status", "TEXT NOT NULL DEFAULT 'published'")?;
+                migration::add_column(&tx, "blog", "format", "TEXT NOT NULL DEFAULT 'html'")?;
+                migration::add_column(&tx, "blog", "content_html", "TEXT")?;
+                migration::add_column(&tx, "blog", "cover_variants", "TEXT")?;
+                migration::add_column(&tx, "blog", "version", "INTEGER NOT NULL DEFAULT 1")?;
+                migration::add_column(&tx, "blog", "deleted_at", "TEXT")?;
+                migration::add_column(&tx, "blog", "word_count", "INTEGER NOT NULL DEFAULT 0")?;
+                migration::add_column(&tx, "blog", "reading_time", "INTEGER NOT NULL DEFAULT 0")?;
+                migration::add_column(&tx, "blog", "excerpt", "TEXT")?;
+                migration::add_column(&tx, "blog", "search_text", "TEXT")?;
+
+                tx.execute(sql::CREATE_SLUG_INDEX, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_SLUG_REDIRECTS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_TAGS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_POST_TAGS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_CO_AUTHORS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_REVISIONS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+                migration::add_column(
+                    &tx,
+                    "blog_revisions",
+                    "format",
+                    "TEXT NOT NULL DEFAULT 'html'",
+                )?;
+                migration::add_column(&tx, "blog_revisions", "cover_variants", "TEXT")?;
+                migration::add_column(&tx, "blog_revisions", "tags", "TEXT")?;
+                migration::add_column(&tx, "blog_revisions", "co_authors", "TEXT")?;
+                migration::add_column(&tx, "blog_revisions", "status", "TEXT")?;
+
+                tx.execute(sql::CREATE_MEDIA_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_VIEWS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_VISITORS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                // Index the blog posts created before the search index,
+                // or indexed with their raw content
+                let search_indexed: bool = tx
+                    .query_row(sql::SEARCH_TABLE_EXISTS, [], |row| row.get(0))
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                if !search_indexed {
+                    tx.execute_batch(sql::DROP_RAW_SEARCH)
+                        .map_err(|_| KontrollerError::DbTableCreation)?;
+                }
+                for statement in [
+                    sql::CREATE_SEARCH_TABLE,
+                    sql::CREATE_SEARCH_INSERT_TRIGGER,
+                    sql::CREATE_SEARCH_DELETE_TRIGGER,
+                    sql::CREATE_SEARCH_UPDATE_TRIGGER,
+                ] {
+                    tx.execute(statement, ())
+                        .map_err(|_| KontrollerError::DbTableCreation)?;
+                }
+                if !search_indexed {
+                    tx.execute(sql::REBUILD_SEARCH, ())
+                        .map_err(|_| KontrollerError::DbTableCreation)?;
+                }
+
+                // Give slugs to blog posts created before slugs existed
+                let without_slug = {
+                    let mut stmt = tx
+                        .prepare(sql::READ_WITHOUT_SLUG)
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                    let rows = stmt
+                        .query_map([], |row| {
+                            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                        })
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                    rows.collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| KontrollerError::DbField)?
+                };
+
+                for (id, title) in without_slug {
+                    let slug = unique_slug(&tx, &slugify(&title), id)?;
+                    tx.execute(sql::UPDATE_SLUG, params![slug, id])
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                }
+
+                // Render the content of blog posts created before content
+                // was rendered
+                let without_rendered = {
+                    let mut stmt = tx
+                        .prepare(sql::READ_WITHOUT_RENDERED)
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                    let rows = stmt
+                        .query_map([], |row| {
+                            Ok((
+                                row.get::<_, i64>(0)?,
+                                row.get::<_, String>(1)?,
+                                row.get::<_, ContentFormat>(2)?,
+                            ))
+                        })
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                    rows.collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| KontrollerError::DbField)?
+                };
+
+                for (id, content, format) in without_rendered {
+                    tx.execute(sql::UPDATE_RENDERED, params![render(&content, format), id])
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                }
+
+                // Compute the reading statistics of blog posts created
+                // before they were computed
+                let without_stats = {
+                    let mut stmt = tx
+                        .prepare(sql::READ_WITHOUT_STATS)
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                    let rows = stmt
+                        .query_map([], |row| {
+                            Ok((
+                                row.get::<_, i64>(0)?,
+                                row.get::<_, String>(1)?,
+                                row.get::<_, ContentFormat>(2)?,
+                            ))
+                        })
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                    rows.collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| KontrollerError::DbField)?
+                };
+
+                for (id, content, format) in without_stats {
+                    let stats = ContentStats::new(&content, format);
+                    tx.execute(
+                        sql::UPDATE_STATS,
+                        params![
+                            stats.word_count,
+                            stats.reading_time,
+                            stats.excerpt,
+                            stats.text,
+                            id
+                        ],
+                    )
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                }
+
                 tx.commit().map_err(|_| KontrollerError::DbTableCreation)?;
 
                 Ok(())
@@ -94,11 +1137,31 @@ impl Database {
         }
     }
 
-    /// Create a new blog
-    pub fn create_blog(&self, blog: &DatabaseBlogPostInput) -> Result<(), KontrollerError> {
-        match &self.conn {
+    /// Create a new blog, the slug is generated from the title if the
+    /// blog has none
+    pub fn create_blog(
+        &mut self,
+        blog: &DatabaseBlogPostInput,
+    ) -> Result<BlogPost, KontrollerError> {
+        match &mut self.conn {
             Some(conn) => {
-                conn.execute(
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                let slug = match &blog.slug {
+                    Some(slug) => slugify(slug),
+                    None => slugify(&blog.title),
+                };
+                // A new blog post has no id yet, so no id matches it
+                let slug = unique_slug(&tx, &slug, -1)?;
+
+                let format = blog.format.unwrap_or_default();
+                let content = stored_content(&blog.content, format);
+                let content_html = render(&content, format);
+                let stats = ContentStats::new(&content, format);
+
+                tx.execute(
                     sql::CREATE,
                     params![
                         &blog.title,
@@ -106,12 +1169,56 @@ impl Database {
                         blog.overview,
                         blog.author,
                         blog.cover,
-                        blog.content,
-                        blog.date
+                        content,
+                        blog.date,
+                        slug,
+                        blog.status,
+                        format,
+                        content_html,
+                        blog.cover_variants,
+                        stats.word_count,
+                        stats.reading_time,
+                        stats.excerpt,
+                        stats.text
                     ],
                 )
                 .map_err(|_| KontrollerError::DbField)?;
-                Ok(())
+
+                let id = tx.last_insert_rowid();
+
+                let tags = match &blog.tags {
+                    Some(names) => set_post_tags(&tx, id, names)?,
+                    None => vec![],
+                };
+                let co_authors = match &blog.co_authors {
+                    Some(usernames) => set_post_co_authors(&tx, id, usernames)?,
+                    None => vec![],
+                };
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(BlogPost {
+                    id,
+                    title: blog.title.clone(),
+                    subtitle: blog.subtitle.clone(),
+                    overview: blog.overview.clone(),
+                    author: blog.author.clone(),
+                    cover: blog.cover.clone(),
+                    cover_variants: blog.cover_variants.clone(),
+                    content,
+                    format,
+                    content_html,
+                    date: blog.date,
+                    slug,
+                    status: blog.status,
+                    version: 1,
+                    deleted_at: None,
+                    word_count: stats.word_count,
+                    reading_time: stats.reading_time,
+                    excerpt: excerpt(blog.overview.as_deref(), stats.excerpt),
+                    tags,
+                    co_authors,
+                })
             }
             None => Err(KontrollerError::DbConnection),
         }
@@ -121,22 +1228,16 @@ impl Database {
     pub fn get_blog_by_id(&self, id: i64) -> Result<Option<BlogPost>, KontrollerError> {
         match &self.conn {
             Some(conn) => {
-                let mut stmt = conn
-                    .prepare(sql::READ)
-                    .map_err(|_| KontrollerError::DbSQL)?;
-                let mut rows = stmt
-                    .query(&[(":id", &id)])
-                    .map_err(|_| KontrollerError::DbSQL)?;
-                match rows.next().map_err(|_| KontrollerError::DbSQL)? {
-                    Some(s) => Ok(Some(BlogPost {
-                        id: s.get(0).map_err(|_| KontrollerError::DbField)?,
-                        title: s.get(1).map_err(|_| KontrollerError::DbField)?,
-                        subtitle: s.get(2).map_err(|_| KontrollerError::DbField)?,
-                        overview: s.get(3).map_err(|_| KontrollerError::DbField)?,
-                        author: s.get(4).map_err(|_| KontrollerError::DbField)?,
-                        cover: s.get(5).map_err(|_| KontrollerError::DbField)?,
-                        content: s.get(6).map_err(|_| KontrollerError::DbField)?,
-                        date: s.get(7).map_err(|_| KontrollerError::DbField)?,
+                let post = conn
+                    .query_row(sql::READ, &[(":id", &id)], blog_post_from_row)
+                    .optional()
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                match post {
+                    Some(post) => Ok(Some(BlogPost {
+                        tags: post_tags(conn, post.id)?,
+                        co_authors: post_co_authors(conn, post.id)?,
+                        ..post
                     })),
                     None => Ok(None),
                 }
@@ -145,32 +1246,1039 @@ impl Database {
         }
     }
 
-    /// Get all posts
-    pub fn get_all(&self) -> Result<Vec<BlogPost>, KontrollerError> {
+    /// Get a blog post by its current slug
+    pub fn get_blog_by_slug(&self, slug: &str) -> Result<Option<BlogPost>, KontrollerError> {
         match &self.conn {
             Some(conn) => {
-                let mut posts: Vec<BlogPost> = vec![];
-                let mut stmt = conn
-                    .prepare(sql::READ_ALL)
-                    .map_err(|_| KontrollerError::DbSQL)?;
-                let posts_iter = stmt
-                    .query_map([], |row| {
-                        Ok(BlogPost {
-                            id: row.get(0).unwrap(),
-                            title: row.get(1).unwrap(), //.map_err(|_| KontrollerError::DbField)?,
-                            subtitle: row.get(2).unwrap(), //.map_err(|_| KontrollerError::DbField)?,
-                            overview: row.get(3).unwrap(), //.map_err(|_| KontrollerError::DbField)?,
-                            author: row.get(4).unwrap(), //.map_err(|_| KontrollerError::DbField)?,
-                            cover: row.get(5).unwrap(),  //.map_err(|_| KontrollerError::DbField)?,
-                            content: row.get(6).unwrap(), //.map_err(|_| KontrollerError::DbField)?,
-                            date: row.get(7).unwrap(),   //.map_err(|_| KontrollerError::DbField)?,
-                        })
-                    })
+                let post = conn
+                    .query_row(sql::READ_BY_SLUG, &[(":slug", slug)], blog_post_from_row)
+                    .optional()
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                match post {
+                    Some(post) => Ok(Some(BlogPost {
+                        tags: post_tags(conn, post.id)?,
+                        co_authors: post_co_authors(conn, post.id)?,
+                        ..post
+                    })),
+                    None => Ok(None),
+                }
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get the current slug of the blog post an old slug redirects to
+    pub fn get_slug_redirect(&self, slug: &str) -> Result<Option<String>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let id: Option<i64> = conn
+                    .query_row(sql::READ_SLUG_REDIRECT, &[(":slug", slug)], |row| {
+                        row.get(0)
+                    })
+                    .optional()
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                match id {
+                    Some(id) => Ok(self.get_blog_by_id(id)?.map(|post| post.slug)),
+                    None => Ok(None),
+                }
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get all public posts
+    pub fn get_all(&self) -> Result<Vec<BlogPost>, KontrollerError> {
+        self.query_posts(sql::READ_ALL, named_params! { ":now": Utc::now() })
+    }
+
+    /// Get a page of public posts
+    pub fn get_page(
+        &self,
+        query: &BlogPostQuery,
+        now: DateTime<Utc>,
+    ) -> Result<BlogPostPage<BlogPost>, KontrollerError> {
+        let mut conditions: Vec<&str> = vec![
+            "status IN ('published', 'scheduled')",
+            "(date IS NULL OR date <= :now)",
+            "deleted_at IS NULL",
+        ];
+        let mut params: Vec<(&str, &dyn ToSql)> = vec![(":now", &now)];
+
+        if let Some(author) = &query.author {
+            conditions.push(
+                "(author = :author
+                  OR id IN (SELECT post_id FROM blog_post_authors WHERE username = :author))",
+            );
+            params.push((":author", author));
+        }
+        if let Some(tag) = &query.tag {
+            conditions.push(
+                "id IN (SELECT post_id FROM blog_post_tags
+                  JOIN blog_tags ON blog_tags.id = blog_post_tags.tag_id
+                  WHERE blog_tags.slug = :tag)",
+            );
+            params.push((":tag", tag));
+        }
+        if let Some(from) = &query.from {
+            conditions.push("date >= :from");
+            params.push((":from", from));
+        }
+        if let Some(to) = &query.to {
+            conditions.push("date <= :to");
+            params.push((":to", to));
+        }
+
+        let filter = conditions.join(" AND ");
+        let total: i64 = match &self.conn {
+            Some(conn) => conn
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM blog WHERE {filter}"),
+                    params.as_slice(),
+                    |row| row.get(0),
+                )
+                .map_err(|_| KontrollerError::DbSQL)?,
+            None => return Err(KontrollerError::DbConnection),
+        };
+
+        // Posts without a date sort as the oldest, compared as an empty
+        // date so the cursor does not skip them
+        let (order, after_cursor) = match query.sort.unwrap_or_default() {
+            PostSort::Newest => (
+                "COALESCE(date, '') DESC, id DESC",
+                "(COALESCE(date, ''), id) < (SELECT COALESCE(date, ''), id FROM blog WHERE id = :cursor)",
+            ),
+            PostSort::Oldest => (
+                "COALESCE(date, '') ASC, id ASC",
+                "(COALESCE(date, ''), id) > (SELECT COALESCE(date, ''), id FROM blog WHERE id = :cursor)",
+            ),
+            PostSort::Title => (
+                "title ASC, id ASC",
+                "(title, id) > (SELECT title, id FROM blog WHERE id = :cursor)",
+            ),
+        };
+
+        let limit = query.limit();
+        let offset = (query.page() - 1) * limit;
+        let mut filter = filter;
+        if let Some(cursor) = &query.cursor {
+            filter = format!("{filter} AND {after_cursor}");
+            params.push((":cursor", cursor));
+        } else {
+            params.push((":offset", &offset));
+        }
+        params.push((":limit", &limit));
+
+        let pagination = if query.cursor.is_some() {
+            "LIMIT :limit"
+        } else {
+            "LIMIT :limit OFFSET :offset"
+        };
+
+        let posts = self.query_posts(
+            &format!("SELECT * FROM blog WHERE {filter} ORDER BY {order} {pagination}"),
+            params.as_slice(),
+        )?;
+
+        let next_cursor = if posts.len() as i64 == limit {
+            posts.last().map(|post| post.id)
+        } else {
+            None
+        };
+
+        Ok(BlogPostPage {
+            posts,
+            total,
+            page: query.cursor.map_or(Some(query.page()), |_| None),
+            limit,
+            next_cursor,
+        })
+    }
+
+    /// Search the public posts, most relevant first
+    pub fn search(
+        &self,
+        query: &SearchQuery,
+        now: DateTime<Utc>,
+    ) -> Result<BlogPostPage<SearchResult>, KontrollerError> {
+        let expression = query.match_expression();
+        let mut conditions: Vec<&str> = vec![
+            "blog_search MATCH :query",
+            "blog.status IN ('published', 'scheduled')",
+            "(blog.date IS NULL OR blog.date <= :now)",
+            "blog.deleted_at IS NULL",
+        ];
+        let mut params: Vec<(&str, &dyn ToSql)> = vec![(":query", &expression), (":now", &now)];
+
+        if let Some(author) = &query.author {
+            conditions.push(
+                "(blog.author = :author
+                  OR blog.id IN (SELECT post_id FROM blog_post_authors WHERE username = :author))",
+            );
+            params.push((":author", author));
+        }
+        if let Some(tag) = &query.tag {
+            conditions.push(
+                "blog.id IN (SELECT post_id FROM blog_post_tags
+                  JOIN blog_tags ON blog_tags.id = blog_post_tags.tag_id
+                  WHERE blog_tags.slug = :tag)",
+            );
+            params.push((":tag", tag));
+        }
+
+        let conn = match &self.conn {
+            Some(conn) => conn,
+            None => return Err(KontrollerError::DbConnection),
+        };
+
+        let filter = conditions.join(" AND ");
+        let from = "blog_search JOIN blog ON blog.id = blog_search.rowid";
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {from} WHERE {filter}"),
+                params.as_slice(),
+                |row| row.get(0),
+            )
+            .map_err(|_| KontrollerError::DbSQL)?;
+
+        let limit = query.limit();
+        let offset = (query.page() - 1) * limit;
+        params.push((":limit", &limit));
+        params.push((":offset", &offset));
+
+        // Matches are marked with control characters, and turned into
+        // `<mark>` tags once the text is escaped
+        let select = format!(
+            "SELECT blog.*,
+              highlight(blog_search, 0, '{MARK_START}', '{MARK_END}') AS title_highlight,
+              snippet(blog_search, -1, '{MARK_START}', '{MARK_END}', '…', 24) AS snippet,
+              bm25(blog_search, 10.0, 5.0, 3.0, 1.0) AS rank
+             FROM {from} WHERE {filter} ORDER BY rank LIMIT :limit OFFSET :offset"
+        );
+
+        let mut stmt = conn.prepare(&select).map_err(|_| KontrollerError::DbSQL)?;
+        let rows = stmt
+            .query_map(params.as_slice(), |row| {
+                Ok((
+                    blog_post_from_row(row)?,
+                    row.get::<_, String>("title_highlight")?,
+                    row.get::<_, String>("snippet")?,
+                    row.get::<_, f64>("rank")?,
+                ))
+            })
+            .map_err(|_| KontrollerError::DbField)?;
+
+        let mut results = vec![];
+        for row in rows {
+            let (post, title, snippet, rank) = row.map_err(|_| KontrollerError::DbField)?;
+            results.push(SearchResult {
+                post: BlogPostSummary::from(BlogPost {
+                    tags: post_tags(conn, post.id)?,
+                    co_authors: post_co_authors(conn, post.id)?,
+                    ..post
+                }),
+                title_highlight: mark_html(&title),
+                snippet: mark_html(&snippet),
+                rank,
+            });
+        }
+
+        Ok(BlogPostPage {
+            posts: results,
+            total,
+            page: Some(query.page()),
+            limit,
+            next_cursor: None,
+        })
+    }
+
+    /// Get the slugs of all public posts with the date they were last
+    /// modified: the latest of their publish date and revision dates
+    pub fn get_last_modified(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<LastModified>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_LAST_MODIFIED)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let posts = stmt
+                    .query_map(named_params! { ":now": now }, |row| {
+                        let date: Option<DateTime<Utc>> = row.get(1)?;
+                        let revised: Option<DateTime<Utc>> = row.get(2)?;
+                        Ok((row.get(0)?, date.max(revised)))
+                    })
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                posts
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get the cover photos of all posts, including the covers of their
+    /// revisions
+    pub fn get_covers(&self) -> Result<Vec<Cover>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_COVERS)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let covers = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                covers
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get the cover photos of a post, including the covers of its
+    /// revisions
+    pub fn get_post_covers(&self, id: i64) -> Result<Vec<Cover>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_POST_COVERS)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let covers = stmt
+                    .query_map(named_params! { ":id": id }, |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                covers
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Check if a cover photo is used by a post or a revision
+    pub fn cover_in_use(&self, cover: &str) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => conn
+                .query_row(
+                    sql::COVER_IN_USE,
+                    named_params! { ":cover": cover },
+                    |row| row.get(0),
+                )
+                .map_err(|_| KontrollerError::DbField),
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get all posts that are not public, optionally only the posts
+    /// with a status
+    pub fn get_unpublished(
+        &self,
+        status: Option<PostStatus>,
+    ) -> Result<Vec<BlogPost>, KontrollerError> {
+        match status {
+            Some(status) => {
+                self.query_posts(sql::READ_BY_STATUS, named_params! { ":status": status })
+            }
+            None => self.query_posts(sql::READ_UNPUBLISHED, named_params! { ":now": Utc::now() }),
+        }
+    }
+
+    /// Get the blog posts selected by a query
+    fn query_posts(
+        &self,
+        query: &str,
+        params: &[(&str, &dyn ToSql)],
+    ) -> Result<Vec<BlogPost>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut posts: Vec<BlogPost> = vec![];
+                let mut stmt = conn.prepare(query).map_err(|_| KontrollerError::DbSQL)?;
+                let posts_iter = stmt
+                    .query_map(params, blog_post_from_row)
                     .map_err(|_| KontrollerError::DbField)?;
 
                 for post in posts_iter {
-                    posts.push(post.unwrap());
+                    let post = post.map_err(|_| KontrollerError::DbField)?;
+                    posts.push(BlogPost {
+                        tags: post_tags(conn, post.id)?,
+                        co_authors: post_co_authors(conn, post.id)?,
+                        ..post
+                    });
+                }
+
+                Ok(posts)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Set the status and publish date of a blog post, returns `false`
+    /// if the post does not exist or is in the trash
+    pub fn set_status(
+        &self,
+        id: i64,
+        status: PostStatus,
+        date: Option<DateTime<Utc>>,
+    ) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let updated = conn
+                    .execute(sql::UPDATE_STATUS, params![status, date, id])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(updated > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Move a blog post to the trash, returns `false` if the post does
+    /// not exist or is already in the trash
+    pub fn trash(&self, id: i64, now: DateTime<Utc>) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let updated = conn
+                    .execute(sql::TRASH, params![now, id])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(updated > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Take a blog post out of the trash, returns `false` if the post
+    /// is not in the trash
+    pub fn restore_from_trash(&self, id: i64) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let updated = conn
+                    .execute(sql::RESTORE_FROM_TRASH, params![id])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(updated > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get the posts in the trash, most recently deleted first
+    pub fn get_trash(&self) -> Result<Vec<BlogPost>, KontrollerError> {
+        self.query_posts(sql::READ_TRASH, &[])
+    }
+
+    /// Get the ids of the posts moved to the trash before a date
+    pub fn get_expired_trash(&self, before: DateTime<Utc>) -> Result<Vec<i64>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_EXPIRED_TRASH)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let ids = stmt
+                    .query_map(named_params! { ":before": before }, |row| row.get(0))
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                ids.collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Delete article permanently, with its revisions
+    pub fn delete(&mut self, id: i64) -> Result<(), KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                tx.execute(sql::DELETE, &[(":id", &format!("{id}"))])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                for delete in [
+                    sql::DELETE_SLUG_REDIRECTS,
+                    sql::DELETE_POST_TAGS,
+                    sql::DELETE_CO_AUTHORS,
+                    sql::DELETE_REVISIONS,
+                    sql::DELETE_POST_VIEWS,
+                    sql::DELETE_POST_VISITORS,
+                ] {
+                    tx.execute(delete, &[(":id", &id)])
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                }
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Update article, a revision of the updated article is stored.
+    /// `editor` is the username of the account updating the article.
+    pub fn update(
+        &mut self,
+        id: i64,
+        blog: &DatabaseBlogPostInput,
+        editor: Option<&str>,
+    ) -> Result<(), KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                store_original_revision(&tx, id)?;
+                apply_update(&tx, id, blog)?;
+                store_revision(&tx, id, editor, Utc::now())?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTableCreation)?;
+
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Update the fields of a blog post present in a partial update, in
+    /// a single statement. The update is refused with
+    /// [`KontrollerError::VersionConflict`] if the post is no longer at
+    /// the version the update is based on. A revision of the updated
+    /// post is stored, returns `None` if the post does not exist.
+    pub fn patch(
+        &mut self,
+        id: i64,
+        patch: &PatchBlogInput,
+        editor: Option<&str>,
+    ) -> Result<Option<BlogPost>, KontrollerError> {
+        let current = match self.get_blog_by_id(id)? {
+            Some(post) => post,
+            None => return Ok(None),
+        };
+
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                store_original_revision(&tx, id)?;
+
+                let mut columns: Vec<&str> = vec![];
+                let mut values: Vec<Box<dyn ToSql>> = vec![];
+
+                if let Some(title) = &patch.title {
+                    columns.push("title");
+                    values.push(Box::new(title.clone()));
+                }
+                for (column, value) in [
+                    ("subtitle", &patch.subtitle),
+                    ("overview", &patch.overview),
+                    ("author", &patch.author),
+                ] {
+                    if let Some(value) = value {
+                        columns.push(column);
+                        values.push(Box::new(value.clone()));
+                    }
+                }
+                if let Some(cover) = &patch.cover {
+                    columns.extend(["cover", "cover_variants"]);
+                    values.push(Box::new(cover.clone()));
+                    values.push(Box::new(None::<ImageVariants>));
+                }
+
+                // The content is rendered again when it or its format
+                // changes
+                if patch.content.is_some() || patch.format.is_some() {
+                    let format = patch.format.unwrap_or(current.format);
+                    let content =
+                        stored_content(patch.content.as_ref().unwrap_or(&current.content), format);
+                    let content_html = render(&content, format);
+                    let stats = ContentStats::new(&content, format);
+                    columns.extend([
+                        "content",
+                        "format",
+                        "content_html",
+                        "word_count",
+                        "reading_time",
+                        "excerpt",
+                        "search_text",
+                    ]);
+                    values.push(Box::new(content));
+                    values.push(Box::new(format));
+                    values.push(Box::new(content_html));
+                    values.push(Box::new(stats.word_count));
+                    values.push(Box::new(stats.reading_time));
+                    values.push(Box::new(stats.excerpt));
+                    values.push(Box::new(stats.text));
+                }
+
+                // A new slug is generated when the title changes, unless
+                // one is provided
+                let slug = match (&patch.slug, &patch.title) {
+                    (Some(slug), _) => Some(slugify(slug)),
+                    (None, Some(title)) if *title != current.title => Some(slugify(title)),
+                    _ => None,
+                };
+                if let Some(slug) = slug {
+                    let old_slug = Some(current.slug.as_str()).filter(|slug| !slug.is_empty());
+                    if let Some(slug) = redirect_slug(&tx, id, old_slug, &slug)? {
+                        columns.push("slug");
+                        values.push(Box::new(slug));
+                    }
+                }
+
+                let assignments: String = columns
+                    .iter()
+                    .map(|column| format!("{column} = ?, "))
+                    .collect();
+                values.push(Box::new(id));
+                values.push(Box::new(patch.version));
+
+                let updated = tx
+                    .execute(
+                        &format!(
+                            "UPDATE blog SET {assignments}version = version + 1 WHERE id = ? AND version = ?"
+                        ),
+                        params_from_iter(values.iter()),
+                    )
+                    .map_err(|_| KontrollerError::DbField)?;
+                if updated == 0 {
+                    return Err(KontrollerError::VersionConflict);
+                }
+
+                if let Some(names) = &patch.tags {
+                    set_post_tags(&tx, id, names)?;
+                }
+                if let Some(usernames) = &patch.co_authors {
+                    set_post_co_authors(&tx, id, usernames)?;
+                }
+
+                store_revision(&tx, id, editor, Utc::now())?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+            }
+            None => return Err(KontrollerError::DbConnection),
+        }
+
+        self.get_blog_by_id(id)
+    }
+
+    /// Get the revisions of a blog post, newest first
+    pub fn get_revisions(
+        &self,
+        post_id: i64,
+    ) -> Result<Vec<BlogPostRevisionSummary>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_REVISIONS)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let revisions = stmt
+                    .query_map(named_params! { ":post_id": post_id }, |row| {
+                        Ok(BlogPostRevisionSummary {
+                            id: row.get("id")?,
+                            post_id: row.get("post_id")?,
+                            title: row.get("title")?,
+                            editor: row.get("editor")?,
+                            date: row.get("date")?,
+                        })
+                    })
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                revisions
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get a revision of a blog post
+    pub fn get_revision(
+        &self,
+        post_id: i64,
+        id: i64,
+    ) -> Result<Option<BlogPostRevision>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => conn
+                .query_row(
+                    sql::READ_REVISION,
+                    named_params! { ":id": id, ":post_id": post_id },
+                    |row| {
+                        Ok(BlogPostRevision {
+                            id: row.get("id")?,
+                            post_id: row.get("post_id")?,
+                            title: row.get("title")?,
+                            subtitle: row.get("subtitle")?,
+                            overview: row.get("overview")?,
+                            author: row.get("author")?,
+                            cover: row.get("cover")?,
+                            cover_variants: row.get("cover_variants")?,
+                            content: row.get("content")?,
+                            format: row.get("format")?,
+                            slug: row.get("slug")?,
+                            editor: row.get("editor")?,
+                            date: row.get("date")?,
+                            tags: json_list(row, "tags")?,
+                            co_authors: json_list(row, "co_authors")?,
+                            status: row.get("status")?,
+                        })
+                    },
+                )
+                .optional()
+                .map_err(|_| KontrollerError::DbField),
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Make a revision the current version of a blog post, with its
+    /// tags, co-authors and status, the restored version is stored as a
+    /// new revision. Returns `false` if the revision does not exist.
+    /// Revisions stored before their tags, co-authors and status leave
+    /// them as they are.
+    pub fn restore_revision(
+        &mut self,
+        post_id: i64,
+        id: i64,
+        editor: Option<&str>,
+    ) -> Result<bool, KontrollerError> {
+        let revision = match self.get_revision(post_id, id)? {
+            Some(revision) => revision,
+            None => return Ok(false),
+        };
+
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                let blog = DatabaseBlogPostInput {
+                    title: revision.title,
+                    subtitle: None,
+                    overview: None,
+                    author: None,
+                    cover: None,
+                    cover_variants: None,
+                    content: revision.content,
+                    date: None,
+                    slug: revision.slug,
+                    status: PostStatus::default(),
+                    tags: revision.tags,
+                    co_authors: revision.co_authors,
+                    format: Some(revision.format),
+                };
+
+                apply_update(&tx, post_id, &blog)?;
+
+                // Unlike updates, restoring also clears optional fields
+                tx.execute(
+                    sql::RESTORE_OPTIONAL_FIELDS,
+                    params![
+                        revision.subtitle,
+                        revision.overview,
+                        revision.author,
+                        revision.cover,
+                        revision.cover_variants,
+                        post_id
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbSQL)?;
+
+                // The publish date is kept
+                if let Some(status) = revision.status {
+                    tx.execute(sql::RESTORE_STATUS, params![status, post_id])
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                }
+
+                store_revision(&tx, post_id, editor, Utc::now())?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(true)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Create a new tag, fails with [`KontrollerError::DbField`] if a
+    /// tag with the same slug exists
+    pub fn create_tag(&self, name: &str, kind: TagKind) -> Result<Tag, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let name = name.trim();
+                let slug = slugify(name);
+
+                conn.execute(sql::CREATE_TAG, params![name, slug, kind])
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                Ok(Tag {
+                    id: conn.last_insert_rowid(),
+                    name: name.to_string(),
+                    slug,
+                    kind,
+                })
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get a tag by its id
+    pub fn get_tag(&self, id: i64) -> Result<Option<Tag>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => conn
+                .query_row(sql::READ_TAG, named_params! { ":id": id }, tag_from_row)
+                .optional()
+                .map_err(|_| KontrollerError::DbField),
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get a tag by its slug
+    pub fn get_tag_by_slug(&self, slug: &str) -> Result<Option<Tag>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => conn
+                .query_row(
+                    sql::READ_TAG_BY_SLUG,
+                    named_params! { ":slug": slug },
+                    tag_from_row,
+                )
+                .optional()
+                .map_err(|_| KontrollerError::DbField),
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get all tags, optionally only the tags of a kind, with their
+    /// number of public posts
+    pub fn get_tags(
+        &self,
+        kind: Option<TagKind>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<TagCount>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_TAGS)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let tags = stmt
+                    .query_map(named_params! { ":kind": kind, ":now": now }, |row| {
+                        Ok(TagCount {
+                            tag: tag_from_row(row)?,
+                            posts: row.get("posts")?,
+                        })
+                    })
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                tags.collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Rename a tag, its slug is generated from the new name. The kind
+    /// of the tag is changed if one is provided. Returns `None` if the
+    /// tag does not exist, fails with [`KontrollerError::DbField`] if
+    /// another tag has the same slug.
+    pub fn rename_tag(
+        &self,
+        id: i64,
+        name: &str,
+        kind: Option<TagKind>,
+    ) -> Result<Option<Tag>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let name = name.trim();
+                let updated = conn
+                    .execute(sql::UPDATE_TAG, params![name, slugify(name), kind, id])
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                if updated > 0 {
+                    self.get_tag(id)
+                } else {
+                    Ok(None)
+                }
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Merge the tag `from` into the tag `into`: the posts with `from`
+    /// get `into` and `from` is deleted. Returns `false` if either tag
+    /// does not exist.
+    pub fn merge_tags(&mut self, from: i64, into: i64) -> Result<bool, KontrollerError> {
+        if self.get_tag(from)?.is_none() || self.get_tag(into)?.is_none() {
+            return Ok(false);
+        }
+
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                tx.execute(sql::MERGE_POST_TAGS, params![from, into])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                tx.execute(sql::DELETE_TAG_POSTS, params![from])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                tx.execute(sql::DELETE_TAG, params![from])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(true)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Delete a tag and remove it from all posts, returns `false` if
+    /// the tag does not exist
+    pub fn delete_tag(&mut self, id: i64) -> Result<bool, KontrollerError> {
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                tx.execute(sql::DELETE_TAG_POSTS, params![id])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let deleted = tx
+                    .execute(sql::DELETE_TAG, params![id])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
+
+                Ok(deleted > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Add an uploaded file to the media library
+    pub fn create_media(
+        &self,
+        input: &MediaFileInput,
+        uploader: &str,
+        date: DateTime<Utc>,
+    ) -> Result<MediaFile, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let image = &input.image;
+                let variants = Some(&image.variants).filter(|variants| !variants.0.is_empty());
+
+                conn.execute(
+                    sql::CREATE_MEDIA,
+                    params![
+                        image.url,
+                        input.name.trim(),
+                        input.alt.as_deref().filter(|alt| !alt.is_empty()),
+                        image.media_type,
+                        image.width,
+                        image.height,
+                        image.size as i64,
+                        variants,
+                        uploader,
+                        date
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+
+                self.get_media_file(conn.last_insert_rowid())?
+                    .ok_or(KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get a file of the media library, with the posts using it
+    pub fn get_media_file(&self, id: i64) -> Result<Option<MediaFile>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let file = conn
+                    .query_row(sql::READ_MEDIA, named_params! { ":id": id }, |row| {
+                        media_file_from_row(row)
+                    })
+                    .optional()
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                match file {
+                    Some(file) => Ok(Some(MediaFile {
+                        used_by: self.get_media_usage(&file)?,
+                        ..file
+                    })),
+                    None => Ok(None),
+                }
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get all files of the media library, newest first, with the posts
+    /// using them
+    pub fn get_media_files(&self) -> Result<Vec<MediaFile>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_ALL_MEDIA)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let files = stmt
+                    .query_map([], media_file_from_row)
+                    .map_err(|_| KontrollerError::DbField)?
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                files
+                    .into_iter()
+                    .map(|file| {
+                        Ok(MediaFile {
+                            used_by: self.get_media_usage(&file)?,
+                            ..file
+                        })
+                    })
+                    .collect()
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get the blog posts using a media library file, or one of its
+    /// variants, as their cover or in their content. Posts with a
+    /// revision using the file are included, restoring it would show a
+    /// missing image
+    pub fn get_media_usage(&self, file: &MediaFile) -> Result<Vec<MediaUsage>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_MEDIA_USAGE)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                let mut posts: Vec<MediaUsage> = vec![];
+                for url in file.urls() {
+                    let rows = stmt
+                        .query_map(named_params! { ":url": url }, |row| {
+                            Ok(MediaUsage {
+                                id: row.get(0)?,
+                                title: row.get(1)?,
+                                slug: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                            })
+                        })
+                        .map_err(|_| KontrollerError::DbField)?;
+
+                    for post in rows {
+                        let post = post.map_err(|_| KontrollerError::DbField)?;
+                        if !posts.iter().any(|used| used.id == post.id) {
+                            posts.push(post);
+                        }
+                    }
                 }
+                posts.sort_by_key(|post| post.id);
 
                 Ok(posts)
             }
@@ -178,79 +2286,139 @@ impl Database {
         }
     }
 
-    /// Delete article
-    pub fn delete(&self, id: i64) -> Result<(), KontrollerError> {
+    /// Rename a media library file or change its alternative text,
+    /// returns `false` if the file does not exist
+    pub fn update_media(&self, id: i64, input: &UpdateMediaInput) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let updated = conn
+                    .execute(
+                        sql::UPDATE_MEDIA,
+                        params![input.name.as_deref().map(str::trim), input.alt, id],
+                    )
+                    .map_err(|_| KontrollerError::DbField)?;
+                Ok(updated > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Remove a file from the media library, the stored file is left
+    /// untouched. Returns `false` if the file does not exist.
+    pub fn delete_media(&self, id: i64) -> Result<bool, KontrollerError> {
         match &self.conn {
             Some(conn) => {
-                conn.execute(sql::DELETE, &[(":id", &format!("{id}"))])
+                let deleted = conn
+                    .execute(sql::DELETE_MEDIA, named_params! { ":id": id })
                     .map_err(|_| KontrollerError::DbSQL)?;
-                Ok(())
+                Ok(deleted > 0)
             }
             None => Err(KontrollerError::DbConnection),
         }
     }
 
-    /// Update article
-    pub fn update(&mut self, id: i64, blog: &DatabaseBlogPostInput) -> Result<(), KontrollerError> {
+    /// Count a view of a blog post, unless the visitor's view was
+    /// already counted in the time window. Returns `true` if the view
+    /// was counted.
+    pub fn record_view(
+        &mut self,
+        post_id: i64,
+        visitor: &str,
+        now: DateTime<Utc>,
+        window: Duration,
+    ) -> Result<bool, KontrollerError> {
         match &mut self.conn {
             Some(conn) => {
                 let tx = conn
                     .transaction()
                     .map_err(|_| KontrollerError::DbTransaction)?;
 
-                // Update title
-                tx.execute(
-                    "UPDATE blog SET title = ?1 WHERE id = ?2",
-                    &[&blog.title, &format!("{id}")],
-                )
-                .map_err(|_| KontrollerError::DbTableCreation)?;
+                tx.execute(sql::DELETE_EXPIRED_VISITORS, params![now - window])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let new_visitor = tx
+                    .execute(sql::CREATE_VISITOR, params![post_id, visitor, now])
+                    .map_err(|_| KontrollerError::DbSQL)?
+                    > 0;
+                if new_visitor {
+                    tx.execute(sql::COUNT_VIEW, params![post_id, now.date_naive()])
+                        .map_err(|_| KontrollerError::DbSQL)?;
+                }
 
-                // Update content
-                tx.execute(
-                    "UPDATE blog SET content = ?1 WHERE id = ?2",
-                    &[&blog.content, &format!("{id}")],
-                )
-                .map_err(|_| KontrollerError::DbTableCreation)?;
+                tx.commit().map_err(|_| KontrollerError::DbTransaction)?;
 
-                // Update subtitle
-                if let Some(subtitle) = &blog.subtitle {
-                    tx.execute(
-                        "UPDATE blog SET subtitle = ?1 WHERE id = ?2",
-                        &[subtitle, &format!("{id}")],
-                    )
-                    .map_err(|_| KontrollerError::DbTableCreation)?;
-                }
+                Ok(new_visitor)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
 
-                // Update overview
-                if let Some(overview) = &blog.overview {
-                    tx.execute(
-                        "UPDATE blog SET overview = ?1 WHERE id = ?2",
-                        &[overview, &format!("{id}")],
+    /// Get the most viewed public posts of a period, most viewed first
+    pub fn get_top_posts(
+        &self,
+        query: &AnalyticsQuery,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<PostViews>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_TOP_POSTS)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let posts = stmt
+                    .query_map(
+                        named_params! {
+                            ":now": now,
+                            ":from": query.from,
+                            ":to": query.to,
+                            ":limit": query.limit(),
+                        },
+                        |row| {
+                            Ok(PostViews {
+                                id: row.get(0)?,
+                                title: row.get(1)?,
+                                slug: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                                views: row.get(3)?,
+                            })
+                        },
                     )
-                    .map_err(|_| KontrollerError::DbTableCreation)?;
-                }
+                    .map_err(|_| KontrollerError::DbField)?;
 
-                // Update author
-                if let Some(author) = &blog.author {
-                    tx.execute(
-                        "UPDATE blog SET author = ?1 WHERE id = ?2",
-                        &[author, &format!("{id}")],
-                    )
-                    .map_err(|_| KontrollerError::DbTableCreation)?;
-                }
+                posts
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
 
-                // Update cover
-                if let Some(cover) = &blog.cover {
-                    tx.execute(
-                        "UPDATE blog SET cover = ?1 WHERE id = ?2",
-                        &[cover, &format!("{id}")],
+    /// Get the views of the days of a period that have views, optionally
+    /// of a single post
+    pub fn get_daily_views(
+        &self,
+        query: &AnalyticsQuery,
+    ) -> Result<Vec<DailyViews>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn
+                    .prepare(sql::READ_DAILY_VIEWS)
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                let days = stmt
+                    .query_map(
+                        named_params! {
+                            ":post_id": query.post_id,
+                            ":from": query.from,
+                            ":to": query.to,
+                        },
+                        |row| {
+                            Ok(DailyViews {
+                                date: row.get(0)?,
+                                views: row.get(1)?,
+                            })
+                        },
                     )
-                    .map_err(|_| KontrollerError::DbTableCreation)?;
-                }
-
-                tx.commit().map_err(|_| KontrollerError::DbTableCreation)?;
+                    .map_err(|_| KontrollerError::DbField)?;
 
-                Ok(())
+                days.collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
             }
             None => Err(KontrollerError::DbConnection),
         }
@@ -260,13 +2428,16 @@ impl Database {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::media::variants::{ImageVariant, StoredImage};
+    use crate::media::MediaType;
+    use crate::test_utils::{blog_input, TempDatabase};
     use chrono::Utc;
-
-    const TEST_DB_PATH: &str = "BLOG_TEST_DATABASE.sqlite";
+    use kong::json_from_str;
 
     #[test]
     fn connect_db() {
-        let mut db = Database::new(TEST_DB_PATH);
+        let db_file = TempDatabase::new("BLOG_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
 
         // Connect to database
         db.connect().unwrap();
@@ -279,35 +2450,803 @@ mod test {
 
     #[test]
     fn test_store_get_account_blog() {
-        remove_test_db();
-        let mut db = Database::new(TEST_DB_PATH);
+        let db_file = TempDatabase::new("BLOG_STORE_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
 
         let blog = DatabaseBlogPostInput {
             title: "Test Title".to_string(),
             subtitle: Some("Test subtitle".to_string()),
             overview: Some("Test overview".to_string()),
             author: Some("Test author".to_string()),
-            cover: None,
             content: "Test content".to_string(),
-            date: None,
+            ..blog_input()
         };
 
         db.connect().unwrap();
-        db.create_blog(&blog).unwrap();
+        let post = db.create_blog(&blog).unwrap();
 
-        let res = db.get_blog_by_id(1).unwrap();
+        let res = db.get_blog_by_id(post.id).unwrap();
 
         if let Some(r) = res {
-            assert_eq!(r, blog)
+            assert_eq!(r, post)
         } else {
             panic!("Account not found")
         }
     }
 
-    fn remove_test_db() {
-        let test_db_path = std::path::Path::new(TEST_DB_PATH);
-        if std::path::Path::exists(test_db_path) {
-            std::fs::remove_file(test_db_path).unwrap();
+    #[test]
+    fn test_blog_slugs() {
+        let db_file = TempDatabase::new("BLOG_SLUG_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Hello World".to_string(),
+            content: "Test content".to_string(),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let first = db.create_blog(&blog).unwrap();
+        let second = db.create_blog(&blog).unwrap();
+        assert_eq!(first.slug, "hello-world");
+        assert_eq!(second.slug, "hello-world-2");
+
+        // Changing the title changes the slug, the old slug redirects
+        blog.title = "Goodbye World".to_string();
+        db.update(first.id, &blog, None).unwrap();
+        let post = db.get_blog_by_slug("goodbye-world").unwrap().unwrap();
+        assert_eq!(post.id, first.id);
+        assert_eq!(
+            db.get_slug_redirect("hello-world").unwrap(),
+            Some("goodbye-world".to_string())
+        );
+
+        // The old slug is still reserved for the first post
+        let third = db.create_blog(&blog).unwrap();
+        assert_eq!(third.slug, "goodbye-world-2");
+    }
+
+    #[test]
+    fn test_blog_publishing() {
+        let db_file = TempDatabase::new("BLOG_PUBLISH_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        let now = Utc::now();
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Draft".to_string(),
+            content: "Test content".to_string(),
+            status: PostStatus::Draft,
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let draft = db.create_blog(&blog).unwrap();
+
+        blog.title = "Scheduled".to_string();
+        blog.status = PostStatus::Scheduled;
+        blog.date = Some(now + chrono::Duration::days(1));
+        let scheduled = db.create_blog(&blog).unwrap();
+
+        blog.title = "Published".to_string();
+        blog.status = PostStatus::Published;
+        blog.date = Some(now);
+        let published = db.create_blog(&blog).unwrap();
+
+        let public: Vec<i64> = db.get_all().unwrap().iter().map(|p| p.id).collect();
+        assert_eq!(public, vec![published.id]);
+
+        let unpublished: Vec<i64> = db
+            .get_unpublished(None)
+            .unwrap()
+            .iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(unpublished, vec![scheduled.id, draft.id]);
+
+        // Publishing the draft makes it public
+        assert!(db
+            .set_status(draft.id, PostStatus::Published, Some(now))
+            .unwrap());
+        assert_eq!(db.get_all().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_blog_pagination() {
+        let db_file = TempDatabase::new("BLOG_PAGE_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        let now = Utc::now();
+
+        db.connect().unwrap();
+        for i in 0..5 {
+            db.create_blog(&DatabaseBlogPostInput {
+                title: format!("Post {i}"),
+                author: Some(if i % 2 == 0 { "even" } else { "odd" }.to_string()),
+                content: "Test content".to_string(),
+                date: Some(now - chrono::Duration::hours(5 - i)),
+                ..blog_input()
+            })
+            .unwrap();
         }
+
+        let mut query = BlogPostQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = db.get_page(&query, now).unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!(
+            page.posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![5, 4]
+        );
+
+        // The cursor continues after the last post of the page
+        query.cursor = page.next_cursor;
+        let page = db.get_page(&query, now).unwrap();
+        assert_eq!(
+            page.posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+
+        let query = BlogPostQuery {
+            author: Some("even".to_string()),
+            sort: Some(PostSort::Oldest),
+            page: Some(2),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = db.get_page(&query, now).unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.posts.iter().map(|p| p.id).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(page.next_cursor, None);
+
+        // Pages far past the end are refused before an offset is computed
+        assert!(query.is_valid());
+        let query = BlogPostQuery {
+            page: Some(i64::MAX),
+            ..query
+        };
+        assert!(!query.is_valid());
+
+        // Posts without a date are the oldest, and reached by the cursor
+        let undated = db
+            .create_blog(&DatabaseBlogPostInput {
+                title: "Undated".to_string(),
+                content: "Test content".to_string(),
+                ..blog_input()
+            })
+            .unwrap();
+        let mut query = BlogPostQuery {
+            limit: Some(2),
+            cursor: Some(2),
+            ..Default::default()
+        };
+        let page = db.get_page(&query, now).unwrap();
+        assert_eq!(
+            page.posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![1, undated.id]
+        );
+
+        query.sort = Some(PostSort::Oldest);
+        query.cursor = Some(undated.id);
+        let page = db.get_page(&query, now).unwrap();
+        assert_eq!(
+            page.posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn test_blog_tags() {
+        let db_file = TempDatabase::new("BLOG_TAG_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        let now = Utc::now();
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Tagged".to_string(),
+            content: "Test content".to_string(),
+            date: Some(now),
+            tags: Some(vec![
+                "Rust".to_string(),
+                "Web Dev".to_string(),
+                "日本".to_string(),
+            ]),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let news = db.create_tag("News", TagKind::Category).unwrap();
+        assert!(db.create_tag("news", TagKind::Tag).is_err());
+
+        let first = db.create_blog(&blog).unwrap();
+        let names: Vec<&str> = first.tags.iter().map(|t| t.name.as_str()).collect();
+        // Names a slug can not be made from are left out
+        assert_eq!(names, vec!["Rust", "Web Dev"]);
+
+        blog.tags = Some(vec!["rust".to_string(), "News".to_string()]);
+        let second = db.create_blog(&blog).unwrap();
+        assert_eq!(second.tags.len(), 2);
+
+        let counts: Vec<(String, i64)> = db
+            .get_tags(None, now)
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.tag.slug, t.posts))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("news".to_string(), 1),
+                ("rust".to_string(), 2),
+                ("web-dev".to_string(), 1)
+            ]
+        );
+
+        let query = BlogPostQuery {
+            tag: Some("web-dev".to_string()),
+            ..Default::default()
+        };
+        let page = db.get_page(&query, now).unwrap();
+        assert_eq!(
+            page.posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![first.id]
+        );
+
+        // Merging moves the posts to the other tag
+        let web_dev = db.get_tag_by_slug("web-dev").unwrap().unwrap();
+        assert!(db.merge_tags(web_dev.id, news.id).unwrap());
+        assert!(db.get_tag_by_slug("web-dev").unwrap().is_none());
+        let news_posts = db
+            .get_tags(Some(TagKind::Category), now)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.posts)
+            .collect::<Vec<_>>();
+        assert_eq!(news_posts, vec![2]);
+
+        let renamed = db.rename_tag(news.id, "Updates", None).unwrap().unwrap();
+        assert_eq!(renamed.slug, "updates");
+        assert_eq!(renamed.kind, TagKind::Category);
+
+        assert!(db.delete_tag(renamed.id).unwrap());
+        let post = db.get_blog_by_id(second.id).unwrap().unwrap();
+        let names: Vec<&str> = post.tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["Rust"]);
+    }
+
+    #[test]
+    fn test_blog_revisions() {
+        let db_file = TempDatabase::new("BLOG_REVISION_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Original".to_string(),
+            content: "First draft".to_string(),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let post = db.create_blog(&blog).unwrap();
+
+        blog.title = "Edited".to_string();
+        blog.subtitle = Some("Now with a subtitle".to_string());
+        blog.content = "Second draft".to_string();
+        blog.tags = Some(vec!["Drafts".to_string()]);
+        blog.co_authors = Some(vec!["bob".to_string()]);
+        db.update(post.id, &blog, Some("editor")).unwrap();
+        db.set_status(post.id, PostStatus::Archived, None).unwrap();
+
+        // The original version is kept before the first update
+        let revisions = db.get_revisions(post.id).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].editor.as_deref(), Some("editor"));
+        assert_eq!(revisions[1].editor, None);
+        assert_eq!(revisions[1].title, "Original");
+
+        let edited = db.get_revision(post.id, revisions[0].id).unwrap().unwrap();
+        assert_eq!(edited.tags, Some(vec!["Drafts".to_string()]));
+        assert_eq!(edited.co_authors, Some(vec!["bob".to_string()]));
+        assert_eq!(edited.status, Some(PostStatus::Published));
+
+        assert!(db
+            .restore_revision(post.id, revisions[1].id, Some("admin"))
+            .unwrap());
+        let restored = db.get_blog_by_id(post.id).unwrap().unwrap();
+        assert_eq!(restored.title, "Original");
+        assert_eq!(restored.slug, "original");
+        assert_eq!(restored.subtitle, None);
+        assert_eq!(restored.content, "First draft");
+        assert!(restored.tags.is_empty());
+        assert!(restored.co_authors.is_empty());
+        assert_eq!(restored.status, PostStatus::Published);
+        assert_eq!(db.get_revisions(post.id).unwrap().len(), 3);
+
+        // Revisions stored before their tags leave them unchanged
+        db.update(post.id, &blog, None).unwrap();
+        db.conn
+            .as_ref()
+            .unwrap()
+            .execute("UPDATE blog_revisions SET tags = NULL, status = NULL", ())
+            .unwrap();
+        assert!(db.restore_revision(post.id, revisions[1].id, None).unwrap());
+        let restored = db.get_blog_by_id(post.id).unwrap().unwrap();
+        assert_eq!(restored.tags[0].name, "Drafts");
+        assert!(restored.co_authors.is_empty());
+
+        assert!(!db.restore_revision(post.id, 42, None).unwrap());
+    }
+
+    #[test]
+    fn test_blog_content_formats() {
+        let db_file = TempDatabase::new("BLOG_FORMAT_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Formats".to_string(),
+            content: "<p>Hi</p><script>alert(1)</script>".to_string(),
+            format: Some(ContentFormat::Html),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+
+        // HTML content is sanitized before it is stored
+        let post = db.create_blog(&blog).unwrap();
+        assert_eq!(post.content, "<p>Hi</p>");
+        assert_eq!(db.get_blog_by_id(post.id).unwrap().unwrap(), post);
+
+        // Markdown content is stored as written
+        blog.content = "**Hi**".to_string();
+        blog.format = Some(ContentFormat::Markdown);
+        db.update(post.id, &blog, None).unwrap();
+        let post = db.get_blog_by_id(post.id).unwrap().unwrap();
+        assert_eq!(post.content, "**Hi**");
+        assert_eq!(post.content_html, "<p><strong>Hi</strong></p>\n");
+    }
+
+    #[test]
+    fn test_blog_co_authors() {
+        let db_file = TempDatabase::new("BLOG_AUTHORS_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Written together".to_string(),
+            author: Some("alice".to_string()),
+            co_authors: Some(vec!["carol".to_string(), "bob".to_string()]),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+
+        let post = db.create_blog(&blog).unwrap();
+        assert_eq!(post.co_authors, vec!["carol", "bob"]);
+        assert_eq!(db.get_blog_by_id(post.id).unwrap().unwrap(), post);
+
+        // Co-authored posts are listed with the posts of the co-author
+        let query = BlogPostQuery {
+            author: Some("bob".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(db.get_page(&query, Utc::now()).unwrap().total, 1);
+
+        blog.co_authors = Some(vec!["carol".to_string()]);
+        db.update(post.id, &blog, None).unwrap();
+        assert_eq!(db.get_page(&query, Utc::now()).unwrap().total, 0);
+
+        // Co-authors are left unchanged if none are given
+        blog.co_authors = None;
+        db.update(post.id, &blog, None).unwrap();
+        let post = db.get_blog_by_id(post.id).unwrap().unwrap();
+        assert_eq!(post.co_authors, vec!["carol"]);
+    }
+
+    #[test]
+    fn test_blog_search() {
+        let db_file = TempDatabase::new("BLOG_SEARCH_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Rust & SQLite".to_string(),
+            overview: Some("Embedding a database".to_string()),
+            author: Some("admin".to_string()),
+            content: "Full-text search with FTS5.".to_string(),
+            tags: Some(vec!["Databases".to_string()]),
+            format: Some(ContentFormat::Markdown),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+
+        let rust = db.create_blog(&blog).unwrap();
+        blog.title = "Gardening".to_string();
+        blog.overview = None;
+        blog.content = "Some words about rust on tools.".to_string();
+        blog.tags = None;
+        let gardening = db.create_blog(&blog).unwrap();
+        blog.title = "Rust draft".to_string();
+        blog.status = PostStatus::Draft;
+        db.create_blog(&blog).unwrap();
+
+        let mut query = SearchQuery {
+            q: "rust".to_string(),
+            ..Default::default()
+        };
+        let now = Utc::now();
+
+        // Drafts are not found, matches in titles rank first
+        let page = db.search(&query, now).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.posts[0].post.id, rust.id);
+        assert_eq!(
+            page.posts[0].title_highlight,
+            "<mark>Rust</mark> &amp; SQLite"
+        );
+        assert!(page.posts[1].snippet.contains("<mark>rust</mark>"));
+
+        query.tag = Some("databases".to_string());
+        assert_eq!(db.search(&query, now).unwrap().total, 1);
+
+        // The index follows updates and deletions
+        query.tag = None;
+        query.q = "tomatoes".to_string();
+        blog.status = PostStatus::Published;
+        blog.content = "Growing tomatoes".to_string();
+        db.update(gardening.id, &blog, None).unwrap();
+        assert_eq!(
+            db.search(&query, now).unwrap().posts[0].post.id,
+            gardening.id
+        );
+
+        db.delete(gardening.id).unwrap();
+        assert_eq!(db.search(&query, now).unwrap().total, 0);
+
+        // The markup of HTML content is not indexed
+        blog.title = "Links".to_string();
+        blog.content =
+            "<p class=\"note\">See <a href=\"https://example.com\">the orchard</a></p>".to_string();
+        blog.format = Some(ContentFormat::Html);
+        let links = db.create_blog(&blog).unwrap();
+        query.q = "href".to_string();
+        assert_eq!(db.search(&query, now).unwrap().total, 0);
+        query.q = "orchard".to_string();
+        let page = db.search(&query, now).unwrap();
+        assert_eq!(page.posts[0].post.id, links.id);
+        assert!(page.posts[0].snippet.contains("<mark>orchard</mark>"));
+        assert!(!page.posts[0].snippet.contains("href"));
+    }
+
+    #[test]
+    fn test_blog_covers() {
+        let db_file = TempDatabase::new("BLOG_COVERS_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Covered".to_string(),
+            cover: Some("uploads/covers/first.png".to_string()),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let post = db.create_blog(&blog).unwrap();
+
+        // Replaced covers are still used by the revisions
+        let variants = ImageVariants(
+            [(
+                "thumbnail".to_string(),
+                ImageVariant {
+                    url: "uploads/covers/second-thumbnail.png".to_string(),
+                    webp: "uploads/covers/second-thumbnail.webp".to_string(),
+                    width: 320,
+                    height: 200,
+                },
+            )]
+            .into(),
+        );
+        blog.cover = Some("uploads/covers/second.png".to_string());
+        blog.cover_variants = Some(variants.clone());
+        db.update(post.id, &blog, None).unwrap();
+        assert_eq!(
+            db.get_blog_by_id(post.id).unwrap().unwrap().cover_variants,
+            Some(variants.clone())
+        );
+
+        let mut covers = db.get_post_covers(post.id).unwrap();
+        covers.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            covers,
+            vec![
+                ("uploads/covers/first.png".to_string(), None),
+                ("uploads/covers/second.png".to_string(), Some(variants))
+            ]
+        );
+        assert_eq!(db.get_covers().unwrap().len(), 2);
+        assert!(db.cover_in_use("uploads/covers/first.png").unwrap());
+
+        db.delete(post.id).unwrap();
+        assert!(!db.cover_in_use("uploads/covers/first.png").unwrap());
+        assert!(db.get_covers().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_blog_media_library() {
+        let db_file = TempDatabase::new("BLOG_MEDIA_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+
+        let input = MediaFileInput {
+            image: StoredImage {
+                url: "uploads/library/diagram.png".to_string(),
+                media_type: MediaType::Png,
+                size: 2048,
+                width: 640,
+                height: 480,
+                variants: ImageVariants::default(),
+            },
+            name: " Diagram ".to_string(),
+            alt: Some("A diagram".to_string()),
+        };
+        let file = db.create_media(&input, "author", Utc::now()).unwrap();
+        assert_eq!(file.name, "Diagram");
+        assert_eq!(file.variants, None);
+        assert!(db.create_media(&input, "author", Utc::now()).is_err());
+
+        // Posts using the file, in their content or as their cover
+        let blog = DatabaseBlogPostInput {
+            title: "Illustrated".to_string(),
+            content: "![A diagram](/uploads/library/diagram.png)".to_string(),
+            status: PostStatus::Draft,
+            ..blog_input()
+        };
+        let post = db.create_blog(&blog).unwrap();
+        let files = db.get_media_files().unwrap();
+        assert_eq!(files[0].used_by.len(), 1);
+        assert_eq!(files[0].used_by[0].slug, post.slug);
+
+        // Revisions of a post still use the file
+        let mut edited = blog.clone();
+        edited.content = "No more diagram".to_string();
+        db.update(post.id, &edited, None).unwrap();
+        let files = db.get_media_files().unwrap();
+        assert_eq!(files[0].used_by.len(), 1);
+        assert_eq!(files[0].used_by[0].id, post.id);
+
+        let update = UpdateMediaInput {
+            name: Some("Architecture".to_string()),
+            alt: Some(String::new()),
+        };
+        assert!(db.update_media(file.id, &update).unwrap());
+        let file = db.get_media_file(file.id).unwrap().unwrap();
+        assert_eq!(file.name, "Architecture");
+        assert_eq!(file.alt, None);
+
+        assert!(db.delete_media(file.id).unwrap());
+        assert!(db.get_media_file(file.id).unwrap().is_none());
+        assert!(!db.update_media(file.id, &update).unwrap());
+    }
+
+    #[test]
+    fn test_blog_trash() {
+        let db_file = TempDatabase::new("BLOG_TRASH_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        let now = Utc::now();
+
+        let blog = DatabaseBlogPostInput {
+            title: "Oops".to_string(),
+            content: "Deleted by accident".to_string(),
+            tags: Some(vec!["Mistakes".to_string()]),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let post = db.create_blog(&blog).unwrap();
+        let other = db.create_blog(&blog).unwrap();
+
+        // Posts in the trash are hidden from the public listings
+        assert!(db.trash(post.id, now - chrono::Duration::days(40)).unwrap());
+        assert!(!db.trash(post.id, now).unwrap());
+        assert_eq!(db.get_all().unwrap().len(), 1);
+        assert_eq!(db.get_tags(None, now).unwrap()[0].posts, 1);
+        let trashed = db.get_blog_by_id(post.id).unwrap().unwrap();
+        assert!(!trashed.is_public(now));
+        assert!(!db.set_status(post.id, PostStatus::Draft, None).unwrap());
+
+        // Restored posts come back as they were
+        assert!(db.restore_from_trash(post.id).unwrap());
+        assert!(!db.restore_from_trash(post.id).unwrap());
+        assert!(db.get_blog_by_id(post.id).unwrap().unwrap().is_public(now));
+        assert_eq!(db.get_all().unwrap().len(), 2);
+
+        db.trash(post.id, now - chrono::Duration::days(40)).unwrap();
+        db.trash(other.id, now).unwrap();
+        let trash = db.get_trash().unwrap();
+        assert_eq!(
+            trash.iter().map(|post| post.id).collect::<Vec<_>>(),
+            vec![other.id, post.id]
+        );
+        assert_eq!(
+            db.get_expired_trash(now - chrono::Duration::days(30))
+                .unwrap(),
+            vec![post.id]
+        );
+    }
+
+    #[test]
+    fn test_blog_views() {
+        let db_file = TempDatabase::new("BLOG_VIEWS_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        let now = Utc::now();
+        let window = chrono::Duration::minutes(30);
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Popular".to_string(),
+            content: "Everyone reads this".to_string(),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let popular = db.create_blog(&blog).unwrap();
+        blog.title = "Niche".to_string();
+        let niche = db.create_blog(&blog).unwrap();
+
+        // Visitors are counted once per window
+        let yesterday = now - chrono::Duration::days(1);
+        assert!(db.record_view(popular.id, "a", yesterday, window).unwrap());
+        assert!(!db
+            .record_view(
+                popular.id,
+                "a",
+                yesterday + chrono::Duration::minutes(10),
+                window
+            )
+            .unwrap());
+        assert!(db.record_view(popular.id, "a", now, window).unwrap());
+        assert!(db.record_view(popular.id, "b", now, window).unwrap());
+        assert!(db.record_view(niche.id, "a", now, window).unwrap());
+
+        let query = AnalyticsQuery::default();
+        let top = db.get_top_posts(&query, now).unwrap();
+        assert_eq!(
+            top.iter()
+                .map(|post| (post.id, post.views))
+                .collect::<Vec<_>>(),
+            vec![(popular.id, 3), (niche.id, 1)]
+        );
+
+        let query = AnalyticsQuery {
+            post_id: Some(popular.id),
+            ..Default::default()
+        };
+        let days = db.get_daily_views(&query).unwrap();
+        assert_eq!(
+            days.iter()
+                .map(|day| (day.date, day.views))
+                .collect::<Vec<_>>(),
+            vec![(yesterday.date_naive(), 1), (now.date_naive(), 2)]
+        );
+
+        let query = AnalyticsQuery {
+            from: Some(now.date_naive()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(db.get_top_posts(&query, now).unwrap()[0].views, 2);
+
+        // The views are deleted with the post
+        db.delete(popular.id).unwrap();
+        assert_eq!(
+            db.get_top_posts(&AnalyticsQuery::default(), now)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Only public posts are reported
+        db.set_status(niche.id, PostStatus::Draft, None).unwrap();
+        assert!(db
+            .get_top_posts(&AnalyticsQuery::default(), now)
+            .unwrap()
+            .is_empty());
+        db.set_status(niche.id, PostStatus::Published, None)
+            .unwrap();
+        db.trash(niche.id, now).unwrap();
+        assert!(db
+            .get_top_posts(&AnalyticsQuery::default(), now)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_blog_patch() {
+        let db_file = TempDatabase::new("BLOG_PATCH_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        let blog = DatabaseBlogPostInput {
+            title: "Patchwork".to_string(),
+            subtitle: Some("Pieces".to_string()),
+            overview: Some("Sewn together".to_string()),
+            cover: Some("uploads/covers/quilt.png".to_string()),
+            content: "*Squares*".to_string(),
+            format: Some(ContentFormat::Markdown),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let post = db.create_blog(&blog).unwrap();
+        assert_eq!(post.version, 1);
+
+        // Absent fields are kept, null fields are removed
+        let patch: PatchBlogInput =
+            json_from_str(r#"{ "version": 1, "subtitle": null, "cover": null, "title": "Quilt" }"#)
+                .unwrap();
+        let patched = db.patch(post.id, &patch, Some("editor")).unwrap().unwrap();
+        assert_eq!(patched.title, "Quilt");
+        assert_eq!(patched.slug, "quilt");
+        assert_eq!(patched.subtitle, None);
+        assert_eq!(patched.cover, None);
+        assert_eq!(patched.overview, post.overview);
+        assert_eq!(patched.content_html, post.content_html);
+        assert_eq!(patched.version, 2);
+        assert_eq!(
+            db.get_slug_redirect("patchwork").unwrap(),
+            Some("quilt".to_string())
+        );
+
+        // Changes based on an old version are refused
+        let patch: PatchBlogInput =
+            json_from_str(r#"{ "version": 1, "content": "Circles" }"#).unwrap();
+        assert!(matches!(
+            db.patch(post.id, &patch, None),
+            Err(KontrollerError::VersionConflict)
+        ));
+        assert_eq!(
+            db.get_blog_by_id(post.id).unwrap().unwrap().content,
+            "*Squares*"
+        );
+
+        let patch = PatchBlogInput {
+            version: 2,
+            content: Some("Circles".to_string()),
+            ..Default::default()
+        };
+        let patched = db.patch(post.id, &patch, None).unwrap().unwrap();
+        assert_eq!(patched.content_html, "<p>Circles</p>\n");
+        assert_eq!(patched.version, 3);
+        assert_eq!(db.get_revisions(post.id).unwrap().len(), 3);
+        assert!(db.patch(42, &patch, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_blog_reading_stats() {
+        let db_file = TempDatabase::new("BLOG_READING_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        let mut blog = DatabaseBlogPostInput {
+            title: "Short read".to_string(),
+            content: "## A *short* post\n\nOnly a few words.".to_string(),
+            ..blog_input()
+        };
+
+        db.connect().unwrap();
+        let post = db.create_blog(&blog).unwrap();
+        assert_eq!((post.word_count, post.reading_time), (7, 1));
+        assert_eq!(post.excerpt, "A short post Only a few words.");
+        assert_eq!(db.get_blog_by_id(post.id).unwrap().unwrap(), post);
+
+        // The statistics follow the content, the overview is the excerpt
+        blog.content = "word ".repeat(401);
+        blog.overview = Some("Longer now".to_string());
+        db.update(post.id, &blog, None).unwrap();
+        let updated = db.get_blog_by_id(post.id).unwrap().unwrap();
+        assert_eq!((updated.word_count, updated.reading_time), (401, 3));
+        assert_eq!(updated.excerpt, "Longer now");
+
+        let patch = PatchBlogInput {
+            version: updated.version,
+            overview: Some(None),
+            content: Some("Back to *short*".to_string()),
+            ..Default::default()
+        };
+        let patched = db.patch(post.id, &patch, None).unwrap().unwrap();
+        assert_eq!(patched.word_count, 3);
+        assert_eq!(patched.excerpt, "Back to short");
     }
 }
@@ -1,8 +1,17 @@
 //! # Delete single article post kontroller
+//!
+//! Only admin accounts can delete blog posts, see [`super::roles`].
+//! Deleted posts are moved to the trash, where they can be restored
+//! until they are purged, see [`super::trash`]. The posts in the trash
+//! for longer than the retention period are purged with every delete.
 
 use super::database::Database;
+use super::trash::{purge_expired_trash, PurgeHook};
 use crate::accounts::database::Database as AccountsDatabase;
-use crate::login::is_admin;
+use crate::error::KontrollerError;
+use crate::login::{is_admin, kpassport_error};
+use crate::media::storage::MediaStorage;
+use chrono::{Duration, Utc};
 use kong::{server, ErrorResponse, Kong, Kontrol, Method};
 use std::sync::{Arc, Mutex};
 
@@ -16,6 +25,32 @@ pub struct DeleteArticleByIdKontroller {
     pub database: Arc<Mutex<Database>>,
     /// Accounts database
     pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Storage of the cover photos
+    pub media_storage: Arc<dyn MediaStorage>,
+    /// Cleanup of the data kept about purged posts
+    pub purge_hooks: Vec<Arc<dyn PurgeHook>>,
+    /// How long deleted posts are kept in the trash
+    pub retention: Duration,
+}
+
+impl DeleteArticleByIdKontroller {
+    /// Move a post to the trash and purge the expired posts, returns
+    /// `false` if the post does not exist or is already in the trash
+    fn delete(&self, id: i64) -> Result<bool, KontrollerError> {
+        let mut db = self.database.lock().unwrap();
+        let now = Utc::now();
+
+        let trashed = db.trash(id, now)?;
+        // The post is in the trash even if purging fails, the expired
+        // posts are purged again with the next delete
+        let _ = purge_expired_trash(
+            &mut db,
+            &*self.media_storage,
+            &self.purge_hooks,
+            now - self.retention,
+        );
+        Ok(trashed)
+    }
 }
 
 impl Kontrol for DeleteArticleByIdKontroller {
@@ -32,16 +67,18 @@ impl Kontrol for DeleteArticleByIdKontroller {
     /// Delete article
     fn kontrol(&self, kong: &Kong) -> server::Response {
         if let Some(k) = &kong.kpassport {
-            if let Ok(admin) = is_admin(k, self.accounts_database.clone()) {
-                if admin {
+            match is_admin(k, self.accounts_database.clone()) {
+                Ok(true) => {
                     if let Some(url_params) = &kong.url_parameters {
                         if let Some(id) = url_params.find("id") {
                             let id: i64 = id.parse().unwrap();
-                            if let Ok(_) = self.database.lock().unwrap().delete(id) {
-                                server::Response::text("Article has been deleted, succesfully.")
-                                    .with_status_code(200)
-                            } else {
-                                ErrorResponse::internal()
+                            match self.delete(id) {
+                                Ok(true) => server::Response::text(
+                                    "Article has been moved to the trash, succesfully.",
+                                )
+                                .with_status_code(200),
+                                Ok(false) => ErrorResponse::not_found(),
+                                Err(_) => ErrorResponse::internal(),
                             }
                         } else {
                             ErrorResponse::bad_request()
@@ -49,11 +86,9 @@ impl Kontrol for DeleteArticleByIdKontroller {
                     } else {
                         ErrorResponse::bad_request()
                     }
-                } else {
-                    ErrorResponse::unauthorized()
                 }
-            } else {
-                ErrorResponse::internal()
+                Ok(false) => ErrorResponse::unauthorized(),
+                Err(err) => kpassport_error(err),
             }
         } else {
             ErrorResponse::unauthorized()
@@ -0,0 +1,347 @@
+//! # 📡 Blog feed kontroller
+//!
+//! This __kontroller__ serves the most recent published blog posts as
+//! an RSS 2.0 or Atom 1.0 feed, see [`FeedFormat`]. If the kontroller's
+//! address has a `slug` url parameter, the feed only has the posts with
+//! the tag with that slug.
+//!
+//! Feed entries have the post's title, excerpt as summary, author,
+//! publish date, permalink and cover image as enclosure. Permalinks are
+//! built from the site URL, the permalink prefix and the post's slug.
+
+use super::database::Database;
+use super::{BlogPost, BlogPostQuery};
+use crate::xml::escape as escape_xml;
+use chrono::{DateTime, Utc};
+use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ## 📡 Feed format
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum FeedFormat {
+    /// RSS 2.0
+    Rss,
+    /// Atom 1.0
+    Atom,
+}
+
+impl FeedFormat {
+    /// Content type of the feed
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+}
+
+/// Guess the content type of an image from its file extension
+fn image_type(path: &str) -> &'static str {
+    let extension = path.rsplit('.').next().unwrap_or_default();
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => "image/jpeg",
+    }
+}
+
+/// 📡 Blog feed kontroller
+pub struct FeedKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Format of the feed
+    pub format: FeedFormat,
+    /// Title of the site, used as the title of the feed
+    pub site_title: String,
+    /// Description of the site, used as the description of the RSS
+    /// feed and the subtitle of the Atom feed. RSS feeds without one
+    /// are described by their title.
+    pub site_description: Option<String>,
+    /// Base URL of the site, eg `https://example.com`
+    pub site_url: String,
+    /// URL path blog post slugs are appended to, eg `/blog/`
+    pub permalink_prefix: String,
+    /// Largest number of posts in the feed
+    pub limit: i64,
+}
+
+impl FeedKontroller {
+    /// Absolute URL of a path on the site
+    fn url(&self, path: &str) -> String {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_string()
+        } else {
+            format!(
+                "{}/{}",
+                self.site_url.trim_end_matches('/'),
+                path.trim_start_matches('/')
+            )
+        }
+    }
+
+    /// Permalink of a blog post
+    fn permalink(&self, post: &BlogPost) -> String {
+        self.url(&format!("{}{}", self.permalink_prefix, post.slug))
+    }
+
+    /// Build an RSS 2.0 feed
+    pub fn rss(&self, title: &str, posts: &[BlogPost]) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <rss version=\"2.0\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n<channel>\n",
+        );
+        xml.push_str(&format!("<title>{}</title>\n", escape_xml(title)));
+        xml.push_str(&format!(
+            "<link>{}</link>\n",
+            escape_xml(&self.url(&self.permalink_prefix))
+        ));
+        xml.push_str(&format!(
+            "<description>{}</description>\n",
+            escape_xml(self.site_description.as_deref().unwrap_or(title))
+        ));
+        if let Some(date) = posts.iter().filter_map(|post| post.date).max() {
+            xml.push_str(&format!(
+                "<lastBuildDate>{}</lastBuildDate>\n",
+                date.to_rfc2822()
+            ));
+        }
+
+        for post in posts {
+            let permalink = escape_xml(&self.permalink(post));
+
+            xml.push_str("<item>\n");
+            xml.push_str(&format!("<title>{}</title>\n", escape_xml(&post.title)));
+            xml.push_str(&format!("<link>{permalink}</link>\n"));
+            xml.push_str(&format!("<guid isPermaLink=\"true\">{permalink}</guid>\n"));
+            if !post.excerpt.is_empty() {
+                xml.push_str(&format!(
+                    "<description>{}</description>\n",
+                    escape_xml(&post.excerpt)
+                ));
+            }
+            if let Some(author) = &post.author {
+                xml.push_str(&format!(
+                    "<dc:creator>{}</dc:creator>\n",
+                    escape_xml(author)
+                ));
+            }
+            if let Some(date) = post.date {
+                xml.push_str(&format!("<pubDate>{}</pubDate>\n", date.to_rfc2822()));
+            }
+            for tag in &post.tags {
+                xml.push_str(&format!("<category>{}</category>\n", escape_xml(&tag.name)));
+            }
+            if let Some(cover) = &post.cover {
+                // The size of the cover is unknown
+                xml.push_str(&format!(
+                    "<enclosure url=\"{}\" length=\"0\" type=\"{}\"/>\n",
+                    escape_xml(&self.url(cover)),
+                    image_type(cover)
+                ));
+            }
+            xml.push_str("</item>\n");
+        }
+
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+
+    /// Build an Atom 1.0 feed
+    pub fn atom(&self, title: &str, posts: &[BlogPost], now: DateTime<Utc>) -> String {
+        let updated = posts
+            .iter()
+            .filter_map(|post| post.date)
+            .max()
+            .unwrap_or(now);
+        let link = escape_xml(&self.url(&self.permalink_prefix));
+
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+        );
+        xml.push_str(&format!("<title>{}</title>\n", escape_xml(title)));
+        if let Some(description) = &self.site_description {
+            xml.push_str(&format!(
+                "<subtitle>{}</subtitle>\n",
+                escape_xml(description)
+            ));
+        }
+        xml.push_str(&format!("<id>{link}</id>\n"));
+        xml.push_str(&format!("<link href=\"{link}\"/>\n"));
+        xml.push_str(&format!("<updated>{}</updated>\n", updated.to_rfc3339()));
+        // Entries without an author use the feed's author
+        xml.push_str(&format!(
+            "<author><name>{}</name></author>\n",
+            escape_xml(&self.site_title)
+        ));
+
+        for post in posts {
+            let permalink = escape_xml(&self.permalink(post));
+            let date = post.date.unwrap_or(updated).to_rfc3339();
+
+            xml.push_str("<entry>\n");
+            xml.push_str(&format!("<title>{}</title>\n", escape_xml(&post.title)));
+            xml.push_str(&format!("<id>{permalink}</id>\n"));
+            xml.push_str(&format!("<link href=\"{permalink}\"/>\n"));
+            xml.push_str(&format!("<published>{date}</published>\n"));
+            xml.push_str(&format!("<updated>{date}</updated>\n"));
+            if !post.excerpt.is_empty() {
+                xml.push_str(&format!(
+                    "<summary>{}</summary>\n",
+                    escape_xml(&post.excerpt)
+                ));
+            }
+            if let Some(author) = &post.author {
+                xml.push_str(&format!(
+                    "<author><name>{}</name></author>\n",
+                    escape_xml(author)
+                ));
+            }
+            for tag in &post.tags {
+                xml.push_str(&format!(
+                    "<category term=\"{}\" label=\"{}\"/>\n",
+                    escape_xml(&tag.slug),
+                    escape_xml(&tag.name)
+                ));
+            }
+            if let Some(cover) = &post.cover {
+                xml.push_str(&format!(
+                    "<link rel=\"enclosure\" href=\"{}\" type=\"{}\"/>\n",
+                    escape_xml(&self.url(cover)),
+                    image_type(cover)
+                ));
+            }
+            xml.push_str("</entry>\n");
+        }
+
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
+
+impl Kontrol for FeedKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get feed
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let slug = kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("slug"));
+
+        let db = self.database.lock().unwrap();
+
+        // Tag feeds are titled after the tag
+        let title = match slug {
+            Some(slug) => match db.get_tag_by_slug(slug) {
+                Ok(Some(tag)) => format!("{} - {}", self.site_title, tag.name),
+                Ok(None) => return ErrorResponse::not_found(),
+                Err(_) => return ErrorResponse::internal(),
+            },
+            None => self.site_title.clone(),
+        };
+
+        let query = BlogPostQuery {
+            limit: Some(self.limit),
+            tag: slug.map(str::to_string),
+            ..Default::default()
+        };
+        let now = Utc::now();
+
+        match db.get_page(&query, now) {
+            Ok(page) => {
+                let xml = match self.format {
+                    FeedFormat::Rss => self.rss(&title, &page.posts),
+                    FeedFormat::Atom => self.atom(&title, &page.posts, now),
+                };
+                server::Response::from_data(self.format.content_type(), xml).with_status_code(200)
+            }
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{ContentFormat, PostStatus};
+    use super::*;
+
+    fn kontroller(format: FeedFormat) -> FeedKontroller {
+        FeedKontroller {
+            address: "/feed".to_string(),
+            method: Method::Get,
+            database: Arc::new(Mutex::new(Database::new("unused.sqlite"))),
+            format,
+            site_title: "Blog".to_string(),
+            site_description: None,
+            site_url: "https://example.com/".to_string(),
+            permalink_prefix: "/blog/".to_string(),
+            limit: 20,
+        }
+    }
+
+    fn post() -> BlogPost {
+        BlogPost {
+            id: 1,
+            title: "Hello & welcome".to_string(),
+            subtitle: None,
+            overview: Some("An overview".to_string()),
+            author: Some("admin".to_string()),
+            cover: Some("uploads/cover.png".to_string()),
+            cover_variants: None,
+            content: "Content".to_string(),
+            format: ContentFormat::Markdown,
+            content_html: "<p>Content</p>".to_string(),
+            date: Some(Utc::now()),
+            slug: "hello-welcome".to_string(),
+            status: PostStatus::Published,
+            version: 1,
+            deleted_at: None,
+            word_count: 1,
+            reading_time: 1,
+            excerpt: "An overview".to_string(),
+            tags: vec![],
+            co_authors: vec![],
+        }
+    }
+
+    #[test]
+    fn test_feeds() {
+        let rss = kontroller(FeedFormat::Rss).rss("Blog", &[post()]);
+        assert!(rss.contains("<description>Blog</description>"));
+        assert!(rss.contains("<title>Hello &amp; welcome</title>"));
+        assert!(rss.contains("<link>https://example.com/blog/hello-welcome</link>"));
+        assert!(rss.contains(
+            "<enclosure url=\"https://example.com/uploads/cover.png\" length=\"0\" type=\"image/png\"/>"
+        ));
+
+        let atom = kontroller(FeedFormat::Atom).atom("Blog", &[post()], Utc::now());
+        assert!(atom.contains("<id>https://example.com/blog/hello-welcome</id>"));
+        assert!(atom.contains("<summary>An overview</summary>"));
+        assert!(!atom.contains("<subtitle>"));
+
+        // The channel is described by the site description
+        let described = FeedKontroller {
+            site_description: Some("Notes & essays".to_string()),
+            ..kontroller(FeedFormat::Rss)
+        };
+        let rss = described.rss("Blog", &[post()]);
+        assert!(rss.contains("<description>Notes &amp; essays</description>"));
+        let atom = described.atom("Blog", &[post()], Utc::now());
+        assert!(atom.contains("<subtitle>Notes &amp; essays</subtitle>"));
+    }
+}
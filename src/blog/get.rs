@@ -1,8 +1,18 @@
 //! # Get single blog post kontroller
+//!
+//! Blog posts are served with the public profiles of their authors,
+//! see [`BlogPostWithAuthors`].
+//!
+//! The views of the posts are counted when the kontrollers have a
+//! `view_window`, see [`super::analytics`].
 
+use super::analytics::{count_view, post_visit};
+use super::authors::BlogPostWithAuthors;
 use super::database::Database;
+use crate::accounts::database::Database as AccountsDatabase;
 use crate::error::KontrollerError;
-use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use chrono::{Duration, Utc};
+use kong::{server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
 use std::sync::{Arc, Mutex};
 
 /// Get a single blog post kontroller
@@ -13,6 +23,11 @@ pub struct GetBlogPostByIdKontroller {
     pub method: Method,
     /// SQLite database handle
     pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Time window a visitor's views are counted once in, views are
+    /// not counted if `None`
+    pub view_window: Option<Duration>,
 }
 
 impl Kontrol for GetBlogPostByIdKontroller {
@@ -26,16 +41,27 @@ impl Kontrol for GetBlogPostByIdKontroller {
         self.method
     }
 
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        self.view_window
+            .and_then(|_| post_visit(request))
+            .map(|visit| visit.as_json())
+    }
+
     /// Get blogs
     fn kontrol(&self, kong: &Kong) -> server::Response {
         if let Some(url_params) = &kong.url_parameters {
             if let Some(id) = url_params.find("id") {
                 let id: i64 = id.parse().unwrap();
-                let res = self.database.lock().unwrap().get_blog_by_id(id);
-                match res {
+                let mut db = self.database.lock().unwrap();
+                match db.get_blog_by_id(id) {
                     Ok(post) => {
-                        if let Some(post) = post {
-                            server::Response::json(&post).with_status_code(200)
+                        // Unpublished posts are not public
+                        if let Some(post) = post.filter(|post| post.is_public(Utc::now())) {
+                            count_view(kong, &mut db, post.id, self.view_window);
+                            match BlogPostWithAuthors::new(post, self.accounts_database.clone()) {
+                                Ok(post) => server::Response::json(&post).with_status_code(200),
+                                Err(_) => ErrorResponse::internal(),
+                            }
                         } else {
                             ErrorResponse::not_found()
                         }
@@ -53,3 +79,79 @@ impl Kontrol for GetBlogPostByIdKontroller {
         }
     }
 }
+
+/// Get a single blog post by its slug kontroller
+///
+/// The slug is taken from the `slug` url parameter. If the slug is an
+/// old slug of a blog post, the response redirects to the post's
+/// current permalink: `permalink_prefix` followed by the current slug.
+pub struct GetBlogPostBySlugKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// URL path blog post slugs are appended to, eg `/blog/`
+    pub permalink_prefix: String,
+    /// Time window a visitor's views are counted once in, views are
+    /// not counted if `None`
+    pub view_window: Option<Duration>,
+}
+
+impl Kontrol for GetBlogPostBySlugKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        self.view_window
+            .and_then(|_| post_visit(request))
+            .map(|visit| visit.as_json())
+    }
+
+    /// Get blog
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Some(url_params) = &kong.url_parameters {
+            if let Some(slug) = url_params.find("slug") {
+                let mut db = self.database.lock().unwrap();
+
+                match db.get_blog_by_slug(slug) {
+                    Ok(Some(post)) if post.is_public(Utc::now()) => {
+                        count_view(kong, &mut db, post.id, self.view_window);
+                        match BlogPostWithAuthors::new(post, self.accounts_database.clone()) {
+                            Ok(post) => server::Response::json(&post).with_status_code(200),
+                            Err(_) => ErrorResponse::internal(),
+                        }
+                    }
+                    Ok(Some(_)) => ErrorResponse::not_found(),
+                    // Check if the slug is an old slug
+                    Ok(None) => match db.get_slug_redirect(slug) {
+                        Ok(Some(slug)) => server::Response::redirect_301(format!(
+                            "{}{slug}",
+                            self.permalink_prefix
+                        )),
+                        Ok(None) => ErrorResponse::not_found(),
+                        Err(_) => ErrorResponse::internal(),
+                    },
+                    Err(err) => match err {
+                        KontrollerError::DbField => ErrorResponse::bad_request(),
+                        _ => ErrorResponse::internal(),
+                    },
+                }
+            } else {
+                ErrorResponse::bad_request()
+            }
+        } else {
+            ErrorResponse::bad_request()
+        }
+    }
+}
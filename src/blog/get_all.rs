@@ -1,13 +1,25 @@
 //! # Get blog posts kontroller
 //!
-//! This __kontroller__ is used to post a new blog post
-//! It gets the input from the HTTP request validates it
-//! and creates the blog post from the validated input. The blog post
-//! is stored in an SQLite database.
+//! This __kontroller__ is used to list the published blog posts a page
+//! at a time. The page, filters and sort order are read from the query
+//! string of the request, see [`BlogPostQuery`]:
+//!
+//! - `page` and `limit`, or `cursor`: the `id` of the last post of the
+//!   previous page
+//! - `author`, `tag` (the slug of a tag), `from` and `to` (RFC 3339
+//!   dates) filters
+//! - `sort`: `newest`, `oldest` or `title`
+//! - `summary=true` leaves the content of the posts out
+//!
+//! The response also contains the total number of matching posts.
 
 use super::database::Database;
+use super::roles::blog_role;
+use super::{BlogPostQuery, BlogPostSummary, PostStatus};
+use crate::accounts::database::Database as AccountsDatabase;
 use crate::error::KontrollerError;
-use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use chrono::Utc;
+use kong::{json, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
 use std::sync::{Arc, Mutex};
 
 /// Get all blog posts kontroller
@@ -31,13 +43,113 @@ impl Kontrol for GetAllBlogPostsKontroller {
         self.method
     }
 
-    /// Get blogs
-    fn kontrol(&self, _kong: &Kong) -> server::Response {
-        // Store blog into the database
-        let res = self.database.lock().unwrap().get_all();
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        BlogPostQuery::from_request(request).map(|query| query.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        if let Some(input) = input {
+            match BlogPostQuery::from_json_str(input.to_string()) {
+                Ok(query) if query.is_valid() => Ok(Some(query.as_json())),
+                _ => Err(()),
+            }
+        } else {
+            Err(())
+        }
+    }
+
+    /// Get published blogs
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let query = match &kong.input {
+            Some(input) => match BlogPostQuery::from_json_str(input.to_string()) {
+                Ok(query) => query,
+                Err(_) => return ErrorResponse::bad_request(),
+            },
+            None => BlogPostQuery::default(),
+        };
+
+        let res = self.database.lock().unwrap().get_page(&query, Utc::now());
+
+        match res {
+            Ok(page) => {
+                if query.summary.unwrap_or(false) {
+                    server::Response::json(&page.map(BlogPostSummary::from)).with_status_code(200)
+                } else {
+                    server::Response::json(&page).with_status_code(200)
+                }
+            }
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
+
+/// Get unpublished blog posts kontroller
+///
+/// Lists the drafts, scheduled and archived posts for the accounts
+/// with a blog role, authors only see the posts they can edit. The
+/// `status` query parameter limits the list to one status.
+pub struct GetUnpublishedBlogPostsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for GetUnpublishedBlogPostsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        Some(json!({ "status": request.get_param("status") }))
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.as_ref().map(|input| &input["status"]) {
+            Some(JsonValue::String(status)) if PostStatus::from_name(status).is_none() => Err(()),
+            _ => Ok(input),
+        }
+    }
+
+    /// Get unpublished blogs
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let (username, role) = match blog_role(kong, self.accounts_database.clone()) {
+            Ok(account) => account,
+            Err(response) => return response,
+        };
+
+        let status = kong
+            .input
+            .as_ref()
+            .and_then(|input| input["status"].as_str())
+            .and_then(PostStatus::from_name);
+
+        let res = self.database.lock().unwrap().get_unpublished(status);
 
         match res {
-            Ok(posts) => server::Response::json(&posts).with_status_code(200),
+            Ok(posts) => {
+                // Authors only see their own posts
+                let posts: Vec<_> = posts
+                    .into_iter()
+                    .filter(|post| role.can_edit(&username, post))
+                    .collect();
+                server::Response::json(&posts).with_status_code(200)
+            }
             Err(err) => match err {
                 KontrollerError::DbField => ErrorResponse::bad_request(),
                 _ => ErrorResponse::internal(),
@@ -1,7 +1,11 @@
 //! # ⌨️ User Input
 
+use super::slug::has_slug_characters;
+use super::{ContentFormat, PostStatus, TagKind};
+use crate::media::{variants::StoredImage, Upload};
+use chrono::{DateTime, NaiveDate, Utc};
 use kong::{
-    inputs::UserInput, json, json_from_str, validate::ValidationError, JsonError, JsonValue,
+    inputs::UserInput, json, json_from_str, server, validate::ValidationError, JsonError, JsonValue,
 };
 use serde::{Deserialize, Serialize};
 
@@ -12,8 +16,25 @@ pub struct CreateBlogInput {
     pub subtitle: Option<String>,
     pub overview: Option<String>,
     pub author: Option<String>,
-    pub cover: Option<String>,
+    /// The uploaded cover photo, stored once the account is checked
+    pub cover: Option<Upload>,
     pub content: String,
+    pub slug: Option<String>,
+    /// Publishing status, the post is a draft if `None`. Ignored when
+    /// updating a post.
+    pub status: Option<PostStatus>,
+    /// When the post is published, ignored when updating a post
+    pub publish_at: Option<DateTime<Utc>>,
+    /// Names of the tags of the post, left unchanged if `None` when
+    /// updating a post
+    pub tags: Option<Vec<String>>,
+    /// Usernames of the co-authors of the post, left unchanged if
+    /// `None` when updating a post
+    #[serde(default)]
+    pub co_authors: Option<Vec<String>>,
+    /// Format of the content, markdown if `None` when creating a post,
+    /// left unchanged if `None` when updating a post
+    pub format: Option<ContentFormat>,
 }
 
 impl CreateBlogInput {
@@ -25,10 +46,35 @@ impl CreateBlogInput {
             "overview": self.overview,
             "author": self.author,
             "cover": self.cover,
-            "content": self.content
+            "content": self.content,
+            "slug": self.slug,
+            "status": self.status,
+            "publish_at": self.publish_at,
+            "tags": self.tags,
+            "co_authors": self.co_authors,
+            "format": self.format
         })
     }
 
+    /// Read names, eg tag names or usernames, from a comma separated
+    /// list, as sent in forms
+    pub fn names_from_list(list: Option<String>) -> Option<Vec<String>> {
+        list.map(|list| {
+            list.split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+    }
+
+    /// Check if a slug can be made from the name of every tag
+    pub fn has_valid_tags(&self) -> bool {
+        self.tags
+            .iter()
+            .flatten()
+            .all(|tag| has_slug_characters(tag))
+    }
+
     /// from json
     pub fn from_json_str(json_str: String) -> Result<CreateBlogInput, JsonError> {
         let a: CreateBlogInput = json_from_str(&json_str)?;
@@ -42,3 +88,589 @@ impl UserInput for CreateBlogInput {
         Ok(())
     }
 }
+
+/// ## ⌨️ Blog publishing input
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct PublishBlogInput {
+    /// When the post is published, now if `None`
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+impl PublishBlogInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "publish_at": self.publish_at
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<PublishBlogInput, JsonError> {
+        let a: PublishBlogInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+}
+
+/// ## 🔃 Blog listing sort order
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PostSort {
+    /// Most recently published first
+    #[default]
+    Newest,
+    /// Least recently published first
+    Oldest,
+    /// Alphabetically by title
+    Title,
+}
+
+/// ## 🔎 Blog listing query
+///
+/// Pagination, filtering and sorting of the public blog listing, read
+/// from the query string of the request. Pages are selected with
+/// `page` and `limit`, or with a `cursor`: the `id` of the last post of
+/// the previous page.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct BlogPostQuery {
+    /// Page number, starting at 1
+    pub page: Option<i64>,
+    /// Number of posts per page
+    pub limit: Option<i64>,
+    /// Id of the last post of the previous page
+    pub cursor: Option<i64>,
+    /// Only posts by this author
+    pub author: Option<String>,
+    /// Only posts with the tag with this slug
+    pub tag: Option<String>,
+    /// Only posts published on or after this date
+    pub from: Option<DateTime<Utc>>,
+    /// Only posts published on or before this date
+    pub to: Option<DateTime<Utc>>,
+    /// Sort order
+    pub sort: Option<PostSort>,
+    /// Leave the `content` of the posts out of the response
+    pub summary: Option<bool>,
+}
+
+impl BlogPostQuery {
+    /// Number of posts per page if no limit is provided
+    pub const DEFAULT_LIMIT: i64 = 20;
+    /// Largest number of posts per page
+    pub const MAX_LIMIT: i64 = 100;
+    /// Largest page number, so the offset of a page can not overflow
+    pub const MAX_PAGE: i64 = 100_000;
+
+    /// Read the query from the query string of a request
+    pub fn from_request(request: &server::Request) -> Option<BlogPostQuery> {
+        /// Parse an optional query string parameter
+        fn param<T: std::str::FromStr>(request: &server::Request, name: &str) -> Option<Option<T>> {
+            match request.get_param(name).filter(|value| !value.is_empty()) {
+                Some(value) => value.parse().ok().map(Some),
+                None => Some(None),
+            }
+        }
+
+        let sort = match request.get_param("sort").as_deref() {
+            Some("newest") => Some(PostSort::Newest),
+            Some("oldest") => Some(PostSort::Oldest),
+            Some("title") => Some(PostSort::Title),
+            Some("") | None => None,
+            Some(_) => return None,
+        };
+
+        Some(BlogPostQuery {
+            page: param(request, "page")?,
+            limit: param(request, "limit")?,
+            cursor: param(request, "cursor")?,
+            author: param(request, "author")?,
+            tag: param(request, "tag")?,
+            from: param(request, "from")?,
+            to: param(request, "to")?,
+            sort,
+            summary: param(request, "summary")?,
+        })
+    }
+
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "page": self.page,
+            "limit": self.limit,
+            "cursor": self.cursor,
+            "author": self.author,
+            "tag": self.tag,
+            "from": self.from,
+            "to": self.to,
+            "sort": self.sort,
+            "summary": self.summary
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<BlogPostQuery, JsonError> {
+        let a: BlogPostQuery = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Number of posts per page
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT)
+    }
+
+    /// Page number
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1)
+    }
+
+    /// Check if the page and limit are in range
+    pub fn is_valid(&self) -> bool {
+        (1..=Self::MAX_LIMIT).contains(&self.limit()) && (1..=Self::MAX_PAGE).contains(&self.page())
+    }
+}
+
+/// ## 🔎 Blog search query
+///
+/// Full-text search of the public blog posts, read from the query
+/// string of the request. The words in `q` are all looked for in the
+/// title, subtitle, overview and content of the posts, the last word
+/// also matches words it is the beginning of.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct SearchQuery {
+    /// The words to search for
+    pub q: String,
+    /// Page number, starting at 1
+    pub page: Option<i64>,
+    /// Number of results per page
+    pub limit: Option<i64>,
+    /// Only posts by this author
+    pub author: Option<String>,
+    /// Only posts with the tag with this slug
+    pub tag: Option<String>,
+}
+
+impl SearchQuery {
+    /// Longest search text
+    pub const MAX_LENGTH: usize = 200;
+
+    /// Read the query from the query string of a request
+    pub fn from_request(request: &server::Request) -> Option<SearchQuery> {
+        /// Parse an optional query string parameter
+        fn param<T: std::str::FromStr>(request: &server::Request, name: &str) -> Option<Option<T>> {
+            match request.get_param(name).filter(|value| !value.is_empty()) {
+                Some(value) => value.parse().ok().map(Some),
+                None => Some(None),
+            }
+        }
+
+        Some(SearchQuery {
+            q: request.get_param("q")?,
+            page: param(request, "page")?,
+            limit: param(request, "limit")?,
+            author: param(request, "author")?,
+            tag: param(request, "tag")?,
+        })
+    }
+
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "q": self.q,
+            "page": self.page,
+            "limit": self.limit,
+            "author": self.author,
+            "tag": self.tag
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<SearchQuery, JsonError> {
+        let a: SearchQuery = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Number of results per page
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(BlogPostQuery::DEFAULT_LIMIT)
+    }
+
+    /// Page number
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1)
+    }
+
+    /// The words searched for
+    fn words(&self) -> impl Iterator<Item = &str> {
+        self.q
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+    }
+
+    /// FTS5 query matching the posts with all the words. The words are
+    /// quoted so the search text can not use the FTS5 query syntax.
+    pub fn match_expression(&self) -> String {
+        let mut words: Vec<String> = self.words().map(|word| format!("\"{word}\"")).collect();
+        // A last word still being typed matches longer words
+        if self.q.ends_with(char::is_alphanumeric) {
+            if let Some(last) = words.last_mut() {
+                last.push('*');
+            }
+        }
+        words.join(" ")
+    }
+
+    /// Check if there are words to search for, and if the page and
+    /// limit are in range
+    pub fn is_valid(&self) -> bool {
+        self.q.len() <= Self::MAX_LENGTH
+            && self.words().next().is_some()
+            && (1..=BlogPostQuery::MAX_LIMIT).contains(&self.limit())
+            && (1..=BlogPostQuery::MAX_PAGE).contains(&self.page())
+    }
+}
+
+/// ## 📈 Blog analytics query
+///
+/// The period and the post of the analytics, read from the query string
+/// of the request. Days are in UTC, as `YYYY-MM-DD`.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct AnalyticsQuery {
+    /// First day of the period
+    pub from: Option<NaiveDate>,
+    /// Last day of the period
+    pub to: Option<NaiveDate>,
+    /// Only the views of the blog post with this id
+    pub post_id: Option<i64>,
+    /// Number of posts listed
+    pub limit: Option<i64>,
+}
+
+impl AnalyticsQuery {
+    /// Number of posts listed if no limit is provided
+    pub const DEFAULT_LIMIT: i64 = 10;
+
+    /// Read the query from the query string of a request
+    pub fn from_request(request: &server::Request) -> Option<AnalyticsQuery> {
+        /// Parse an optional query string parameter
+        fn param<T: std::str::FromStr>(request: &server::Request, name: &str) -> Option<Option<T>> {
+            match request.get_param(name).filter(|value| !value.is_empty()) {
+                Some(value) => value.parse().ok().map(Some),
+                None => Some(None),
+            }
+        }
+
+        Some(AnalyticsQuery {
+            from: param(request, "from")?,
+            to: param(request, "to")?,
+            post_id: param(request, "post_id")?,
+            limit: param(request, "limit")?,
+        })
+    }
+
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "from": self.from,
+            "to": self.to,
+            "post_id": self.post_id,
+            "limit": self.limit
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<AnalyticsQuery, JsonError> {
+        let a: AnalyticsQuery = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Number of posts listed
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT)
+    }
+
+    /// Check if the limit is in range and the period is not reversed
+    pub fn is_valid(&self) -> bool {
+        (1..=BlogPostQuery::MAX_LIMIT).contains(&self.limit())
+            && !matches!((self.from, self.to), (Some(from), Some(to)) if from > to)
+    }
+}
+
+/// ## 👣 Blog post visit
+///
+/// The visitor reading a blog post, known by a hash, see
+/// [`super::analytics`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PostVisit {
+    /// Hash of the visitor
+    pub visitor: String,
+}
+
+impl PostVisit {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({ "visitor": self.visitor })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<PostVisit, JsonError> {
+        let a: PostVisit = json_from_str(&json_str)?;
+        Ok(a)
+    }
+}
+
+/// ## ⌨️ Tag input
+///
+/// Used to create and rename tags.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TagInput {
+    /// The name of the tag
+    pub name: String,
+    /// Kind of tag, a tag if `None` when creating a tag, left
+    /// unchanged if `None` when renaming a tag
+    pub kind: Option<TagKind>,
+}
+
+impl TagInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "name": self.name,
+            "kind": self.kind
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<TagInput, JsonError> {
+        let a: TagInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Check if the name has any characters a slug can be made from
+    pub fn is_valid(&self) -> bool {
+        has_slug_characters(&self.name)
+    }
+}
+
+/// ## ⌨️ Tag merging input
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MergeTagsInput {
+    /// Id of the tag the posts are moved to
+    pub into: i64,
+}
+
+impl MergeTagsInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "into": self.into
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<MergeTagsInput, JsonError> {
+        let a: MergeTagsInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+}
+
+/// ## ⌨️ Media library upload input
+///
+/// A stored upload, with the name and alternative text sent with it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MediaFileInput {
+    /// The stored image
+    pub image: StoredImage,
+    /// Display name of the file
+    pub name: String,
+    /// Alternative text of the image
+    pub alt: Option<String>,
+}
+
+impl MediaFileInput {
+    /// Longest name or alternative text, in bytes
+    pub const MAX_LENGTH: usize = 500;
+
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "image": self.image,
+            "name": self.name,
+            "alt": self.alt
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<MediaFileInput, JsonError> {
+        let a: MediaFileInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Check if the name is not blank and the texts are not too long
+    pub fn is_valid(&self) -> bool {
+        !self.name.trim().is_empty()
+            && self.name.len() <= Self::MAX_LENGTH
+            && self.alt.as_ref().map_or(0, String::len) <= Self::MAX_LENGTH
+    }
+}
+
+/// ## ⌨️ Media library upload request input
+///
+/// An upload waiting to be stored, with the name and alternative text
+/// sent with it. The name defaults to the client's file name.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MediaUploadInput {
+    /// The uploaded file
+    pub file: Upload,
+    /// Display name of the file
+    pub name: Option<String>,
+    /// Alternative text of the image
+    pub alt: Option<String>,
+}
+
+impl MediaUploadInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "file": self.file,
+            "name": self.name,
+            "alt": self.alt
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<MediaUploadInput, JsonError> {
+        let a: MediaUploadInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Check if the texts are not too long
+    pub fn is_valid(&self) -> bool {
+        self.name.as_ref().map_or(0, String::len) <= MediaFileInput::MAX_LENGTH
+            && self.alt.as_ref().map_or(0, String::len) <= MediaFileInput::MAX_LENGTH
+    }
+}
+
+/// ## ⌨️ Media library file update input
+///
+/// Fields left `None` are unchanged, an empty `alt` removes the
+/// alternative text.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct UpdateMediaInput {
+    /// New display name of the file
+    pub name: Option<String>,
+    /// New alternative text of the image
+    pub alt: Option<String>,
+}
+
+impl UpdateMediaInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "name": self.name,
+            "alt": self.alt
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<UpdateMediaInput, JsonError> {
+        let a: UpdateMediaInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Check if the name is not blank and the texts are not too long
+    pub fn is_valid(&self) -> bool {
+        !self
+            .name
+            .as_ref()
+            .is_some_and(|name| name.trim().is_empty())
+            && self.name.as_ref().map_or(0, String::len) <= MediaFileInput::MAX_LENGTH
+            && self.alt.as_ref().map_or(0, String::len) <= MediaFileInput::MAX_LENGTH
+    }
+}
+
+/// Deserialize a field that can be absent, `null` or set: an absent
+/// field is `None`, a `null` field is `Some(None)`
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// ## ⌨️ Blog post partial update input
+///
+/// Only the fields present are updated. Optional fields of the post
+/// are removed when set to `null`. `version` is the version of the post
+/// the changes are based on, the update is refused if the post was
+/// changed since.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct PatchBlogInput {
+    /// Version of the post the changes are based on
+    pub version: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub subtitle: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub overview: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub author: Option<Option<String>>,
+    /// Only `null` is accepted, to remove the cover. Covers are
+    /// uploaded with the update kontroller.
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cover: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ContentFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub co_authors: Option<Vec<String>>,
+}
+
+impl PatchBlogInput {
+    /// new generic resource, absent fields are left out
+    pub fn as_json(&self) -> JsonValue {
+        json!(self)
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<PatchBlogInput, JsonError> {
+        let a: PatchBlogInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Check if the title is not blank, the tags have a slug and the
+    /// cover is only removed
+    pub fn is_valid(&self) -> bool {
+        !self
+            .title
+            .as_ref()
+            .is_some_and(|title| title.trim().is_empty())
+            && self
+                .tags
+                .iter()
+                .flatten()
+                .all(|tag| has_slug_characters(tag))
+            && !matches!(self.cover, Some(Some(_)))
+    }
+}
@@ -0,0 +1,365 @@
+//! # 🗂️ Blog media library kontrollers
+//!
+//! These __kontrollers__ allow accounts with a blog role to upload the
+//! images used in the content of blog posts, and to list the library
+//! with the posts using each file, see [`MediaFile`].
+//!
+//! Uploads are sent as the `file` field of a multipart form, with an
+//! optional `name` and `alt` text. Once the account is checked, they
+//! are checked against the kontroller's `policy` and stored in the
+//! `library` directory of the media storage without their metadata,
+//! along with resized variants, see [`crate::media::variants`].
+//!
+//! Authors can rename, change the alternative text of and delete the
+//! files they uploaded, editors any file. The file is taken from the
+//! `id` url parameter. Renaming a file only changes its display name,
+//! its URL stays the same so the posts using it keep working. Files
+//! used by posts can not be deleted.
+
+use super::database::Database;
+use super::inputs::{MediaFileInput, MediaUploadInput, UpdateMediaInput};
+use super::roles::{blog_role, BlogRole};
+use super::MediaFile;
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::error::KontrollerError;
+use crate::media::variants::{store_image, VariantSize};
+use crate::media::{storage::MediaStorage, MediaPolicy, MediaReferences};
+use chrono::Utc;
+use kong::{server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// Get the media file id from the `id` url parameter
+fn media_id(kong: &Kong) -> Option<i64> {
+    kong.url_parameters
+        .as_ref()
+        .and_then(|url_params| url_params.find("id"))
+        .and_then(|id| id.parse().ok())
+}
+
+/// Get the media file in the `id` url parameter if the account behind
+/// the request can change it, or the error response to send
+fn media_owner(
+    kong: &Kong,
+    accounts_db: Arc<Mutex<AccountsDatabase>>,
+    database: &Arc<Mutex<Database>>,
+) -> Result<MediaFile, server::Response> {
+    let (username, role) = blog_role(kong, accounts_db)?;
+    let id = media_id(kong).ok_or_else(ErrorResponse::bad_request)?;
+
+    match database.lock().unwrap().get_media_file(id) {
+        Ok(Some(file))
+            if role >= BlogRole::Editor || file.uploader.as_deref() == Some(&username) =>
+        {
+            Ok(file)
+        }
+        Ok(Some(_)) => Err(ErrorResponse::unauthorized()),
+        Ok(None) => Err(ErrorResponse::not_found()),
+        Err(_) => Err(ErrorResponse::internal()),
+    }
+}
+
+/// ## 📤 Media upload kontroller
+pub struct UploadMediaKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Storage of the media files
+    pub media_storage: Arc<dyn MediaStorage>,
+    /// Types and largest size of the uploads
+    pub policy: MediaPolicy,
+    /// Sizes of the resized variants of the images, see
+    /// [`VariantSize::defaults`]
+    pub sizes: Vec<VariantSize>,
+}
+
+impl Kontrol for UploadMediaKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        if let Ok(input) = server::post_input!(request, {
+            file: server::input::post::BufferedFile,
+            name: Option<String>,
+            alt: Option<String>,
+        }) {
+            // The file is stored once the account is checked
+            let file = self.policy.buffer(Some(input.file)).ok()??;
+
+            // The name defaults to the client's file name
+            let name = input
+                .name
+                .or_else(|| file.filename.clone())
+                .filter(|name| !name.trim().is_empty());
+
+            let input = MediaUploadInput {
+                file,
+                name,
+                alt: input.alt,
+            };
+
+            Some(input.as_json())
+        } else {
+            None
+        }
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.map(|input| MediaUploadInput::from_json_str(input.to_string())) {
+            Some(Ok(input)) if input.is_valid() => Ok(Some(input.as_json())),
+            _ => Err(()),
+        }
+    }
+
+    /// Add the upload to the media library
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let (username, _) = match blog_role(kong, self.accounts_database.clone()) {
+            Ok(account) => account,
+            Err(response) => return response,
+        };
+
+        let input = match kong
+            .input
+            .as_ref()
+            .map(|input| MediaUploadInput::from_json_str(input.to_string()))
+        {
+            Some(Ok(input)) => input,
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let image = match store_image(
+            &*self.media_storage,
+            &self.policy,
+            "library",
+            &input.file,
+            &self.sizes,
+        ) {
+            Ok(image) => image,
+            Err(KontrollerError::MediaStorage) => return ErrorResponse::internal(),
+            Err(_) => return ErrorResponse::bad_request(),
+        };
+
+        // Without a name, the file is named after its URL
+        let name = input
+            .name
+            .unwrap_or_else(|| image.url.rsplit('/').next().unwrap_or_default().to_string());
+        let input = MediaFileInput {
+            image,
+            name,
+            alt: input.alt,
+        };
+
+        let res = self
+            .database
+            .lock()
+            .unwrap()
+            .create_media(&input, &username, Utc::now());
+
+        match res {
+            Ok(file) => server::Response::json(&file).with_status_code(201),
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
+
+/// ## 🗂️ Media library kontroller
+///
+/// Lists all the files of the library, newest first.
+pub struct GetMediaLibraryKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for GetMediaLibraryKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// List the media library
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = blog_role(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        match self.database.lock().unwrap().get_media_files() {
+            Ok(files) => server::Response::json(&files).with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## ✏️ Media file update kontroller
+///
+/// Renames the file, or changes its alternative text, from the `name`
+/// and `alt` fields of the JSON body. An empty `alt` removes the
+/// alternative text.
+pub struct UpdateMediaKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for UpdateMediaKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<UpdateMediaInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        input.ok().map(|input| input.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.map(|input| UpdateMediaInput::from_json_str(input.to_string())) {
+            Some(Ok(input)) if input.is_valid() => Ok(Some(input.as_json())),
+            _ => Err(()),
+        }
+    }
+
+    /// Update media file
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let file = match media_owner(kong, self.accounts_database.clone(), &self.database) {
+            Ok(file) => file,
+            Err(response) => return response,
+        };
+
+        let input = match kong
+            .input
+            .as_ref()
+            .map(|input| UpdateMediaInput::from_json_str(input.to_string()))
+        {
+            Some(Ok(input)) => input,
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let db = self.database.lock().unwrap();
+
+        match db.update_media(file.id, &input) {
+            Ok(true) => match db.get_media_file(file.id) {
+                Ok(Some(file)) => server::Response::json(&file).with_status_code(200),
+                Ok(None) => ErrorResponse::not_found(),
+                Err(_) => ErrorResponse::internal(),
+            },
+            Ok(false) => ErrorResponse::not_found(),
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
+
+/// ## 🗑️ Media file deletion kontroller
+///
+/// Removes the file and its variants from the library and the media
+/// storage.
+pub struct DeleteMediaKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Storage of the media files
+    pub media_storage: Arc<dyn MediaStorage>,
+}
+
+impl Kontrol for DeleteMediaKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Delete media file
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let file = match media_owner(kong, self.accounts_database.clone(), &self.database) {
+            Ok(file) => file,
+            Err(response) => return response,
+        };
+
+        // Posts would show a missing image
+        if !file.used_by.is_empty() {
+            return ErrorResponse::bad_request();
+        }
+
+        if self.database.lock().unwrap().delete_media(file.id).is_err() {
+            return ErrorResponse::internal();
+        }
+
+        for url in file.urls() {
+            if let Some(key) = self.media_storage.key(&url) {
+                if self.media_storage.delete(&key).is_err() {
+                    return ErrorResponse::internal();
+                }
+            }
+        }
+
+        server::Response::text("Media file has been deleted.").with_status_code(200)
+    }
+}
+
+/// ## 🗂️ Media library references
+///
+/// The files of the library and their variants, so they are not taken
+/// for orphans.
+pub struct BlogLibrary {
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl MediaReferences for BlogLibrary {
+    /// The files of the media library, with their variants
+    fn media_urls(&self) -> Result<Vec<String>, KontrollerError> {
+        let files = self.database.lock().unwrap().get_media_files()?;
+
+        Ok(files.iter().flat_map(MediaFile::urls).collect())
+    }
+}
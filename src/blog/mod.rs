@@ -1,8 +1,9 @@
 //! # Blog kontroller
 //!
-//! The `blog` __kontroller__ allows an admin account (from the `account`
-//! kontroller) to post posts, delete, edit blog posts. The kontroller
-//!  allows the blog posts to be read publicly by anyone.
+//! The `blog` __kontroller__ allows accounts (from the `account`
+//! kontroller) with an editorial role to post, edit and publish blog
+//! posts, and admin accounts to delete them, see [`roles`]. The
+//! kontroller allows the blog posts to be read publicly by anyone.
 //!
 //! > To use the blog kontroller, enable it with the `blog` Cargo
 //! > feature.
@@ -10,18 +11,121 @@
 //! The `blog` kontroller depends on the `login` kontroller,
 //! enabling the `blog` kontroller automatically enables the
 //! `login` kontroller.
+//!
+//! Blog posts can be read by their `id` or by their unique slug, see
+//! [`slug`].
+//!
+//! ## ✍️ Authors
+//! Blog posts are written by accounts: the author of a new post is the
+//! logged in account unless another one is given, and a post can have
+//! co-authors. Single posts are served with the public profiles of
+//! their authors, and the posts of an account are listed with the
+//! [`authors`] kontroller.
+//!
+//! ## 📅 Publishing
+//! Every blog post has a [`PostStatus`], new posts are drafts unless
+//! another status is provided. Only published posts whose `date` has
+//! passed can be read publicly, so a post can be scheduled by
+//! publishing it with a future `publish_at` date. Admins can list the
+//! unpublished posts and publish or unpublish posts with the
+//! [`publish`] kontrollers.
+//!
+//! Published posts are also served as RSS and Atom feeds, see
+//! [`feed`], and can be listed in the sitemap of the `sitemap`
+//! kontroller.
+//!
+//! ## 🖋️ Content
+//! Blog post content is markdown or HTML, see [`ContentFormat`]. The
+//! raw content is kept as written, apart from HTML content which is
+//! sanitized when stored, and every post also has its content rendered
+//! to sanitized HTML, see [`render`].
+//!
+//! ## 📖 Reading time
+//! The word count and estimated reading time of blog posts are computed
+//! when their content is stored, and posts without an overview get an
+//! excerpt of their content, see [`reading`]. They are served with the
+//! posts and in the listings.
+//!
+//! ## 🖼️ Cover photos
+//! Cover photos are stored in a media storage, see [`crate::media`],
+//! without their metadata and with resized variants, which are served
+//! with the posts as `cover_variants`. Covers are removed from the
+//! storage once no post or revision uses them. The
+//! [`covers`] are also listed as media references, so the orphaned
+//! files of the storage can be found.
+//!
+//! ## 🗂️ Media library
+//! Accounts with a blog role upload the images used in the content of
+//! posts to a media library, and list them with the posts using them,
+//! see [`library`].
+//!
+//! ## 🩹 Partial updates
+//! Blog posts can also be updated a few fields at a time with the
+//! [`patch`] kontroller. Every post has a `version`, incremented by
+//! every update, so that concurrent edits are detected instead of
+//! overwriting each other.
+//!
+//! ## 🕰️ Revisions
+//! Every update of a blog post stores a revision of the post, the
+//! accounts that can edit the post can list, compare and restore the
+//! revisions with the [`revisions`] kontrollers.
+//!
+//! ## 🗑️ Trash
+//! Deleting a blog post moves it to the trash, where it is hidden from
+//! the public kontrollers. Admins can list the trash, restore posts
+//! and purge them permanently with the [`trash`] kontrollers, posts are
+//! also purged once they have been in the trash for longer than the
+//! retention period.
+//!
+//! ## 📈 Analytics
+//! The single post kontrollers can count the views of the public
+//! posts, once per visitor in a time window and leaving out bots. Only
+//! the number of views per post and day is kept, visitors are known by
+//! a hash for the length of the window, their IP address is never
+//! stored. Admins get the most read posts and the views over time with
+//! the [`analytics`] kontrollers.
+//!
+//! ## 🔎 Search
+//! The public blog posts can be searched by their title, subtitle,
+//! overview and content with the [`search`] kontroller. The search
+//! index is kept up to date by the database as posts change.
+//!
+//! ## 🏷️ Tags
+//! Blog posts are organised with [`Tag`]s, either free form tags or
+//! categories. Tags are assigned by name when a post is created or
+//! updated, unknown names create new tags. Admins can manage the tags
+//! with the [`tags`] kontrollers, and anyone can list the tags and the
+//! posts with a tag.
 
+use crate::media::{variants::ImageVariants, MediaType};
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
-pub use inputs::CreateBlogInput;
+pub use inputs::{AnalyticsQuery, BlogPostQuery, CreateBlogInput, PostSort, SearchQuery};
 
+pub mod analytics;
+pub mod authors;
+pub mod covers;
 pub mod create;
 pub mod database;
 pub mod delete;
+pub mod feed;
 pub mod get;
 pub mod get_all;
 pub mod inputs;
+pub mod library;
+pub mod patch;
+pub mod publish;
+pub mod reading;
+pub mod render;
+pub mod revisions;
+pub mod roles;
+pub mod search;
+#[cfg(feature = "sitemap")]
+pub mod sitemap;
+pub mod slug;
+pub mod tags;
+pub mod trash;
 pub mod update;
 
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
@@ -36,24 +140,51 @@ pub struct DatabaseBlogPostInput {
     pub author: Option<String>,
     /// The path to the cover image of the blog post
     pub cover: Option<String>,
+    /// The resized variants of the cover image
+    #[serde(default)]
+    pub cover_variants: Option<ImageVariants>,
     /// The actual content of the blog post
     pub content: String,
-    /// The date when the blog post was published
+    /// The date when the blog post was, or is scheduled to be, published
     pub date: Option<DateTime<Utc>>,
+    /// The slug of the blog post, generated from the title if `None`
+    pub slug: Option<String>,
+    /// The publishing status of the blog post
+    pub status: PostStatus,
+    /// Names of the tags of the blog post, left unchanged if `None`
+    pub tags: Option<Vec<String>>,
+    /// Usernames of the co-authors of the blog post, left unchanged if
+    /// `None`
+    pub co_authors: Option<Vec<String>>,
+    /// The format of the content, markdown for new posts and left
+    /// unchanged for updated posts if `None`
+    pub format: Option<ContentFormat>,
 }
 
+/// The uploaded cover is not converted, kontrollers store it once the
+/// account is checked and set the cover of the post
 impl From<CreateBlogInput> for DatabaseBlogPostInput {
     fn from(value: CreateBlogInput) -> Self {
+        let (status, date) = PostStatus::resolve(
+            value.status.unwrap_or_default(),
+            value.publish_at,
+            Utc::now(),
+        );
+
         DatabaseBlogPostInput {
             title: value.title,
             subtitle: value.subtitle,
             overview: value.overview,
             author: value.author,
-            cover: value.cover,
+            cover: None,
+            cover_variants: None,
             content: value.content,
-            // TODO: don't hardcode this, allow to use date from
-            // user input
-            date: Some(Utc::now()),
+            date,
+            slug: value.slug,
+            status,
+            tags: value.tags,
+            co_authors: value.co_authors,
+            format: value.format,
         }
     }
 }
@@ -68,12 +199,454 @@ pub struct BlogPost {
     pub subtitle: Option<String>,
     /// The overview of the blog post
     pub overview: Option<String>,
-    /// The username of the blog author
+    /// The username of the account of the blog author
     pub author: Option<String>,
     /// The path to the cover image of the blog post
     pub cover: Option<String>,
+    /// The resized variants of the cover image
+    #[serde(default)]
+    pub cover_variants: Option<ImageVariants>,
     /// The actual content of the blog post
     pub content: String,
+    /// The format of the content
+    pub format: ContentFormat,
+    /// The content rendered to sanitized HTML
+    pub content_html: String,
+    /// The date when the blog post was published
+    pub date: Option<DateTime<Utc>>,
+    /// The unique slug used in the blog post's permalink
+    pub slug: String,
+    /// The publishing status of the blog post
+    pub status: PostStatus,
+    /// The version of the blog post, incremented by every update
+    pub version: i64,
+    /// When the blog post was moved to the trash, `None` unless it is
+    /// in the trash
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Number of words of the content
+    #[serde(default)]
+    pub word_count: i64,
+    /// Estimated reading time of the content, in minutes
+    #[serde(default)]
+    pub reading_time: i64,
+    /// The overview, or the beginning of the content as plain text if
+    /// the blog post has no overview
+    #[serde(default)]
+    pub excerpt: String,
+    /// The tags of the blog post
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    /// The usernames of the accounts of the co-authors
+    #[serde(default)]
+    pub co_authors: Vec<String>,
+}
+
+/// ## 📰 Blog post summary
+///
+/// A blog post without its content, used in listings.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct BlogPostSummary {
+    /// The Identifier of the blog post, the Rust Type is `i64`
+    pub id: i64,
+    /// The title of the blog post
+    pub title: String,
+    /// The subtitle of the blog post
+    pub subtitle: Option<String>,
+    /// The overview of the blog post
+    pub overview: Option<String>,
+    /// The username of the blog author
+    pub author: Option<String>,
+    /// The path to the cover image of the blog post
+    pub cover: Option<String>,
+    /// The resized variants of the cover image
+    #[serde(default)]
+    pub cover_variants: Option<ImageVariants>,
     /// The date when the blog post was published
     pub date: Option<DateTime<Utc>>,
+    /// The unique slug used in the blog post's permalink
+    pub slug: String,
+    /// The publishing status of the blog post
+    pub status: PostStatus,
+    /// Number of words of the content
+    #[serde(default)]
+    pub word_count: i64,
+    /// Estimated reading time of the content, in minutes
+    #[serde(default)]
+    pub reading_time: i64,
+    /// The overview, or the beginning of the content as plain text if
+    /// the blog post has no overview
+    #[serde(default)]
+    pub excerpt: String,
+    /// The tags of the blog post
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    /// The usernames of the accounts of the co-authors
+    #[serde(default)]
+    pub co_authors: Vec<String>,
+}
+
+impl From<BlogPost> for BlogPostSummary {
+    fn from(post: BlogPost) -> Self {
+        BlogPostSummary {
+            id: post.id,
+            title: post.title,
+            subtitle: post.subtitle,
+            overview: post.overview,
+            author: post.author,
+            cover: post.cover,
+            cover_variants: post.cover_variants,
+            date: post.date,
+            slug: post.slug,
+            status: post.status,
+            word_count: post.word_count,
+            reading_time: post.reading_time,
+            excerpt: post.excerpt,
+            tags: post.tags,
+            co_authors: post.co_authors,
+        }
+    }
+}
+
+/// ## 🔎 Blog search result
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct SearchResult {
+    /// The blog post found
+    #[serde(flatten)]
+    pub post: BlogPostSummary,
+    /// The title as HTML, the words found in it are in `<mark>` tags
+    pub title_highlight: String,
+    /// An extract of the post as HTML around the words found, which
+    /// are in `<mark>` tags
+    pub snippet: String,
+    /// Relevance of the post, lower is more relevant
+    pub rank: f64,
+}
+
+/// ## 📚 A page of blog posts
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct BlogPostPage<T> {
+    /// The blog posts on the page
+    pub posts: Vec<T>,
+    /// Number of posts matching the query, on all pages
+    pub total: i64,
+    /// The page number, `None` when paginating with a cursor
+    pub page: Option<i64>,
+    /// Number of posts per page
+    pub limit: i64,
+    /// Cursor of the next page, `None` on the last page
+    pub next_cursor: Option<i64>,
+}
+
+impl<T> BlogPostPage<T> {
+    /// Convert the posts on the page
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> BlogPostPage<U> {
+        BlogPostPage {
+            posts: self.posts.into_iter().map(f).collect(),
+            total: self.total,
+            page: self.page,
+            limit: self.limit,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+impl BlogPost {
+    /// Check if the blog post can be read publicly
+    pub fn is_public(&self, now: DateTime<Utc>) -> bool {
+        self.status.is_live()
+            && !matches!(self.date, Some(date) if date > now)
+            && self.deleted_at.is_none()
+    }
+}
+
+/// ## 📅 Blog post status
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    /// Work in progress, only visible to its authors and editors
+    #[default]
+    Draft,
+    /// Published once its `date` has passed
+    Scheduled,
+    /// Publicly readable
+    Published,
+    /// No longer publicly readable
+    Archived,
+}
+
+impl PostStatus {
+    /// All statuses
+    pub const ALL: [PostStatus; 4] = [
+        PostStatus::Draft,
+        PostStatus::Scheduled,
+        PostStatus::Published,
+        PostStatus::Archived,
+    ];
+
+    /// Name of the status, as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Published => "published",
+            PostStatus::Archived => "archived",
+        }
+    }
+
+    /// Get a status from its name
+    pub fn from_name(name: &str) -> Option<PostStatus> {
+        PostStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == name)
+    }
+
+    /// Check if posts with this status are public once their date
+    /// has passed
+    pub fn is_live(&self) -> bool {
+        matches!(self, PostStatus::Scheduled | PostStatus::Published)
+    }
+
+    /// Get the status and publish date of a post from the requested
+    /// status and publish date. Published posts are published now
+    /// unless a date is provided, and are scheduled if the date is in
+    /// the future.
+    pub fn resolve(
+        status: PostStatus,
+        publish_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> (PostStatus, Option<DateTime<Utc>>) {
+        if status.is_live() {
+            let date = publish_at.unwrap_or(now);
+            let status = if date > now {
+                PostStatus::Scheduled
+            } else {
+                PostStatus::Published
+            };
+            (status, Some(date))
+        } else {
+            (status, publish_at)
+        }
+    }
+}
+
+/// ## 🖋️ Blog post content format
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFormat {
+    /// Markdown, rendered to HTML
+    #[default]
+    Markdown,
+    /// HTML
+    Html,
+}
+
+impl ContentFormat {
+    /// Name of the format, as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentFormat::Markdown => "markdown",
+            ContentFormat::Html => "html",
+        }
+    }
+
+    /// Get a format from its name
+    pub fn from_name(name: &str) -> Option<ContentFormat> {
+        [ContentFormat::Markdown, ContentFormat::Html]
+            .into_iter()
+            .find(|format| format.as_str() == name)
+    }
+}
+
+/// ## 🕰️ Blog post revision
+///
+/// A version of a blog post, stored every time the post is updated.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct BlogPostRevision {
+    /// The Identifier of the revision, the Rust Type is `i64`
+    pub id: i64,
+    /// The Identifier of the blog post
+    pub post_id: i64,
+    /// The title of the blog post
+    pub title: String,
+    /// The subtitle of the blog post
+    pub subtitle: Option<String>,
+    /// The overview of the blog post
+    pub overview: Option<String>,
+    /// The username of the blog author
+    pub author: Option<String>,
+    /// The path to the cover image of the blog post
+    pub cover: Option<String>,
+    /// The resized variants of the cover image
+    #[serde(default)]
+    pub cover_variants: Option<ImageVariants>,
+    /// The actual content of the blog post
+    pub content: String,
+    /// The format of the content
+    pub format: ContentFormat,
+    /// The slug of the blog post
+    pub slug: Option<String>,
+    /// The username of the account that made the revision, `None` for
+    /// the version from before the first update
+    pub editor: Option<String>,
+    /// The date the revision was made
+    pub date: DateTime<Utc>,
+    /// The names of the tags of the blog post, `None` for revisions
+    /// stored before tags were
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    /// The usernames of the co-authors, `None` for revisions stored
+    /// before co-authors were
+    #[serde(default)]
+    pub co_authors: Option<Vec<String>>,
+    /// The status of the blog post, `None` for revisions stored before
+    /// statuses were
+    #[serde(default)]
+    pub status: Option<PostStatus>,
+}
+
+/// ## 🕰️ Blog post revision summary
+///
+/// A revision without its content, used in listings.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct BlogPostRevisionSummary {
+    /// The Identifier of the revision, the Rust Type is `i64`
+    pub id: i64,
+    /// The Identifier of the blog post
+    pub post_id: i64,
+    /// The title of the blog post
+    pub title: String,
+    /// The username of the account that made the revision
+    pub editor: Option<String>,
+    /// The date the revision was made
+    pub date: DateTime<Utc>,
+}
+
+/// ## 🗂️ Media library file
+///
+/// An image uploaded to the media library, to be used in the content
+/// of blog posts.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct MediaFile {
+    /// The Identifier of the file, the Rust Type is `i64`
+    pub id: i64,
+    /// URL of the file
+    pub url: String,
+    /// Display name of the file
+    pub name: String,
+    /// Alternative text of the image
+    pub alt: Option<String>,
+    /// Type of the file
+    pub media_type: MediaType,
+    /// Width of the image, in pixels
+    pub width: u32,
+    /// Height of the image, in pixels
+    pub height: u32,
+    /// Size of the file, in bytes
+    pub size: i64,
+    /// The resized variants of the image
+    pub variants: Option<ImageVariants>,
+    /// Username of the account that uploaded the file
+    pub uploader: Option<String>,
+    /// Date when the file was uploaded
+    pub date: DateTime<Utc>,
+    /// The blog posts using the file, as their cover or in their
+    /// content, including their revisions
+    #[serde(default)]
+    pub used_by: Vec<MediaUsage>,
+}
+
+impl MediaFile {
+    /// URLs of the file and its variants
+    pub fn urls(&self) -> Vec<String> {
+        let mut urls = vec![self.url.clone()];
+        if let Some(variants) = &self.variants {
+            urls.extend(variants.urls());
+        }
+        urls
+    }
+}
+
+/// ## 🔗 A blog post using a media library file
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct MediaUsage {
+    /// The Identifier of the blog post
+    pub id: i64,
+    /// The title of the blog post
+    pub title: String,
+    /// The slug of the blog post
+    pub slug: String,
+}
+
+/// ## 📈 The views of a blog post
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct PostViews {
+    /// The Identifier of the blog post
+    pub id: i64,
+    /// The title of the blog post
+    pub title: String,
+    /// The slug of the blog post
+    pub slug: String,
+    /// Number of views in the period
+    pub views: i64,
+}
+
+/// ## 📅 The views of a day
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct DailyViews {
+    /// The day, in UTC
+    pub date: NaiveDate,
+    /// Number of views on the day
+    pub views: i64,
+}
+
+/// ## 🏷️ Blog post tag
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct Tag {
+    /// The Identifier of the tag, the Rust Type is `i64`
+    pub id: i64,
+    /// The name of the tag
+    pub name: String,
+    /// The unique slug of the tag, generated from its name
+    pub slug: String,
+    /// Whether the tag is a tag or a category
+    pub kind: TagKind,
+}
+
+/// ## 🏷️ A tag and its number of public posts
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct TagCount {
+    /// The tag
+    #[serde(flatten)]
+    pub tag: Tag,
+    /// Number of public blog posts with the tag
+    pub posts: i64,
+}
+
+/// ## 🗂️ Kind of tag
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TagKind {
+    /// Free form tag, a post can have many
+    #[default]
+    Tag,
+    /// Category, a broader grouping of posts
+    Category,
+}
+
+impl TagKind {
+    /// Name of the kind, as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagKind::Tag => "tag",
+            TagKind::Category => "category",
+        }
+    }
+
+    /// Get a kind from its name
+    pub fn from_name(name: &str) -> Option<TagKind> {
+        [TagKind::Tag, TagKind::Category]
+            .into_iter()
+            .find(|kind| kind.as_str() == name)
+    }
 }
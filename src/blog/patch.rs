@@ -0,0 +1,152 @@
+//! # 🩹 Blog post partial update kontroller
+//!
+//! This __kontroller__ updates only the fields of a blog post present
+//! in the JSON body of the request, see [`PatchBlogInput`]: a field
+//! that is absent is left unchanged, an optional field set to `null` is
+//! removed. The post is taken from the `id` url parameter, and a
+//! revision of the updated post is stored.
+//!
+//! Every blog post has a `version`, incremented by every update. The
+//! body must contain the `version` of the post the changes are based
+//! on, if the post was changed since the update is refused with a
+//! `409 Conflict` response, and the client should get the post again.
+//! The updated post is sent back with its new version, also as the
+//! `ETag` header.
+//!
+//! Editors can update any post, authors only the posts they wrote or
+//! co-wrote, see [`super::roles`].
+
+use super::authors::usernames_exist;
+use super::covers::remove_unused_covers;
+use super::database::Database;
+use super::inputs::PatchBlogInput;
+use super::roles::post_editor;
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::error::KontrollerError;
+use crate::media::storage::MediaStorage;
+use kong::{server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ## 🩹 Blog post partial update kontroller
+pub struct PatchBlogPostKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Storage of the cover photos
+    pub media_storage: Arc<dyn MediaStorage>,
+}
+
+impl Kontrol for PatchBlogPostKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<PatchBlogInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        input.ok().map(|input| input.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.map(|input| PatchBlogInput::from_json_str(input.to_string())) {
+            Some(Ok(input)) if input.is_valid() => Ok(Some(input.as_json())),
+            _ => Err(()),
+        }
+    }
+
+    /// Update the fields present
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let id = match kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("id"))
+            .and_then(|id| id.parse().ok())
+        {
+            Some(id) => id,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let (editor, role) =
+            match post_editor(kong, self.accounts_database.clone(), &self.database, id) {
+                Ok(account) => account,
+                Err(response) => return response,
+            };
+
+        let patch = match kong
+            .input
+            .as_ref()
+            .map(|input| PatchBlogInput::from_json_str(input.to_string()))
+        {
+            Some(Ok(patch)) => patch,
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let author = patch.author.as_ref().and_then(Option::as_ref);
+        match usernames_exist(
+            author.into_iter().chain(patch.co_authors.iter().flatten()),
+            self.accounts_database.clone(),
+        ) {
+            Ok(true) => (),
+            Ok(false) => return ErrorResponse::bad_request(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        let mut db = self.database.lock().unwrap();
+        let post = match db.get_blog_by_id(id) {
+            Ok(Some(post)) => post,
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        };
+
+        // Authors can not give their posts to other accounts
+        if !role.can_set_authors(
+            &editor,
+            &post,
+            patch.author.as_ref().map(Option::as_deref),
+            patch.co_authors.as_deref(),
+        ) {
+            return ErrorResponse::unauthorized();
+        }
+        let old_cover = post.cover.map(|cover| (cover, post.cover_variants));
+
+        let res = db.patch(id, &patch, Some(&editor)).and_then(|post| {
+            // The removed cover is kept while a revision uses it
+            if let Some(cover) = old_cover.filter(|_| patch.cover.is_some()) {
+                remove_unused_covers(&db, &*self.media_storage, &[cover])?;
+            }
+            Ok(post)
+        });
+
+        match res {
+            Ok(Some(post)) => {
+                let mut response = server::Response::json(&post);
+                response
+                    .headers
+                    .push(("ETag".into(), format!("\"{}\"", post.version).into()));
+                response.status_code = 200;
+                response
+            }
+            Ok(None) => ErrorResponse::not_found(),
+            Err(err) => match err {
+                KontrollerError::VersionConflict => {
+                    server::Response::text(err.to_string()).with_status_code(409)
+                }
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! # 📅 Blog post publishing kontrollers
+//!
+//! These __kontrollers__ allow an editor account to publish and
+//! unpublish blog posts, the post is taken from the `id` url
+//! parameter.
+//!
+//! A post published with a future `publish_at` date is scheduled, it
+//! becomes public once the date has passed. An unpublished post goes
+//! back to being a draft, or is archived.
+
+use super::database::Database;
+use super::inputs::PublishBlogInput;
+use super::roles::{username_with_role, BlogRole};
+use super::PostStatus;
+use crate::accounts::database::Database as AccountsDatabase;
+use chrono::Utc;
+use kong::{server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// Get the blog post id from the `id` url parameter
+fn post_id(kong: &Kong) -> Option<i64> {
+    kong.url_parameters
+        .as_ref()
+        .and_then(|url_params| url_params.find("id"))
+        .and_then(|id| id.parse().ok())
+}
+
+/// Set the status of the blog post in the `id` url parameter
+fn set_status(
+    kong: &Kong,
+    database: &Arc<Mutex<Database>>,
+    status: PostStatus,
+    date: Option<chrono::DateTime<Utc>>,
+) -> server::Response {
+    let id = if let Some(id) = post_id(kong) {
+        id
+    } else {
+        return ErrorResponse::bad_request();
+    };
+
+    let db = database.lock().unwrap();
+
+    match db.set_status(id, status, date) {
+        Ok(true) => match db.get_blog_by_id(id) {
+            Ok(Some(post)) => server::Response::json(&post).with_status_code(200),
+            Ok(None) => ErrorResponse::not_found(),
+            Err(_) => ErrorResponse::internal(),
+        },
+        Ok(false) => ErrorResponse::not_found(),
+        Err(_) => ErrorResponse::internal(),
+    }
+}
+
+/// ✨ Publish blog post kontroller
+///
+/// Accepts an optional JSON body with a `publish_at` date, the post is
+/// published immediately if none is provided.
+pub struct PublishBlogPostKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for PublishBlogPostKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<PublishBlogInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        // The body is optional
+        Some(input.unwrap_or_default().as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        if let Some(input) = input {
+            if let Ok(input) = PublishBlogInput::from_json_str(input.to_string()) {
+                Ok(Some(input.as_json()))
+            } else {
+                Err(())
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Publish blog post
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) =
+            username_with_role(kong, self.accounts_database.clone(), BlogRole::Editor)
+        {
+            return response;
+        }
+
+        let input = kong
+            .input
+            .as_ref()
+            .and_then(|input| PublishBlogInput::from_json_str(input.to_string()).ok())
+            .unwrap_or_default();
+
+        let (status, date) =
+            PostStatus::resolve(PostStatus::Published, input.publish_at, Utc::now());
+
+        set_status(kong, &self.database, status, date)
+    }
+}
+
+/// 📦 Unpublish blog post kontroller
+///
+/// Makes a post a draft again, or archives it if `archive` is `true`.
+pub struct UnpublishBlogPostKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Archive the post instead of making it a draft
+    pub archive: bool,
+}
+
+impl Kontrol for UnpublishBlogPostKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Unpublish blog post
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) =
+            username_with_role(kong, self.accounts_database.clone(), BlogRole::Editor)
+        {
+            return response;
+        }
+
+        let status = if self.archive {
+            PostStatus::Archived
+        } else {
+            PostStatus::Draft
+        };
+
+        // Keep the publish date of archived posts
+        let date = match (status, post_id(kong)) {
+            (PostStatus::Archived, Some(id)) => {
+                match self.database.lock().unwrap().get_blog_by_id(id) {
+                    Ok(post) => post.and_then(|post| post.date),
+                    Err(_) => return ErrorResponse::internal(),
+                }
+            }
+            _ => None,
+        };
+
+        set_status(kong, &self.database, status, date)
+    }
+}
@@ -0,0 +1,202 @@
+//! # 📖 Reading time and excerpts
+//!
+//! The word count, estimated reading time and excerpt of a blog post
+//! are computed from the text of its content when the content is
+//! stored, see [`ContentStats`]. Markdown syntax and HTML tags are not
+//! counted as words, the text is read from the parsed markdown or
+//! from the HTML without its tags.
+//!
+//! Posts are served with their overview as excerpt, the beginning of
+//! their content is used when they have no overview. The text is also
+//! what the search index holds, see [`super::search`], so searches do
+//! not match the markup.
+
+use super::ContentFormat;
+use pulldown_cmark::{Event, Parser, Tag};
+
+/// Words read in a minute
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Longest excerpt, in characters
+pub const EXCERPT_LENGTH: usize = 200;
+
+/// ## 📖 Reading statistics of blog post content
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct ContentStats {
+    /// Number of words of the content
+    pub word_count: i64,
+    /// Estimated reading time, in minutes
+    pub reading_time: i64,
+    /// The beginning of the content as plain text
+    pub excerpt: String,
+    /// The content as plain text
+    pub text: String,
+}
+
+impl ContentStats {
+    /// Compute the statistics of blog post content
+    pub fn new(content: &str, format: ContentFormat) -> Self {
+        let text = plain_text(content, format);
+        let word_count = text.split_whitespace().count();
+
+        ContentStats {
+            word_count: word_count as i64,
+            reading_time: word_count.div_ceil(WORDS_PER_MINUTE) as i64,
+            excerpt: excerpt(&text, EXCERPT_LENGTH),
+            text,
+        }
+    }
+}
+
+/// Decode the character references of HTML text
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(at) = rest.find('&') {
+        decoded.push_str(&rest[..at]);
+        rest = &rest[at..];
+
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| (&rest[1..end], end));
+        let character = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => match name.strip_prefix('#') {
+                Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16)
+                    .ok()
+                    .and_then(char::from_u32),
+                Some(decimal) => decimal.parse().ok().and_then(char::from_u32),
+                None => None,
+            },
+        });
+
+        match (character, entity) {
+            (Some(character), Some((_, end))) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Get the text of HTML, tags are replaced by spaces
+fn html_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+
+    decode_entities(&text)
+}
+
+/// Get the text of blog post content, without markup
+pub fn plain_text(content: &str, format: ContentFormat) -> String {
+    match format {
+        ContentFormat::Html => html_text(content),
+        ContentFormat::Markdown => {
+            let mut text = String::with_capacity(content.len());
+
+            for event in Parser::new(content) {
+                match event {
+                    Event::Text(words) | Event::Code(words) => text.push_str(&words),
+                    Event::Html(html) => text.push_str(&html_text(&html)),
+                    Event::End(
+                        Tag::Emphasis
+                        | Tag::Strong
+                        | Tag::Strikethrough
+                        | Tag::Link(..)
+                        | Tag::Image(..),
+                    ) => (),
+                    // Blocks and line breaks separate words
+                    Event::End(_) | Event::SoftBreak | Event::HardBreak => text.push(' '),
+                    _ => (),
+                }
+            }
+
+            text
+        }
+    }
+}
+
+/// The beginning of a text, cut after the last whole word that fits in
+/// `length` characters
+pub fn excerpt(text: &str, length: usize) -> String {
+    let mut excerpt = String::new();
+
+    for word in text.split_whitespace() {
+        let separator = usize::from(!excerpt.is_empty());
+        if excerpt.chars().count() + separator + word.chars().count() > length {
+            // A single long word is cut
+            if excerpt.is_empty() {
+                excerpt = word.chars().take(length).collect();
+            }
+            excerpt.push('…');
+            break;
+        }
+
+        if separator == 1 {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(word);
+    }
+
+    excerpt
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_content_stats() {
+        let markdown = "# Hello\n\nSome *emphasis*, a [link](https://example.com) and `code`.";
+        assert_eq!(
+            plain_text(markdown, ContentFormat::Markdown)
+                .split_whitespace()
+                .collect::<Vec<_>>(),
+            vec!["Hello", "Some", "emphasis,", "a", "link", "and", "code."]
+        );
+
+        let html = "<p>Fish &amp; chips</p><p>are&nbsp;good</p>";
+        let stats = ContentStats::new(html, ContentFormat::Html);
+        assert_eq!(stats.word_count, 5);
+        assert_eq!(stats.reading_time, 1);
+        assert_eq!(stats.excerpt, "Fish & chips are good");
+
+        let long = "word ".repeat(450);
+        let stats = ContentStats::new(&long, ContentFormat::Markdown);
+        assert_eq!(stats.reading_time, 3);
+        assert!(stats.excerpt.ends_with("word…"));
+        assert!(stats.excerpt.chars().count() <= EXCERPT_LENGTH + 1);
+
+        assert_eq!(
+            ContentStats::new("", ContentFormat::Markdown).reading_time,
+            0
+        );
+        assert_eq!(excerpt("Supercalifragilistic", 5), "Super…");
+    }
+}
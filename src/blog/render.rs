@@ -0,0 +1,69 @@
+//! # 🖋️ Blog post content rendering
+//!
+//! Blog post content is written in markdown or HTML, see
+//! [`ContentFormat`]. Markdown is rendered to HTML, and all HTML is
+//! cleaned with an allowlist sanitizer that removes scripts, event
+//! handlers and other unsafe markup before it reaches a browser.
+
+use super::ContentFormat;
+use pulldown_cmark::{html, Options, Parser};
+
+/// Remove unsafe markup from HTML. Only allowlisted tags and attributes
+/// are kept, links get `rel="noopener noreferrer"`.
+pub fn sanitize(html: &str) -> String {
+    ammonia::Builder::default()
+        // Code blocks keep their `language-*` class for highlighting
+        .add_tag_attributes("code", &["class"])
+        .clean(html)
+        .to_string()
+}
+
+/// Render markdown to HTML, the HTML is not sanitized
+fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+
+    let mut output = String::new();
+    html::push_html(&mut output, Parser::new_ext(markdown, options));
+    output
+}
+
+/// Render blog post content to sanitized HTML
+pub fn render(content: &str, format: ContentFormat) -> String {
+    match format {
+        ContentFormat::Markdown => sanitize(&markdown_to_html(content)),
+        ContentFormat::Html => sanitize(content),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render("# Title\n\n*hi*", ContentFormat::Markdown),
+            "<h1>Title</h1>\n<p><em>hi</em></p>\n"
+        );
+
+        // Raw HTML in markdown is sanitized too
+        assert_eq!(
+            render(
+                "<script>alert(1)</script>\n\n[x](javascript:alert(1))",
+                ContentFormat::Markdown
+            ),
+            "\n<p><a rel=\"noopener noreferrer\">x</a></p>\n"
+        );
+
+        assert_eq!(
+            render(
+                "<p onclick=\"alert(1)\">Hello</p><img src=x onerror=alert(1)>",
+                ContentFormat::Html
+            ),
+            "<p>Hello</p><img src=\"x\">"
+        );
+    }
+}
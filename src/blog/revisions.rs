@@ -0,0 +1,385 @@
+//! # 🕰️ Blog post revision kontrollers
+//!
+//! These __kontrollers__ allow the accounts that can edit a blog post,
+//! see [`super::roles`], to list the revisions
+//! of a blog post, read a revision, compare two revisions line by line
+//! and restore a revision as the current version of the post. The post
+//! is taken from the `id` url parameter, and the revision from the
+//! `revision` url parameter.
+//!
+//! Restoring a revision stores the restored version as a new revision,
+//! so a restore can be undone like any other update. The tags,
+//! co-authors and status of the revision are restored too, accounts
+//! with the author role can not restore a revision that would change
+//! the authors or the status of the post, see
+//! [`super::roles::BlogRole::can_restore`].
+
+use super::database::Database;
+use super::roles::post_editor;
+use super::BlogPostRevision;
+use crate::accounts::database::Database as AccountsDatabase;
+use kong::{json, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// ## ↔️ Change of a line between two revisions
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LineChange {
+    /// The line is in both revisions
+    Same,
+    /// The line is only in the newer revision
+    Added,
+    /// The line is only in the older revision
+    Removed,
+}
+
+/// ## ↔️ A line of a diff
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct DiffLine {
+    /// How the line changed
+    pub change: LineChange,
+    /// The line
+    pub line: String,
+}
+
+/// ## ↔️ Line by line differences between two revisions
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct RevisionDiff {
+    /// The Identifier of the older revision
+    pub from: i64,
+    /// The Identifier of the newer revision
+    pub to: i64,
+    /// Differences in the title
+    pub title: Vec<DiffLine>,
+    /// Differences in the subtitle
+    pub subtitle: Vec<DiffLine>,
+    /// Differences in the overview
+    pub overview: Vec<DiffLine>,
+    /// Differences in the content
+    pub content: Vec<DiffLine>,
+    /// Differences in the author
+    pub author: Vec<DiffLine>,
+    /// Differences in the tags, one per line
+    pub tags: Vec<DiffLine>,
+    /// Differences in the co-authors, one per line
+    pub co_authors: Vec<DiffLine>,
+    /// Differences in the status
+    pub status: Vec<DiffLine>,
+}
+
+impl RevisionDiff {
+    /// Compare two revisions
+    pub fn new(from: &BlogPostRevision, to: &BlogPostRevision) -> Self {
+        /// Compare optional text
+        fn diff_option(old: &Option<String>, new: &Option<String>) -> Vec<DiffLine> {
+            diff_lines(
+                old.as_deref().unwrap_or_default(),
+                new.as_deref().unwrap_or_default(),
+            )
+        }
+
+        /// Compare lists, one item per line
+        fn diff_list(old: &Option<Vec<String>>, new: &Option<Vec<String>>) -> Vec<DiffLine> {
+            diff_lines(
+                &old.as_deref().unwrap_or_default().join("\n"),
+                &new.as_deref().unwrap_or_default().join("\n"),
+            )
+        }
+
+        RevisionDiff {
+            from: from.id,
+            to: to.id,
+            title: diff_lines(&from.title, &to.title),
+            subtitle: diff_option(&from.subtitle, &to.subtitle),
+            overview: diff_option(&from.overview, &to.overview),
+            content: diff_lines(&from.content, &to.content),
+            author: diff_option(&from.author, &to.author),
+            tags: diff_list(&from.tags, &to.tags),
+            co_authors: diff_list(&from.co_authors, &to.co_authors),
+            status: diff_lines(
+                from.status
+                    .map(|status| status.as_str())
+                    .unwrap_or_default(),
+                to.status.map(|status| status.as_str()).unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+/// Compare two texts line by line
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    diff::lines(old, new)
+        .into_iter()
+        .map(|line| match line {
+            diff::Result::Both(line, _) => DiffLine {
+                change: LineChange::Same,
+                line: line.to_string(),
+            },
+            diff::Result::Right(line) => DiffLine {
+                change: LineChange::Added,
+                line: line.to_string(),
+            },
+            diff::Result::Left(line) => DiffLine {
+                change: LineChange::Removed,
+                line: line.to_string(),
+            },
+        })
+        .collect()
+}
+
+/// Get an id from an url parameter
+fn url_id(kong: &Kong, name: &str) -> Option<i64> {
+    kong.url_parameters
+        .as_ref()
+        .and_then(|url_params| url_params.find(name))
+        .and_then(|id| id.parse().ok())
+}
+
+/// ## 📋 Get blog post revisions kontroller
+///
+/// Lists the revisions of the blog post, newest first, without their
+/// content.
+pub struct GetRevisionsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for GetRevisionsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get revisions
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let id = if let Some(id) = url_id(kong, "id") {
+            id
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        if let Err(response) = post_editor(kong, self.accounts_database.clone(), &self.database, id)
+        {
+            return response;
+        }
+
+        match self.database.lock().unwrap().get_revisions(id) {
+            Ok(revisions) => server::Response::json(&revisions).with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## 🔎 Get blog post revision kontroller
+pub struct GetRevisionKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for GetRevisionKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get revision
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let (id, revision) = match (url_id(kong, "id"), url_id(kong, "revision")) {
+            (Some(id), Some(revision)) => (id, revision),
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        if let Err(response) = post_editor(kong, self.accounts_database.clone(), &self.database, id)
+        {
+            return response;
+        }
+
+        match self.database.lock().unwrap().get_revision(id, revision) {
+            Ok(Some(revision)) => server::Response::json(&revision).with_status_code(200),
+            Ok(None) => ErrorResponse::not_found(),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## ↔️ Compare blog post revisions kontroller
+///
+/// Compares the revisions in the `from` and `to` query parameters line
+/// by line.
+pub struct DiffRevisionsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for DiffRevisionsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let from: i64 = request.get_param("from")?.parse().ok()?;
+        let to: i64 = request.get_param("to")?.parse().ok()?;
+
+        Some(json!({ "from": from, "to": to }))
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match &input {
+            Some(revisions) if revisions["from"].is_i64() && revisions["to"].is_i64() => Ok(input),
+            _ => Err(()),
+        }
+    }
+
+    /// Compare revisions
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let id = if let Some(id) = url_id(kong, "id") {
+            id
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        if let Err(response) = post_editor(kong, self.accounts_database.clone(), &self.database, id)
+        {
+            return response;
+        }
+
+        let (from, to) = match kong
+            .input
+            .as_ref()
+            .map(|input| (input["from"].as_i64(), input["to"].as_i64()))
+        {
+            Some((Some(from), Some(to))) => (from, to),
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let db = self.database.lock().unwrap();
+
+        match (db.get_revision(id, from), db.get_revision(id, to)) {
+            (Ok(Some(from)), Ok(Some(to))) => {
+                server::Response::json(&RevisionDiff::new(&from, &to)).with_status_code(200)
+            }
+            (Ok(_), Ok(_)) => ErrorResponse::not_found(),
+            _ => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## ⏪ Restore blog post revision kontroller
+pub struct RestoreRevisionKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for RestoreRevisionKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Restore revision
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let (id, revision) = match (url_id(kong, "id"), url_id(kong, "revision")) {
+            (Some(id), Some(revision)) => (id, revision),
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let (editor, role) =
+            match post_editor(kong, self.accounts_database.clone(), &self.database, id) {
+                Ok(editor) => editor,
+                Err(response) => return response,
+            };
+
+        let mut db = self.database.lock().unwrap();
+
+        match (db.get_blog_by_id(id), db.get_revision(id, revision)) {
+            (Ok(Some(post)), Ok(Some(revision))) => {
+                if !role.can_restore(&editor, &post, &revision) {
+                    return ErrorResponse::unauthorized();
+                }
+            }
+            (Ok(_), Ok(_)) => return ErrorResponse::not_found(),
+            _ => return ErrorResponse::internal(),
+        }
+
+        match db.restore_revision(id, revision, Some(&editor)) {
+            Ok(true) => match db.get_blog_by_id(id) {
+                Ok(Some(post)) => server::Response::json(&post).with_status_code(200),
+                Ok(None) => ErrorResponse::not_found(),
+                Err(_) => ErrorResponse::internal(),
+            },
+            Ok(false) => ErrorResponse::not_found(),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("one\ntwo\nthree", "one\n2\nthree");
+        let changes: Vec<(LineChange, &str)> = diff
+            .iter()
+            .map(|line| (line.change, line.line.as_str()))
+            .collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                (LineChange::Same, "one"),
+                (LineChange::Removed, "two"),
+                (LineChange::Added, "2"),
+                (LineChange::Same, "three"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! # 🧑‍💼 Blog editorial roles
+//!
+//! Accounts write and manage blog posts with a [`BlogRole`], stored
+//! as their account type:
+//!
+//! - `author`: creates drafts, and edits the posts they wrote or
+//!   co-wrote. Only the author of a post changes its co-authors, and
+//!   can not give it to another account
+//! - `editor`: edits and publishes any post, and manages the tags
+//! - `admin`: everything editors can do, deletes posts and tags, and
+//!   manages the trash
+//!
+//! Admin accounts give accounts the author or editor role with the
+//! [`SetBlogRoleKontroller`], the account is taken from the `username`
+//! url parameter. Admins themselves are promoted and demoted with the
+//! role kontrollers of the `accounts` kontroller.
+
+use super::database::Database;
+use super::{BlogPost, BlogPostRevision};
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::accounts::AuditEntry;
+use crate::login::{admin_username, kpassport_error, real_username};
+use chrono::Utc;
+use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// ## 🧑‍💼 Blog editorial role
+///
+/// Roles are ordered, every role can do what the roles before it can.
+#[derive(Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum BlogRole {
+    /// Writes drafts and edits their own posts
+    Author,
+    /// Edits and publishes any post
+    Editor,
+    /// Site admin
+    Admin,
+}
+
+impl BlogRole {
+    /// Name of the role, as stored in the account type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlogRole::Author => "author",
+            BlogRole::Editor => "editor",
+            BlogRole::Admin => "admin",
+        }
+    }
+
+    /// Get a role from its name
+    pub fn from_name(name: &str) -> Option<BlogRole> {
+        [BlogRole::Author, BlogRole::Editor, BlogRole::Admin]
+            .into_iter()
+            .find(|role| role.as_str() == name)
+    }
+
+    /// Check if an account with this role can edit a blog post.
+    /// Authors can only edit the posts they wrote or co-wrote.
+    pub fn can_edit(&self, username: &str, post: &BlogPost) -> bool {
+        *self >= BlogRole::Editor
+            || post.author.as_deref() == Some(username)
+            || post
+                .co_authors
+                .iter()
+                .any(|co_author| co_author == username)
+    }
+
+    /// Check if an account with this role can set the author and the
+    /// co-authors of a blog post, `None` leaves them unchanged. Authors
+    /// can only make themselves the author, and only the post's author
+    /// can change its co-authors.
+    pub fn can_set_authors(
+        &self,
+        username: &str,
+        post: &BlogPost,
+        author: Option<Option<&str>>,
+        co_authors: Option<&[String]>,
+    ) -> bool {
+        if *self >= BlogRole::Editor {
+            return true;
+        }
+
+        let author_changed = author.is_some_and(|author| author != post.author.as_deref());
+        let co_authors_changed =
+            co_authors.is_some_and(|co_authors| co_authors != post.co_authors.as_slice());
+
+        if post.author.as_deref() == Some(username) {
+            !author_changed || author == Some(Some(username))
+        } else {
+            !author_changed && !co_authors_changed
+        }
+    }
+
+    /// Check if an account with this role can restore a revision of a
+    /// blog post, with its authors and status. Authors can not publish
+    /// or unpublish a post by restoring a revision.
+    pub fn can_restore(
+        &self,
+        username: &str,
+        post: &BlogPost,
+        revision: &BlogPostRevision,
+    ) -> bool {
+        let status_changed = revision.status.is_some_and(|status| status != post.status);
+
+        self.can_set_authors(
+            username,
+            post,
+            Some(revision.author.as_deref()),
+            revision.co_authors.as_deref(),
+        ) && (*self >= BlogRole::Editor || !status_changed)
+    }
+}
+
+/// Get the real username and blog role of the account behind the
+/// kpassport of a request, or the error response to send if the
+/// account has no role
+pub fn blog_role(
+    kong: &Kong,
+    accounts_db: Arc<Mutex<AccountsDatabase>>,
+) -> Result<(String, BlogRole), server::Response> {
+    let k = match &kong.kpassport {
+        Some(k) => k,
+        None => return Err(ErrorResponse::unauthorized()),
+    };
+
+    let username = real_username(k, accounts_db.clone()).map_err(kpassport_error)?;
+    let account = accounts_db
+        .lock()
+        .unwrap()
+        .private_get_account_by_username(&username)
+        .map_err(|_| ErrorResponse::internal())?;
+
+    match account
+        .and_then(|account| account.account_type)
+        .as_deref()
+        .and_then(BlogRole::from_name)
+    {
+        Some(role) => Ok((username, role)),
+        None => Err(ErrorResponse::unauthorized()),
+    }
+}
+
+/// Check that the account behind a request has at least a blog role,
+/// returns its real username or the error response to send
+pub fn username_with_role(
+    kong: &Kong,
+    accounts_db: Arc<Mutex<AccountsDatabase>>,
+    role: BlogRole,
+) -> Result<String, server::Response> {
+    match blog_role(kong, accounts_db)? {
+        (username, account_role) if account_role >= role => Ok(username),
+        _ => Err(ErrorResponse::unauthorized()),
+    }
+}
+
+/// Check that the account behind a request can edit a blog post,
+/// returns its real username and role or the error response to send.
+/// Posts in the trash can not be edited until they are restored.
+pub fn post_editor(
+    kong: &Kong,
+    accounts_db: Arc<Mutex<AccountsDatabase>>,
+    database: &Arc<Mutex<Database>>,
+    id: i64,
+) -> Result<(String, BlogRole), server::Response> {
+    let (username, role) = blog_role(kong, accounts_db)?;
+
+    match database.lock().unwrap().get_blog_by_id(id) {
+        Ok(Some(post)) if post.deleted_at.is_some() => Err(ErrorResponse::not_found()),
+        Ok(Some(post)) if role.can_edit(&username, &post) => Ok((username, role)),
+        Ok(Some(_)) => Err(ErrorResponse::unauthorized()),
+        Ok(None) => Err(ErrorResponse::not_found()),
+        Err(_) => Err(ErrorResponse::internal()),
+    }
+}
+
+/// ## 🧑‍💼 Set blog role kontroller
+///
+/// Gives the account the author or editor role, or removes its role if
+/// `role` is `None`. Admin accounts keep their role.
+pub struct SetBlogRoleKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// The role given to the account, `author` or `editor`
+    pub role: Option<BlogRole>,
+}
+
+impl Kontrol for SetBlogRoleKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Set the blog role of an account
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let admin = match admin_username(kong, self.accounts_database.clone()) {
+            Ok(admin) => admin,
+            Err(response) => return response,
+        };
+
+        // Admins are managed with the account role kontrollers
+        if self.role == Some(BlogRole::Admin) {
+            return ErrorResponse::bad_request();
+        }
+
+        let username = match kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("username"))
+        {
+            Some(username) => username.to_string(),
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let db = self.accounts_database.lock().unwrap();
+
+        match db.private_get_account_by_username(&username) {
+            Ok(Some(account)) if account.account_type.as_deref() == Some("admin") => {
+                return ErrorResponse::bad_request()
+            }
+            Ok(Some(_)) => (),
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        match db.set_account_type(&username, self.role.map(|role| role.as_str())) {
+            Ok(true) => {
+                let entry = AuditEntry {
+                    actor: admin,
+                    action: format!(
+                        "blog_role_{}",
+                        self.role.map_or("none", |role| role.as_str())
+                    ),
+                    subject: Some(username),
+                    date: Utc::now(),
+                };
+
+                if db.create_audit_entry(&entry).is_err() {
+                    return ErrorResponse::internal();
+                }
+
+                server::Response::text("Blog role has been set.").with_status_code(200)
+            }
+            Ok(false) => ErrorResponse::not_found(),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{ContentFormat, PostStatus};
+    use super::*;
+
+    #[test]
+    fn test_can_edit() {
+        let post = BlogPost {
+            id: 1,
+            title: "Title".to_string(),
+            subtitle: None,
+            overview: None,
+            author: Some("alice".to_string()),
+            cover: None,
+            cover_variants: None,
+            content: "Content".to_string(),
+            format: ContentFormat::Markdown,
+            content_html: "<p>Content</p>".to_string(),
+            date: None,
+            slug: "title".to_string(),
+            status: PostStatus::Draft,
+            version: 1,
+            deleted_at: None,
+            word_count: 1,
+            reading_time: 1,
+            excerpt: "Content".to_string(),
+            tags: vec![],
+            co_authors: vec!["bob".to_string()],
+        };
+
+        assert!(BlogRole::Author.can_edit("alice", &post));
+        assert!(BlogRole::Author.can_edit("bob", &post));
+        assert!(!BlogRole::Author.can_edit("carol", &post));
+        assert!(BlogRole::Editor.can_edit("carol", &post));
+        assert!(BlogRole::Admin > BlogRole::Editor);
+
+        let bob = ["bob".to_string()];
+        let carol = ["carol".to_string()];
+
+        // The author manages the co-authors, but keeps the post
+        assert!(BlogRole::Author.can_set_authors(
+            "alice",
+            &post,
+            Some(Some("alice")),
+            Some(&carol)
+        ));
+        assert!(!BlogRole::Author.can_set_authors("alice", &post, Some(Some("bob")), None));
+        assert!(!BlogRole::Author.can_set_authors("alice", &post, Some(None), None));
+
+        // Co-authors leave the authors as they are
+        assert!(BlogRole::Author.can_set_authors("bob", &post, Some(Some("alice")), Some(&bob)));
+        assert!(BlogRole::Author.can_set_authors("bob", &post, None, None));
+        assert!(!BlogRole::Author.can_set_authors("bob", &post, Some(Some("bob")), None));
+        assert!(!BlogRole::Author.can_set_authors("bob", &post, Some(None), None));
+        assert!(!BlogRole::Author.can_set_authors("bob", &post, None, Some(&carol)));
+
+        assert!(BlogRole::Editor.can_set_authors("carol", &post, Some(Some("carol")), Some(&[])));
+
+        let mut revision = BlogPostRevision {
+            id: 1,
+            post_id: 1,
+            title: "Title".to_string(),
+            subtitle: None,
+            overview: None,
+            author: Some("alice".to_string()),
+            cover: None,
+            cover_variants: None,
+            content: "Content".to_string(),
+            format: ContentFormat::Markdown,
+            slug: Some("title".to_string()),
+            editor: None,
+            date: Utc::now(),
+            tags: None,
+            co_authors: Some(vec!["bob".to_string()]),
+            status: Some(PostStatus::Draft),
+        };
+
+        // Restoring follows the same rules, and keeps authors from
+        // publishing
+        assert!(BlogRole::Author.can_restore("bob", &post, &revision));
+        revision.status = Some(PostStatus::Published);
+        assert!(!BlogRole::Author.can_restore("alice", &post, &revision));
+        assert!(BlogRole::Editor.can_restore("carol", &post, &revision));
+        revision.status = None;
+        revision.co_authors = Some(vec![]);
+        assert!(BlogRole::Author.can_restore("alice", &post, &revision));
+        assert!(!BlogRole::Author.can_restore("bob", &post, &revision));
+    }
+}
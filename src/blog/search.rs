@@ -0,0 +1,127 @@
+//! # 🔎 Blog search kontroller
+//!
+//! This __kontroller__ searches the published blog posts by their
+//! title, subtitle, overview and content. The search is read from the
+//! query string of the request, see [`SearchQuery`]:
+//!
+//! - `q`: the words to search for
+//! - `page` and `limit`
+//! - `author` and `tag` (the slug of a tag) filters
+//!
+//! Results are ranked by relevance, matches in the title count the
+//! most. Every result has its title and an extract of the post with
+//! the words found in `<mark>` tags, the rest of the text is escaped so
+//! both can be used as HTML.
+
+use super::database::Database;
+use super::SearchQuery;
+use crate::error::KontrollerError;
+use crate::xml::escape;
+use chrono::Utc;
+use kong::{server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// Marks the start of a match in the search index output
+pub(crate) const MARK_START: char = '\u{2}';
+/// Marks the end of a match in the search index output
+pub(crate) const MARK_END: char = '\u{3}';
+
+/// Escape text from the search index and turn its match marks into
+/// `<mark>` tags
+pub(crate) fn mark_html(text: &str) -> String {
+    escape(text)
+        .replace(MARK_START, "<mark>")
+        .replace(MARK_END, "</mark>")
+}
+
+/// ## 🔎 Search blog posts kontroller
+pub struct SearchBlogPostsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for SearchBlogPostsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        SearchQuery::from_request(request).map(|query| query.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        if let Some(input) = input {
+            match SearchQuery::from_json_str(input.to_string()) {
+                Ok(query) if query.is_valid() => Ok(Some(query.as_json())),
+                _ => Err(()),
+            }
+        } else {
+            Err(())
+        }
+    }
+
+    /// Search published blogs
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let query = match &kong.input {
+            Some(input) => match SearchQuery::from_json_str(input.to_string()) {
+                Ok(query) => query,
+                Err(_) => return ErrorResponse::bad_request(),
+            },
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let res = self.database.lock().unwrap().search(&query, Utc::now());
+
+        match res {
+            Ok(page) => server::Response::json(&page).with_status_code(200),
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search_query() {
+        let query = SearchQuery {
+            q: "rust \"OR\" sql".to_string(),
+            ..Default::default()
+        };
+        assert!(query.is_valid());
+        assert_eq!(query.match_expression(), "\"rust\" \"OR\" \"sql\"*");
+
+        // Pages far past the end are refused before an offset is computed
+        let far = SearchQuery {
+            page: Some(i64::MAX),
+            ..query.clone()
+        };
+        assert!(!far.is_valid());
+
+        let query = SearchQuery {
+            q: "\"(*)\" ".to_string(),
+            ..Default::default()
+        };
+        assert!(!query.is_valid());
+
+        assert_eq!(
+            mark_html("<b>\u{2}Rust\u{3}</b>"),
+            "&lt;b&gt;<mark>Rust</mark>&lt;/b&gt;"
+        );
+    }
+}
@@ -0,0 +1,38 @@
+//! # 🗺️ Blog sitemap source
+//!
+//! Lists the published blog posts in the sitemap of the `sitemap`
+//! kontroller. A post's last modification date is the latest of its
+//! publish date and the dates of its revisions.
+
+use super::database::Database;
+use crate::error::KontrollerError;
+use crate::sitemap::{SitemapSource, SitemapUrl};
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+
+/// ## 🗺️ Blog sitemap source
+pub struct BlogSitemap {
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// URL path blog post slugs are appended to, eg `/blog/`
+    pub permalink_prefix: String,
+}
+
+impl SitemapSource for BlogSitemap {
+    /// The permalinks of the published blog posts
+    fn sitemap_urls(&self) -> Result<Vec<SitemapUrl>, KontrollerError> {
+        let posts = self
+            .database
+            .lock()
+            .unwrap()
+            .get_last_modified(Utc::now())?;
+
+        Ok(posts
+            .into_iter()
+            .map(|(slug, lastmod)| SitemapUrl {
+                path: format!("{}{slug}", self.permalink_prefix),
+                lastmod,
+            })
+            .collect())
+    }
+}
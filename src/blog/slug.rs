@@ -0,0 +1,64 @@
+//! # 🔗 Blog post slugs
+//!
+//! Every blog post has a unique slug, generated from its title or
+//! provided by the author, used to build readable permalinks such as
+//! `/blog/my-first-post`. When a post's slug changes its old slug is
+//! kept as a redirect to the post.
+
+/// Longest generated slug, in characters
+pub const MAX_SLUG_LENGTH: usize = 80;
+
+/// Slug used when a title has no characters usable in a slug
+pub const FALLBACK_SLUG: &str = "post";
+
+/// Turn a title into a slug: lowercase ASCII letters and digits
+/// separated by single dashes
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            if slug.len() >= MAX_SLUG_LENGTH {
+                break;
+            }
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+
+    if slug.is_empty() {
+        FALLBACK_SLUG.to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Check if a name has characters a slug can be made from, tags are
+/// refused without them so they do not all get the fallback slug
+pub fn has_slug_characters(name: &str) -> bool {
+    name.chars().any(|c| c.is_ascii_alphanumeric())
+}
+
+/// Add a collision suffix to a slug, eg `my-post-2`
+pub fn with_suffix(slug: &str, n: u32) -> String {
+    format!("{slug}-{n}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Rust  &  SQLite -- 2023 "), "rust-sqlite-2023");
+        assert_eq!(slugify("¿?"), FALLBACK_SLUG);
+        assert!(has_slug_characters("Rust 2023"));
+        assert!(!has_slug_characters("日本語"));
+        assert_eq!(slugify(&"a".repeat(200)).len(), MAX_SLUG_LENGTH);
+        assert_eq!(with_suffix("hello-world", 2), "hello-world-2");
+    }
+}
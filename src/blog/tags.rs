@@ -0,0 +1,445 @@
+//! # 🏷️ Blog tag kontrollers
+//!
+//! These __kontrollers__ allow an editor account to create, rename and
+//! merge the tags and categories of blog posts, and an admin account
+//! to delete them. The tag is taken from the `id` url parameter.
+//!
+//! Anyone can list the tags with their number of public posts, and
+//! the public posts with a tag, taken from the `slug` url parameter.
+
+use super::database::Database;
+use super::inputs::{MergeTagsInput, TagInput};
+use super::roles::{username_with_role, BlogRole};
+use super::{BlogPostQuery, BlogPostSummary, TagKind};
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::error::KontrollerError;
+use crate::login::admin_username;
+use chrono::Utc;
+use kong::{json, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// Get the tag id from the `id` url parameter
+fn tag_id(kong: &Kong) -> Option<i64> {
+    kong.url_parameters
+        .as_ref()
+        .and_then(|url_params| url_params.find("id"))
+        .and_then(|id| id.parse().ok())
+}
+
+/// Read a tag input from a JSON request body
+fn tag_input(request: &server::Request) -> Option<JsonValue> {
+    let input: Result<TagInput, server::input::json::JsonError> =
+        server::input::json_input(request);
+
+    input.ok().map(|input| input.as_json())
+}
+
+/// Validate a tag input
+fn validate_tag_input(input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+    match input.map(|input| TagInput::from_json_str(input.to_string())) {
+        Some(Ok(input)) if input.is_valid() => Ok(Some(input.as_json())),
+        _ => Err(()),
+    }
+}
+
+/// ## ✨ Tag creation kontroller
+pub struct CreateTagKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for CreateTagKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        tag_input(request)
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        validate_tag_input(input)
+    }
+
+    /// Create tag
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) =
+            username_with_role(kong, self.accounts_database.clone(), BlogRole::Editor)
+        {
+            return response;
+        }
+
+        let input = match kong
+            .input
+            .as_ref()
+            .map(|input| TagInput::from_json_str(input.to_string()))
+        {
+            Some(Ok(input)) => input,
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let res = self
+            .database
+            .lock()
+            .unwrap()
+            .create_tag(&input.name, input.kind.unwrap_or_default());
+
+        match res {
+            Ok(tag) => server::Response::json(&tag).with_status_code(201),
+            Err(err) => match err {
+                // A tag with the same slug exists
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
+
+/// ## ✏️ Tag rename kontroller
+///
+/// Renames the tag and regenerates its slug, the kind of the tag is
+/// changed if one is provided.
+pub struct RenameTagKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for RenameTagKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        tag_input(request)
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        validate_tag_input(input)
+    }
+
+    /// Rename tag
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) =
+            username_with_role(kong, self.accounts_database.clone(), BlogRole::Editor)
+        {
+            return response;
+        }
+
+        let input = match kong
+            .input
+            .as_ref()
+            .map(|input| TagInput::from_json_str(input.to_string()))
+        {
+            Some(Ok(input)) => input,
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let id = if let Some(id) = tag_id(kong) {
+            id
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        let res = self
+            .database
+            .lock()
+            .unwrap()
+            .rename_tag(id, &input.name, input.kind);
+
+        match res {
+            Ok(Some(tag)) => server::Response::json(&tag).with_status_code(200),
+            Ok(None) => ErrorResponse::not_found(),
+            Err(err) => match err {
+                // Another tag has the same slug
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
+
+/// ## 🔀 Tag merging kontroller
+///
+/// Moves the posts of the tag to the tag in the `into` field of the
+/// JSON body, and deletes the tag.
+pub struct MergeTagsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for MergeTagsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<MergeTagsInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        input.ok().map(|input| input.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.map(|input| MergeTagsInput::from_json_str(input.to_string())) {
+            Some(Ok(input)) => Ok(Some(input.as_json())),
+            _ => Err(()),
+        }
+    }
+
+    /// Merge tags
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) =
+            username_with_role(kong, self.accounts_database.clone(), BlogRole::Editor)
+        {
+            return response;
+        }
+
+        let input = match kong
+            .input
+            .as_ref()
+            .map(|input| MergeTagsInput::from_json_str(input.to_string()))
+        {
+            Some(Ok(input)) => input,
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let id = match tag_id(kong) {
+            // A tag can not be merged into itself
+            Some(id) if id != input.into => id,
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let mut db = self.database.lock().unwrap();
+
+        match db.merge_tags(id, input.into) {
+            Ok(true) => match db.get_tag(input.into) {
+                Ok(Some(tag)) => server::Response::json(&tag).with_status_code(200),
+                Ok(None) => ErrorResponse::not_found(),
+                Err(_) => ErrorResponse::internal(),
+            },
+            Ok(false) => ErrorResponse::not_found(),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## 🗑️ Tag deletion kontroller
+pub struct DeleteTagKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for DeleteTagKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Delete tag
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let id = if let Some(id) = tag_id(kong) {
+            id
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        match self.database.lock().unwrap().delete_tag(id) {
+            Ok(true) => server::Response::text("Tag has been deleted.").with_status_code(200),
+            Ok(false) => ErrorResponse::not_found(),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## 📋 Get tags kontroller
+///
+/// Lists all tags with their number of public posts. The `kind` query
+/// parameter limits the list to tags or categories.
+pub struct GetTagsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for GetTagsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        Some(json!({ "kind": request.get_param("kind") }))
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.as_ref().map(|input| &input["kind"]) {
+            Some(JsonValue::String(kind)) if TagKind::from_name(kind).is_none() => Err(()),
+            _ => Ok(input),
+        }
+    }
+
+    /// Get tags
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let kind = kong
+            .input
+            .as_ref()
+            .and_then(|input| input["kind"].as_str())
+            .and_then(TagKind::from_name);
+
+        match self.database.lock().unwrap().get_tags(kind, Utc::now()) {
+            Ok(tags) => server::Response::json(&tags).with_status_code(200),
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
+
+/// ## 📰 Get blog posts by tag kontroller
+///
+/// Lists the public posts with the tag in the `slug` url parameter a
+/// page at a time, the page, filters and sort order are read from the
+/// query string like in
+/// [`super::get_all::GetAllBlogPostsKontroller`].
+pub struct GetTagPostsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for GetTagPostsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        BlogPostQuery::from_request(request).map(|query| query.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.map(|input| BlogPostQuery::from_json_str(input.to_string())) {
+            Some(Ok(query)) if query.is_valid() => Ok(Some(query.as_json())),
+            _ => Err(()),
+        }
+    }
+
+    /// Get the posts with a tag
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let slug = match kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("slug"))
+        {
+            Some(slug) => slug.to_string(),
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let query = match &kong.input {
+            Some(input) => match BlogPostQuery::from_json_str(input.to_string()) {
+                Ok(query) => query,
+                Err(_) => return ErrorResponse::bad_request(),
+            },
+            None => BlogPostQuery::default(),
+        };
+        let query = BlogPostQuery {
+            tag: Some(slug.clone()),
+            ..query
+        };
+
+        let db = self.database.lock().unwrap();
+
+        match db.get_tag_by_slug(&slug) {
+            Ok(Some(_)) => (),
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        match db.get_page(&query, Utc::now()) {
+            Ok(page) => {
+                if query.summary.unwrap_or(false) {
+                    server::Response::json(&page.map(BlogPostSummary::from)).with_status_code(200)
+                } else {
+                    server::Response::json(&page).with_status_code(200)
+                }
+            }
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! # 🗑️ Blog trash kontrollers
+//!
+//! Deleted blog posts are moved to the trash instead of being removed,
+//! see [`super::delete`]. Posts in the trash can not be read publicly
+//! nor edited, they keep their slug, tags, authors and revisions.
+//!
+//! These __kontrollers__ allow admin accounts to list the trash, to
+//! restore a post and to purge a post permanently, the post is taken
+//! from the `id` url parameter. Purging a post removes its revisions,
+//! and its cover photos unless another post uses them, and is written
+//! to the audit log.
+//!
+//! Posts in the trash for longer than the retention period are purged
+//! automatically, whenever a post is deleted or the trash is listed.
+//! Apps can also purge them on their own schedule with
+//! [`purge_expired_trash`].
+//!
+//! Data other kontrollers keep about a post is removed with the
+//! [`PurgeHook`]s of the kontrollers, eg the comments database.
+
+use super::covers::remove_unused_covers;
+use super::database::Database;
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::accounts::AuditEntry;
+use crate::error::KontrollerError;
+use crate::login::admin_username;
+use crate::media::storage::MediaStorage;
+use chrono::{DateTime, Duration, Utc};
+use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// Get the blog post id from the `id` url parameter
+fn post_id(kong: &Kong) -> Option<i64> {
+    kong.url_parameters
+        .as_ref()
+        .and_then(|url_params| url_params.find("id"))
+        .and_then(|id| id.parse().ok())
+}
+
+/// Cleanup of the data kept about a blog post outside of the blog
+/// database, run when the post is purged
+pub trait PurgeHook: Send + Sync {
+    /// Remove the data of a purged blog post
+    fn purge(&self, post_id: i64) -> Result<(), KontrollerError>;
+}
+
+/// Run the purge hooks, delete a post permanently, and remove the
+/// covers it no longer shares with other posts from the storage. The
+/// hooks run first, so a post stays in the trash until all its data is
+/// removed.
+pub fn purge_post(
+    database: &mut Database,
+    storage: &dyn MediaStorage,
+    hooks: &[Arc<dyn PurgeHook>],
+    id: i64,
+) -> Result<(), KontrollerError> {
+    let covers = database.get_post_covers(id)?;
+
+    for hook in hooks {
+        hook.purge(id)?;
+    }
+    database.delete(id)?;
+    remove_unused_covers(database, storage, &covers)
+}
+
+/// Purge the posts moved to the trash before a date, returns their ids
+pub fn purge_expired_trash(
+    database: &mut Database,
+    storage: &dyn MediaStorage,
+    hooks: &[Arc<dyn PurgeHook>],
+    before: DateTime<Utc>,
+) -> Result<Vec<i64>, KontrollerError> {
+    let expired = database.get_expired_trash(before)?;
+
+    for id in &expired {
+        purge_post(database, storage, hooks, *id)?;
+    }
+
+    Ok(expired)
+}
+
+/// ## 🗑️ Blog trash kontroller
+///
+/// Lists the posts in the trash, most recently deleted first, once the
+/// expired posts are purged.
+pub struct GetTrashKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Storage of the cover photos
+    pub media_storage: Arc<dyn MediaStorage>,
+    /// Cleanup of the data kept about purged posts
+    pub purge_hooks: Vec<Arc<dyn PurgeHook>>,
+    /// How long deleted posts are kept in the trash
+    pub retention: Duration,
+}
+
+impl Kontrol for GetTrashKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// List the trash
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let mut db = self.database.lock().unwrap();
+        let before = Utc::now() - self.retention;
+
+        match purge_expired_trash(&mut db, &*self.media_storage, &self.purge_hooks, before)
+            .and_then(|_| db.get_trash())
+        {
+            Ok(posts) => server::Response::json(&posts).with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## ♻️ Blog post restore kontroller
+///
+/// Takes a post out of the trash, with the status it had when it was
+/// deleted.
+pub struct RestoreBlogPostKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for RestoreBlogPostKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Restore blog post
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let id = match post_id(kong) {
+            Some(id) => id,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let db = self.database.lock().unwrap();
+
+        match db.restore_from_trash(id) {
+            Ok(true) => match db.get_blog_by_id(id) {
+                Ok(Some(post)) => server::Response::json(&post).with_status_code(200),
+                Ok(None) => ErrorResponse::not_found(),
+                Err(_) => ErrorResponse::internal(),
+            },
+            Ok(false) => ErrorResponse::not_found(),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## 🔥 Blog post purge kontroller
+///
+/// Deletes a post in the trash permanently, posts that are not in the
+/// trash are not found.
+pub struct PurgeBlogPostKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Storage of the cover photos
+    pub media_storage: Arc<dyn MediaStorage>,
+    /// Cleanup of the data kept about purged posts
+    pub purge_hooks: Vec<Arc<dyn PurgeHook>>,
+}
+
+impl Kontrol for PurgeBlogPostKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Purge blog post
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let admin = match admin_username(kong, self.accounts_database.clone()) {
+            Ok(admin) => admin,
+            Err(response) => return response,
+        };
+
+        let id = match post_id(kong) {
+            Some(id) => id,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let mut db = self.database.lock().unwrap();
+
+        match db.get_blog_by_id(id) {
+            Ok(Some(post)) if post.deleted_at.is_some() => (),
+            Ok(_) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        if purge_post(&mut db, &*self.media_storage, &self.purge_hooks, id).is_err() {
+            return ErrorResponse::internal();
+        }
+
+        let entry = AuditEntry {
+            actor: admin,
+            action: "blog_post_purge".to_string(),
+            subject: Some(id.to_string()),
+            date: Utc::now(),
+        };
+
+        if self
+            .accounts_database
+            .lock()
+            .unwrap()
+            .create_audit_entry(&entry)
+            .is_err()
+        {
+            return ErrorResponse::internal();
+        }
+
+        server::Response::text("Article has been purged.").with_status_code(200)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::DatabaseBlogPostInput;
+    use super::*;
+    use crate::media::storage::LocalStorage;
+    use crate::test_utils::{blog_input, TempDatabase};
+
+    /// A purge hook whose data can not be removed
+    struct FailingHook;
+
+    impl PurgeHook for FailingHook {
+        fn purge(&self, _post_id: i64) -> Result<(), KontrollerError> {
+            Err(KontrollerError::DbSQL)
+        }
+    }
+
+    #[test]
+    fn test_purge_post() {
+        let db_file = TempDatabase::new("BLOG_PURGE_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+        let storage = LocalStorage {
+            root: "BLOG_PURGE_TEST_MEDIA".to_string(),
+            url_prefix: "uploads".to_string(),
+        };
+
+        let now = Utc::now();
+        let post = db
+            .create_blog(&DatabaseBlogPostInput {
+                tags: Some(vec!["Mistakes".to_string()]),
+                ..blog_input()
+            })
+            .unwrap();
+        db.trash(post.id, now - Duration::days(40)).unwrap();
+
+        // The post stays in the trash until the hooks removed its data
+        let hooks: Vec<Arc<dyn PurgeHook>> = vec![Arc::new(FailingHook)];
+        assert!(purge_expired_trash(&mut db, &storage, &hooks, now).is_err());
+        assert_eq!(db.get_trash().unwrap().len(), 1);
+        assert_eq!(db.get_blog_by_id(post.id).unwrap().unwrap().tags.len(), 1);
+
+        assert_eq!(
+            purge_expired_trash(&mut db, &storage, &[], now).unwrap(),
+            vec![post.id]
+        );
+        assert!(db.get_blog_by_id(post.id).unwrap().is_none());
+        assert!(db.get_trash().unwrap().is_empty());
+    }
+}
@@ -3,13 +3,24 @@
 //! This __kontroller__ is used to edit an existing article
 //! It gets the input from the HTTP request validates it
 //! and creates the blog post from the validated input. The blog post
-//! is stored in an SQLite database.
+//! is stored in an SQLite database, along with a revision of the
+//! updated blog post.
+//!
+//! Editors can edit any post, authors only the posts they wrote or
+//! co-wrote, see [`super::roles`].
+//!
+//! A replaced cover photo is removed from the media storage once no
+//! revision of the post uses it, see [`super::covers`].
 
+use super::authors::authors_exist;
+use super::covers::{remove_unused_covers, store_cover};
 use super::database::Database;
-use super::{CreateBlogInput, DatabaseBlogPostInput};
+use super::roles::{post_editor, BlogRole};
+use super::{ContentFormat, CreateBlogInput, DatabaseBlogPostInput};
 use crate::accounts::database::Database as AccountsDatabase;
 use crate::error::KontrollerError;
-use crate::login::is_admin;
+use crate::media::variants::VariantSize;
+use crate::media::{storage::MediaStorage, MediaPolicy};
 use kong::{inputs::UserInput, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
 use std::sync::{Arc, Mutex};
 
@@ -23,35 +34,88 @@ pub struct ArticleUpdateKontroller {
     pub database: Arc<Mutex<Database>>,
     /// Accounts database
     pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Storage of the cover photos
+    pub media_storage: Arc<dyn MediaStorage>,
+    /// Types and largest size of the cover photos
+    pub cover_policy: MediaPolicy,
+    /// Sizes of the resized variants of the cover photos, see
+    /// [`VariantSize::defaults`]
+    pub cover_sizes: Vec<VariantSize>,
 }
 
 impl ArticleUpdateKontroller {
-    /// Store uploaded blog photos
-    fn store_cover_photo(
-        dir_name: &str,
-        photo: server::input::post::BufferedFile,
-    ) -> std::io::Result<String> {
-        let directory = format!("./www/uploads/blog_photos/{dir_name}");
-
-        // create upload directory if it does not exist
-        std::fs::create_dir_all(&directory)?;
-
-        let photo_name = if let Some(photo_name) = &photo.filename {
-            photo_name.clone()
-        } else {
-            "".to_string()
+    /// Update a post as an account that can edit it, see
+    /// [`post_editor`]
+    fn update(
+        &self,
+        id: i64,
+        editor: &str,
+        role: BlogRole,
+        mut input: CreateBlogInput,
+    ) -> server::Response {
+        let cover = input.cover.take();
+        let blog: DatabaseBlogPostInput = input.into();
+        let blog = DatabaseBlogPostInput {
+            author: blog.author.filter(|author| !author.is_empty()),
+            ..blog
         };
-        let timestamp = chrono::Utc::now().timestamp();
-        let file = format!("{directory}/{timestamp}-{photo_name}");
 
-        // Store photo in directory
-        std::fs::write(&file, &photo.data)?;
+        match authors_exist(&blog, self.accounts_database.clone()) {
+            Ok(true) => (),
+            Ok(false) => return ErrorResponse::bad_request(),
+            Err(_) => return ErrorResponse::internal(),
+        }
 
-        // remeber file path
-        let directory = format!("uploads/blog_photos/{dir_name}");
-        let file = format!("{directory}/{timestamp}-{photo_name}");
+        // update article
+        let mut db = self.database.lock().unwrap();
+        let post = match db.get_blog_by_id(id) {
+            Ok(Some(post)) => post,
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        };
 
-        Ok(file)
+        // Authors can not give their posts to other accounts
+        if !role.can_set_authors(
+            editor,
+            &post,
+            blog.author.as_deref().map(Some),
+            blog.co_authors.as_deref(),
+        ) {
+            return ErrorResponse::unauthorized();
+        }
+        let old_cover = post.cover.map(|cover| (cover, post.cover_variants));
+
+        let blog = match cover {
+            Some(cover) => match store_cover(
+                &*self.media_storage,
+                &self.cover_policy,
+                &cover,
+                &self.cover_sizes,
+            ) {
+                Ok(cover) => DatabaseBlogPostInput {
+                    cover: Some(cover.url),
+                    cover_variants: Some(cover.variants),
+                    ..blog
+                },
+                Err(response) => return response,
+            },
+            None => blog,
+        };
+
+        let res = db.update(id, &blog, Some(editor)).and_then(|()| {
+            // The replaced cover is kept while a revision uses it
+            match old_cover.filter(|_| blog.cover.is_some()) {
+                Some(cover) => remove_unused_covers(&db, &*self.media_storage, &[cover]),
+                None => Ok(()),
+            }
+        });
+        match res {
+            Ok(()) => server::Response::text("").with_status_code(200),
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
     }
 }
 
@@ -74,35 +138,36 @@ impl Kontrol for ArticleUpdateKontroller {
             author: Option<String>,
             cover: Option<server::input::post::BufferedFile>,
             content: String,
+            slug: Option<String>,
+            tags: Option<String>,
+            co_authors: Option<String>,
+            format: Option<String>,
         }) {
-            // store cover image
-            if let Some(cover) = input.cover {
-                if let Ok(cover) = ArticleUpdateKontroller::store_cover_photo(&input.title, cover) {
-                    let input = CreateBlogInput {
-                        title: input.title,
-                        subtitle: input.subtitle,
-                        overview: input.overview,
-                        author: input.author,
-                        cover: Some(cover),
-                        content: input.content,
-                    };
-
-                    Some(input.as_json())
-                } else {
-                    None
-                }
-            } else {
-                let input = CreateBlogInput {
-                    title: input.title,
-                    subtitle: input.subtitle,
-                    overview: input.overview,
-                    author: input.author,
-                    cover: None,
-                    content: input.content,
-                };
-
-                Some(input.as_json())
-            }
+            // The format is optional, but should be valid if provided
+            let format = match input.format.as_deref().filter(|s| !s.is_empty()) {
+                Some(format) => Some(ContentFormat::from_name(format)?),
+                None => None,
+            };
+
+            // The cover is stored once the account is checked
+            let cover = self.cover_policy.buffer(input.cover).ok()?;
+
+            let input = CreateBlogInput {
+                title: input.title,
+                subtitle: input.subtitle,
+                overview: input.overview,
+                author: input.author,
+                cover,
+                content: input.content,
+                slug: input.slug,
+                status: None,
+                publish_at: None,
+                tags: CreateBlogInput::names_from_list(input.tags),
+                co_authors: CreateBlogInput::names_from_list(input.co_authors),
+                format,
+            };
+
+            Some(input.as_json())
         } else {
             None
         }
@@ -112,7 +177,7 @@ impl Kontrol for ArticleUpdateKontroller {
     fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
         if let Some(input) = input {
             if let Ok(input) = CreateBlogInput::from_json_str(input.to_string()) {
-                if input.is_valid().is_ok() {
+                if input.is_valid().is_ok() && input.has_valid_tags() {
                     Ok(Some(input.as_json()))
                 } else {
                     // TODO: proper error handling
@@ -129,53 +194,118 @@ impl Kontrol for ArticleUpdateKontroller {
     }
     /// Update article
     fn kontrol(&self, kong: &Kong) -> server::Response {
-        if let Some(k) = &kong.kpassport {
-            if let Ok(admin) = is_admin(k, self.accounts_database.clone()) {
-                if admin {
-                    if let Some(input) = &kong.input {
-                        let input = CreateBlogInput::from_json_str(input.to_string());
-
-                        // Derive blog from string
-                        let blog: DatabaseBlogPostInput = if let Ok(input) = input {
-                            input.into()
-                        } else {
-                            return ErrorResponse::bad_request();
-                        };
-
-                        if let Some(url_params) = &kong.url_parameters {
-                            if let Some(id) = url_params.find("id") {
-                                if let Ok(id) = id.parse() {
-                                    // update article
-                                    let res = self.database.lock().unwrap().update(id, &blog);
-                                    match res {
-                                        Ok(()) => server::Response::text("").with_status_code(200),
-                                        Err(err) => match err {
-                                            KontrollerError::DbField => {
-                                                ErrorResponse::bad_request()
-                                            }
-                                            _ => ErrorResponse::internal(),
-                                        },
-                                    }
-                                } else {
-                                    ErrorResponse::bad_request()
-                                }
-                            } else {
-                                ErrorResponse::bad_request()
-                            }
-                        } else {
-                            ErrorResponse::bad_request()
-                        }
-                    } else {
-                        ErrorResponse::unauthorized()
-                    }
-                } else {
-                    ErrorResponse::unauthorized()
-                }
-            } else {
-                ErrorResponse::internal()
+        let id = match kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("id"))
+            .and_then(|id| id.parse().ok())
+        {
+            Some(id) => id,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        let (editor, role) =
+            match post_editor(kong, self.accounts_database.clone(), &self.database, id) {
+                Ok(account) => account,
+                Err(response) => return response,
+            };
+
+        let input = match &kong.input {
+            Some(input) => match CreateBlogInput::from_json_str(input.to_string()) {
+                Ok(input) => input,
+                Err(_) => return ErrorResponse::bad_request(),
+            },
+            None => return ErrorResponse::bad_request(),
+        };
+
+        self.update(id, &editor, role, input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::accounts::{inputs::AccountCreationInput, Account};
+    use crate::media::storage::LocalStorage;
+    use crate::test_utils::{blog_input, TempDatabase};
+
+    fn input(author: Option<&str>) -> CreateBlogInput {
+        CreateBlogInput {
+            title: "Updated".to_string(),
+            subtitle: None,
+            overview: None,
+            author: author.map(str::to_string),
+            cover: None,
+            content: "Updated content".to_string(),
+            slug: None,
+            status: None,
+            publish_at: None,
+            tags: None,
+            co_authors: None,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_update_authors() {
+        let db_file = TempDatabase::new("BLOG_UPDATE_TEST_DATABASE.sqlite");
+        let accounts_file = TempDatabase::new("BLOG_UPDATE_ACCOUNTS_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+        let mut accounts_db = AccountsDatabase::new(&accounts_file.path);
+        accounts_db.connect().unwrap();
+
+        for username in ["alice", "bob"] {
+            let account: Account = AccountCreationInput {
+                username: username.to_string(),
+                password: "correct horse battery".to_string(),
+                email: None,
+                invite: None,
             }
-        } else {
-            ErrorResponse::unauthorized()
+            .into();
+            accounts_db.create_account(&account).unwrap();
+        }
+
+        let post = db
+            .create_blog(&DatabaseBlogPostInput {
+                author: Some("alice".to_string()),
+                co_authors: Some(vec!["bob".to_string()]),
+                ..blog_input()
+            })
+            .unwrap();
+
+        let kontroller = ArticleUpdateKontroller {
+            address: "/blog/:id".to_string(),
+            method: Method::Put,
+            database: Arc::new(Mutex::new(db)),
+            accounts_database: Arc::new(Mutex::new(accounts_db)),
+            media_storage: Arc::new(LocalStorage {
+                root: "BLOG_UPDATE_TEST_MEDIA".to_string(),
+                url_prefix: "uploads".to_string(),
+            }),
+            cover_policy: MediaPolicy::images(1024),
+            cover_sizes: vec![],
+        };
+
+        // The author and the co-author update the post without the author
+        for editor in ["alice", "bob"] {
+            let response = kontroller.update(post.id, editor, BlogRole::Author, input(None));
+            assert_eq!(response.status_code, 200);
         }
+
+        // The co-author can not take the post
+        let response = kontroller.update(post.id, "bob", BlogRole::Author, input(Some("bob")));
+        assert_eq!(response.status_code, 401);
+
+        let post = kontroller
+            .database
+            .lock()
+            .unwrap()
+            .get_blog_by_id(post.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(post.title, "Updated");
+        assert_eq!(post.author.as_deref(), Some("alice"));
+        assert_eq!(post.co_authors, vec!["bob".to_string()]);
     }
 }
@@ -0,0 +1,164 @@
+//! # ✨ Comment creation kontroller
+//!
+//! This __kontroller__ is used to comment on a public blog post, the
+//! post is taken from the `id` url parameter. Comments can reply to an
+//! approved comment of the same post with `parent_id`.
+//!
+//! Comments by admins are approved right away, other comments wait in
+//! the moderation queue unless the kontroller approves comments by
+//! accounts. Pending comments are answered with `202 Accepted`.
+
+use super::database::Database;
+use super::{Comment, CommentInput, CommentStatus};
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::blog::database::Database as BlogDatabase;
+use crate::error::KontrollerError;
+use crate::login::{is_admin, kpassport_error, session_username};
+use chrono::Utc;
+use kong::{server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ✨ Create comment kontroller
+pub struct CreateCommentKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Blog database
+    pub blog_database: Arc<Mutex<BlogDatabase>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Allow comments by visitors without an account
+    pub allow_guests: bool,
+    /// Approve comments by accounts without moderation
+    pub approve_accounts: bool,
+}
+
+impl Kontrol for CreateCommentKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<CommentInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        input.ok().map(|input| input.as_json())
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.map(|input| CommentInput::from_json_str(input.to_string())) {
+            Some(Ok(input)) if input.is_valid() => Ok(Some(input.as_json())),
+            _ => Err(()),
+        }
+    }
+
+    /// Add comment
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let input = match kong
+            .input
+            .as_ref()
+            .map(|input| CommentInput::from_json_str(input.to_string()))
+        {
+            Some(Ok(input)) => input,
+            _ => return ErrorResponse::bad_request(),
+        };
+
+        let post_id: i64 = match kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("id"))
+            .and_then(|id| id.parse().ok())
+        {
+            Some(id) => id,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        // Only public posts can be commented on
+        match self.blog_database.lock().unwrap().get_blog_by_id(post_id) {
+            Ok(Some(post)) if post.is_public(Utc::now()) => (),
+            Ok(_) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        let db = self.database.lock().unwrap();
+
+        match db.comments_enabled(post_id) {
+            Ok(true) => (),
+            Ok(false) => return ErrorResponse::unauthorized(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        // Replies are only allowed to approved comments of the same post
+        if let Some(parent_id) = input.parent_id {
+            match db.get_comment(parent_id) {
+                Ok(Some(parent))
+                    if parent.post_id == post_id && parent.status == CommentStatus::Approved => {}
+                Ok(_) => return ErrorResponse::bad_request(),
+                Err(_) => return ErrorResponse::internal(),
+            }
+        }
+
+        let (username, name, email, status) = if let Some(k) = &kong.kpassport {
+            let status = match is_admin(k, self.accounts_database.clone()) {
+                Ok(true) => CommentStatus::Approved,
+                Ok(false) if self.approve_accounts => CommentStatus::Approved,
+                Ok(false) => CommentStatus::Pending,
+                Err(err) => return kpassport_error(err),
+            };
+            let username = match session_username(k, self.accounts_database.clone()) {
+                Ok(username) => username,
+                Err(err) => return kpassport_error(err),
+            };
+
+            (Some(username.clone()), username, None, status)
+        } else if self.allow_guests {
+            match input.name {
+                Some(name) => (
+                    None,
+                    name.trim().to_string(),
+                    input.email,
+                    CommentStatus::Pending,
+                ),
+                None => return ErrorResponse::bad_request(),
+            }
+        } else {
+            return ErrorResponse::unauthorized();
+        };
+
+        let comment = Comment {
+            id: 0,
+            post_id,
+            parent_id: input.parent_id,
+            username,
+            name,
+            email,
+            content: input.content.trim().to_string(),
+            status,
+            date: Utc::now(),
+        };
+
+        match db.create_comment(&comment) {
+            Ok(comment) => {
+                let status_code = match comment.status {
+                    CommentStatus::Approved => 201,
+                    _ => 202,
+                };
+                server::Response::json(&comment).with_status_code(status_code)
+            }
+            Err(err) => match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            },
+        }
+    }
+}
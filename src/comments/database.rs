@@ -0,0 +1,397 @@
+//! # 🗄️ Comments database management
+
+use super::{Comment, CommentStatus};
+use crate::blog::trash::PurgeHook;
+use crate::error::KontrollerError;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::{named_params, params, Connection, OptionalExtension, Row};
+use std::sync::Mutex;
+
+/// SQL statements and queries
+pub mod sql {
+    /// Create comments table
+    pub const CREATE_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS comments (
+        id INTEGER PRIMARY KEY,                      -- The Identifier of the comment, the Rust Type is `i64`
+        post_id INTEGER NOT NULL,                    -- The Identifier of the commented blog post
+        parent_id INTEGER,                           -- The Identifier of the comment this comment replies to
+        username TEXT,                               -- The username of the author, NULL for guests
+        name TEXT NOT NULL,                          -- The name of the author
+        email TEXT,                                  -- The email of a guest author
+        content TEXT NOT NULL,                       -- The comment
+        status TEXT NOT NULL,                        -- The moderation status, eg `pending`
+        date TEXT NOT NULL)                          -- The date the comment was posted";
+
+    /// Create the per blog post comment settings table
+    pub const CREATE_SETTINGS_TABLE: &str = "
+      CREATE TABLE IF NOT EXISTS comment_settings (
+        post_id INTEGER PRIMARY KEY,                 -- The Identifier of the blog post
+        enabled INTEGER NOT NULL)                    -- Whether the blog post can be commented on";
+
+    /// Insert a comment in the comments table
+    pub const CREATE: &str = "
+      INSERT INTO comments (
+        post_id,
+        parent_id,
+        username,
+        name,
+        email,
+        content,
+        status,
+        date
+       )
+      VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+
+    /// Get comment by id
+    pub const READ: &str = "SELECT * FROM comments WHERE id = :id;";
+
+    /// Get the comments of a blog post with a status, oldest first
+    pub const READ_BY_POST: &str =
+        "SELECT * FROM comments WHERE post_id = :post_id AND status = :status ORDER BY date, id;";
+
+    /// Get all comments with a status, oldest first
+    pub const READ_BY_STATUS: &str =
+        "SELECT * FROM comments WHERE status = :status ORDER BY date, id;";
+
+    /// Set the status of a comment
+    pub const UPDATE_STATUS: &str = "UPDATE comments SET status = ?1 WHERE id = ?2;";
+
+    /// Delete a comment and all replies to it
+    pub const DELETE_THREAD: &str = "
+      WITH RECURSIVE thread (id) AS (
+        SELECT :id
+        UNION
+        SELECT comments.id FROM comments JOIN thread ON comments.parent_id = thread.id
+      )
+      DELETE FROM comments WHERE id IN thread;";
+
+    /// Delete the comments of a blog post
+    pub const DELETE_BY_POST: &str = "DELETE FROM comments WHERE post_id = :post_id;";
+
+    /// Delete the comment settings of a blog post
+    pub const DELETE_SETTINGS: &str = "DELETE FROM comment_settings WHERE post_id = :post_id;";
+
+    /// Check if a blog post can be commented on, posts without settings can
+    pub const READ_ENABLED: &str = "SELECT enabled FROM comment_settings WHERE post_id = :post_id;";
+
+    /// Enable or disable the comments of a blog post
+    pub const UPDATE_ENABLED: &str =
+        "INSERT OR REPLACE INTO comment_settings (post_id, enabled) VALUES (?1, ?2);";
+}
+
+impl ToSql for CommentStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for CommentStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        CommentStatus::from_name(value.as_str()?).ok_or(FromSqlError::InvalidType)
+    }
+}
+
+/// Read a comment from a row of the comments table
+fn comment_from_row(row: &Row) -> rusqlite::Result<Comment> {
+    Ok(Comment {
+        id: row.get("id")?,
+        post_id: row.get("post_id")?,
+        parent_id: row.get("parent_id")?,
+        username: row.get("username")?,
+        name: row.get("name")?,
+        email: row.get("email")?,
+        content: row.get("content")?,
+        status: row.get("status")?,
+        date: row.get("date")?,
+    })
+}
+
+/// Database management system
+pub struct Database {
+    /// Database file path
+    path: String,
+    /// An SQLite connection handle
+    conn: Option<Connection>,
+}
+
+impl Database {
+    /// Create a new database controller
+    pub fn new(path: &str) -> Self {
+        Database {
+            path: path.to_string(),
+            conn: None,
+        }
+    }
+
+    /// Open SQLite connection, create tables
+    pub fn connect(&mut self) -> Result<(), KontrollerError> {
+        // Open database connection
+        let conn =
+            Connection::open(self.path.clone()).map_err(|_| KontrollerError::DbConnection)?;
+        self.conn = Some(conn);
+
+        // Create database tables if they do not already exist
+        match &mut self.conn {
+            Some(conn) => {
+                let tx = conn
+                    .transaction()
+                    .map_err(|_| KontrollerError::DbTransaction)?;
+
+                tx.execute(sql::CREATE_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.execute(sql::CREATE_SETTINGS_TABLE, ())
+                    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+                tx.commit().map_err(|_| KontrollerError::DbTableCreation)?;
+
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Create a new comment, the `id` of `comment` is ignored and the
+    /// stored comment is returned
+    pub fn create_comment(&self, comment: &Comment) -> Result<Comment, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(
+                    sql::CREATE,
+                    params![
+                        comment.post_id,
+                        comment.parent_id,
+                        comment.username,
+                        comment.name,
+                        comment.email,
+                        comment.content,
+                        comment.status,
+                        comment.date
+                    ],
+                )
+                .map_err(|_| KontrollerError::DbField)?;
+
+                Ok(Comment {
+                    id: conn.last_insert_rowid(),
+                    ..comment.clone()
+                })
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get a comment by its id
+    pub fn get_comment(&self, id: i64) -> Result<Option<Comment>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => conn
+                .query_row(sql::READ, named_params! { ":id": id }, comment_from_row)
+                .optional()
+                .map_err(|_| KontrollerError::DbField),
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Get the approved comments of a blog post, oldest first
+    pub fn get_approved_comments(&self, post_id: i64) -> Result<Vec<Comment>, KontrollerError> {
+        self.query_comments(
+            sql::READ_BY_POST,
+            named_params! { ":post_id": post_id, ":status": CommentStatus::Approved },
+        )
+    }
+
+    /// Get all comments with a status, oldest first
+    pub fn get_comments_by_status(
+        &self,
+        status: CommentStatus,
+    ) -> Result<Vec<Comment>, KontrollerError> {
+        self.query_comments(sql::READ_BY_STATUS, named_params! { ":status": status })
+    }
+
+    /// Get the comments selected by a query
+    fn query_comments(
+        &self,
+        query: &str,
+        params: &[(&str, &dyn ToSql)],
+    ) -> Result<Vec<Comment>, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let mut stmt = conn.prepare(query).map_err(|_| KontrollerError::DbSQL)?;
+                let comments = stmt
+                    .query_map(params, comment_from_row)
+                    .map_err(|_| KontrollerError::DbField)?;
+
+                comments
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| KontrollerError::DbField)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Set the moderation status of a comment, returns `false` if the
+    /// comment does not exist
+    pub fn set_comment_status(
+        &self,
+        id: i64,
+        status: CommentStatus,
+    ) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let updated = conn
+                    .execute(sql::UPDATE_STATUS, params![status, id])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(updated > 0)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Delete a comment and all replies to it, returns the number of
+    /// deleted comments
+    pub fn delete_comment(&self, id: i64) -> Result<usize, KontrollerError> {
+        match &self.conn {
+            Some(conn) => conn
+                .execute(sql::DELETE_THREAD, named_params! { ":id": id })
+                .map_err(|_| KontrollerError::DbSQL),
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Delete the comments and the comment settings of a blog post,
+    /// returns the number of deleted comments
+    pub fn delete_post_comments(&self, post_id: i64) -> Result<usize, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let deleted = conn
+                    .execute(sql::DELETE_BY_POST, named_params! { ":post_id": post_id })
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                conn.execute(sql::DELETE_SETTINGS, named_params! { ":post_id": post_id })
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(deleted)
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Check if a blog post can be commented on
+    pub fn comments_enabled(&self, post_id: i64) -> Result<bool, KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                let enabled: Option<bool> = conn
+                    .query_row(
+                        sql::READ_ENABLED,
+                        named_params! { ":post_id": post_id },
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(|_| KontrollerError::DbSQL)?;
+
+                Ok(enabled.unwrap_or(true))
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+
+    /// Enable or disable the comments of a blog post
+    pub fn set_comments_enabled(&self, post_id: i64, enabled: bool) -> Result<(), KontrollerError> {
+        match &self.conn {
+            Some(conn) => {
+                conn.execute(sql::UPDATE_ENABLED, params![post_id, enabled])
+                    .map_err(|_| KontrollerError::DbSQL)?;
+                Ok(())
+            }
+            None => Err(KontrollerError::DbConnection),
+        }
+    }
+}
+
+/// Purged blog posts lose their comments, see [`crate::blog::trash`]
+impl PurgeHook for Mutex<Database> {
+    fn purge(&self, post_id: i64) -> Result<(), KontrollerError> {
+        self.lock().unwrap().delete_post_comments(post_id)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::CommentThread;
+    use super::*;
+    use crate::test_utils::TempDatabase;
+    use chrono::Utc;
+
+    fn comment(parent_id: Option<i64>, content: &str) -> Comment {
+        Comment {
+            id: 0,
+            post_id: 1,
+            parent_id,
+            username: None,
+            name: "Guest".to_string(),
+            email: Some("guest@example.com".to_string()),
+            content: content.to_string(),
+            status: CommentStatus::Pending,
+            date: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn connect_db() {
+        let db_file = TempDatabase::new("COMMENTS_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+
+        // Connect to database
+        db.connect().unwrap();
+
+        assert!(db.conn.is_some());
+    }
+
+    #[test]
+    fn test_comment_threads() {
+        let db_file = TempDatabase::new("COMMENTS_THREAD_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+
+        let first = db.create_comment(&comment(None, "First")).unwrap();
+        let reply = db
+            .create_comment(&comment(Some(first.id), "Reply"))
+            .unwrap();
+        let spam = db.create_comment(&comment(None, "Spam")).unwrap();
+
+        // Pending comments are in the moderation queue only
+        assert!(db.get_approved_comments(1).unwrap().is_empty());
+        assert_eq!(
+            db.get_comments_by_status(CommentStatus::Pending)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        assert!(db
+            .set_comment_status(first.id, CommentStatus::Approved)
+            .unwrap());
+        assert!(db
+            .set_comment_status(reply.id, CommentStatus::Approved)
+            .unwrap());
+        assert!(db.set_comment_status(spam.id, CommentStatus::Spam).unwrap());
+
+        let threads = CommentThread::build(db.get_approved_comments(1).unwrap());
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].content, "First");
+        assert_eq!(threads[0].replies[0].content, "Reply");
+
+        // Deleting a comment deletes its replies
+        assert_eq!(db.delete_comment(first.id).unwrap(), 2);
+        assert!(db.get_comment(reply.id).unwrap().is_none());
+
+        assert!(db.comments_enabled(1).unwrap());
+        db.set_comments_enabled(1, false).unwrap();
+        assert!(!db.comments_enabled(1).unwrap());
+
+        // Purging the post removes its comments and settings
+        let db = Mutex::new(db);
+        db.purge(1).unwrap();
+        let db = db.into_inner().unwrap();
+        assert!(db.get_comment(spam.id).unwrap().is_none());
+        assert!(db.comments_enabled(1).unwrap());
+    }
+}
@@ -0,0 +1,80 @@
+//! # Get blog post comments kontroller
+//!
+//! This __kontroller__ lists the approved comments of a public blog
+//! post as threads of replies, the post is taken from the `id` url
+//! parameter.
+
+use super::database::Database;
+use super::CommentThread;
+use crate::blog::database::Database as BlogDatabase;
+use chrono::Utc;
+use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// Get blog post comments kontroller
+pub struct GetCommentsKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Blog database
+    pub blog_database: Arc<Mutex<BlogDatabase>>,
+}
+
+/// Comments of a blog post
+#[derive(Serialize)]
+pub struct CommentsResponse {
+    /// Whether new comments can be posted
+    pub enabled: bool,
+    /// The comment threads, oldest first
+    pub comments: Vec<CommentThread>,
+}
+
+impl Kontrol for GetCommentsKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get comments
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let post_id: i64 = match kong
+            .url_parameters
+            .as_ref()
+            .and_then(|url_params| url_params.find("id"))
+            .and_then(|id| id.parse().ok())
+        {
+            Some(id) => id,
+            None => return ErrorResponse::bad_request(),
+        };
+
+        // Comments of unpublished posts are not public
+        match self.blog_database.lock().unwrap().get_blog_by_id(post_id) {
+            Ok(Some(post)) if post.is_public(Utc::now()) => (),
+            Ok(_) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        let db = self.database.lock().unwrap();
+
+        match (
+            db.comments_enabled(post_id),
+            db.get_approved_comments(post_id),
+        ) {
+            (Ok(enabled), Ok(comments)) => server::Response::json(&CommentsResponse {
+                enabled,
+                comments: CommentThread::build(comments),
+            })
+            .with_status_code(200),
+            _ => ErrorResponse::internal(),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! # ⌨️ User Input
+
+use kong::{json, json_from_str, validate::Validate, JsonError, JsonValue};
+use serde::{Deserialize, Serialize};
+
+/// ## ⌨️ Comment input
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CommentInput {
+    /// Name of a guest author, ignored for accounts
+    pub name: Option<String>,
+    /// Email of a guest author, ignored for accounts
+    pub email: Option<String>,
+    /// The comment
+    pub content: String,
+    /// The Identifier of the comment this comment replies to
+    pub parent_id: Option<i64>,
+}
+
+impl CommentInput {
+    /// Longest comment, in characters
+    pub const MAX_LENGTH: usize = 10_000;
+
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "name": self.name,
+            "email": self.email,
+            "content": self.content,
+            "parent_id": self.parent_id
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<CommentInput, JsonError> {
+        let a: CommentInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+
+    /// Check that the comment is not empty or too long, that the guest
+    /// name, if any, is not empty and that the guest email, if any, is
+    /// an email address
+    pub fn is_valid(&self) -> bool {
+        let content = self.content.trim();
+        let name_valid = !matches!(&self.name, Some(name) if name.trim().is_empty());
+        let email_valid = !matches!(&self.email, Some(email) if !Validate::email(email));
+
+        !content.is_empty()
+            && content.chars().count() <= Self::MAX_LENGTH
+            && name_valid
+            && email_valid
+    }
+}
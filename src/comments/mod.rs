@@ -0,0 +1,149 @@
+//! # 💬 Comments kontroller
+//!
+//! The `comments` __kontroller__ allows readers to comment on public
+//! blog posts (from the `blog` kontroller) and to reply to other
+//! comments. Comments are posted by accounts, or by guests with a name
+//! and an optional email address.
+//!
+//! > To use the comments kontroller, enable it with the `comments`
+//! > Cargo feature.
+//!
+//! The `comments` kontroller depends on the `blog` kontroller,
+//! enabling the `comments` kontroller automatically enables the
+//! `blog` kontroller.
+//!
+//! ## 🛡️ Moderation
+//! New comments have the [`CommentStatus::Pending`] status and are only
+//! public once an admin approves them, see [`moderate`]. Admins can
+//! also mark comments as spam, delete them, and enable or disable the
+//! comments of a blog post.
+//!
+//! The comments database is a [`crate::blog::trash::PurgeHook`], add it
+//! to the `purge_hooks` of the blog trash kontrollers to delete the
+//! comments of purged posts.
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub use inputs::CommentInput;
+
+pub mod create;
+pub mod database;
+pub mod get;
+pub mod inputs;
+pub mod moderate;
+
+/// ## 💬 Blog post comment
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct Comment {
+    /// The Identifier of the comment, the Rust Type is `i64`
+    pub id: i64,
+    /// The Identifier of the commented blog post
+    pub post_id: i64,
+    /// The Identifier of the comment this comment replies to
+    pub parent_id: Option<i64>,
+    /// The username of the author, `None` for guest comments
+    pub username: Option<String>,
+    /// The name of the author, the username for accounts
+    pub name: String,
+    /// The email of a guest author
+    pub email: Option<String>,
+    /// The comment
+    pub content: String,
+    /// The moderation status of the comment
+    pub status: CommentStatus,
+    /// The date the comment was posted
+    pub date: DateTime<Utc>,
+}
+
+/// ## 💬 Public comment
+///
+/// An approved comment without the author's email address, and its
+/// approved replies.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct CommentThread {
+    /// The Identifier of the comment, the Rust Type is `i64`
+    pub id: i64,
+    /// The username of the author, `None` for guest comments
+    pub username: Option<String>,
+    /// The name of the author
+    pub name: String,
+    /// The comment
+    pub content: String,
+    /// The date the comment was posted
+    pub date: DateTime<Utc>,
+    /// Replies to the comment, oldest first
+    pub replies: Vec<CommentThread>,
+}
+
+impl CommentThread {
+    /// Arrange comments into threads. Replies to comments that are not
+    /// in `comments` are left out.
+    pub fn build(comments: Vec<Comment>) -> Vec<CommentThread> {
+        let mut children: HashMap<Option<i64>, Vec<Comment>> = HashMap::new();
+        for comment in comments {
+            children.entry(comment.parent_id).or_default().push(comment);
+        }
+
+        CommentThread::replies(&mut children, None)
+    }
+
+    /// Take the replies to a comment, and their replies
+    fn replies(
+        children: &mut HashMap<Option<i64>, Vec<Comment>>,
+        parent_id: Option<i64>,
+    ) -> Vec<CommentThread> {
+        children
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|comment| CommentThread {
+                id: comment.id,
+                username: comment.username,
+                name: comment.name,
+                content: comment.content,
+                date: comment.date,
+                replies: CommentThread::replies(children, Some(comment.id)),
+            })
+            .collect()
+    }
+}
+
+/// ## 🛡️ Comment moderation status
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentStatus {
+    /// Waiting for moderation, only visible to admins
+    #[default]
+    Pending,
+    /// Publicly readable
+    Approved,
+    /// Marked as spam by an admin
+    Spam,
+}
+
+impl CommentStatus {
+    /// All statuses
+    pub const ALL: [CommentStatus; 3] = [
+        CommentStatus::Pending,
+        CommentStatus::Approved,
+        CommentStatus::Spam,
+    ];
+
+    /// Name of the status, as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentStatus::Pending => "pending",
+            CommentStatus::Approved => "approved",
+            CommentStatus::Spam => "spam",
+        }
+    }
+
+    /// Get a status from its name
+    pub fn from_name(name: &str) -> Option<CommentStatus> {
+        CommentStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == name)
+    }
+}
@@ -0,0 +1,237 @@
+//! # 🛡️ Comment moderation kontrollers
+//!
+//! These __kontrollers__ allow an admin account to list the
+//! moderation queue, approve comments or mark them as spam, and delete
+//! comments. The comment is taken from the `id` url parameter.
+//!
+//! The comments of a blog post can be disabled, and enabled again,
+//! with the [`SetCommentsEnabledKontroller`]. Existing comments stay
+//! public when the comments of a post are disabled.
+
+use super::database::Database;
+use super::CommentStatus;
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::login::admin_username;
+use kong::{json, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// Get the comment, or blog post, id from the `id` url parameter
+fn url_id(kong: &Kong) -> Option<i64> {
+    kong.url_parameters
+        .as_ref()
+        .and_then(|url_params| url_params.find("id"))
+        .and_then(|id| id.parse().ok())
+}
+
+/// ## 📋 Moderation queue kontroller
+///
+/// Lists the pending comments, oldest first. The `status` query
+/// parameter lists the comments with another status instead.
+pub struct GetModerationQueueKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for GetModerationQueueKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        Some(json!({ "status": request.get_param("status") }))
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.as_ref().map(|input| &input["status"]) {
+            Some(JsonValue::String(status)) if CommentStatus::from_name(status).is_none() => {
+                Err(())
+            }
+            _ => Ok(input),
+        }
+    }
+
+    /// Get comments to moderate
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let status = kong
+            .input
+            .as_ref()
+            .and_then(|input| input["status"].as_str())
+            .and_then(CommentStatus::from_name)
+            .unwrap_or_default();
+
+        match self.database.lock().unwrap().get_comments_by_status(status) {
+            Ok(comments) => server::Response::json(&comments).with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## ✅ Comment moderation kontroller
+///
+/// Sets the status of the comment, eg to approve it or mark it as
+/// spam.
+pub struct ModerateCommentKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// The status given to the comment
+    pub status: CommentStatus,
+}
+
+impl Kontrol for ModerateCommentKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Moderate comment
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let id = if let Some(id) = url_id(kong) {
+            id
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        let db = self.database.lock().unwrap();
+
+        match db.set_comment_status(id, self.status) {
+            Ok(true) => match db.get_comment(id) {
+                Ok(Some(comment)) => server::Response::json(&comment).with_status_code(200),
+                Ok(None) => ErrorResponse::not_found(),
+                Err(_) => ErrorResponse::internal(),
+            },
+            Ok(false) => ErrorResponse::not_found(),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## 🗑️ Comment deletion kontroller
+///
+/// Deletes the comment and all replies to it.
+pub struct DeleteCommentKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+}
+
+impl Kontrol for DeleteCommentKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Delete comment
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let id = if let Some(id) = url_id(kong) {
+            id
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        match self.database.lock().unwrap().delete_comment(id) {
+            Ok(0) => ErrorResponse::not_found(),
+            Ok(_) => server::Response::text("Comment has been deleted.").with_status_code(200),
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
+
+/// ## 🔒 Enable or disable comments kontroller
+///
+/// Enables or disables new comments on the blog post in the `id` url
+/// parameter.
+pub struct SetCommentsEnabledKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// SQLite database handle
+    pub database: Arc<Mutex<Database>>,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Enable the comments instead of disabling them
+    pub enabled: bool,
+}
+
+impl Kontrol for SetCommentsEnabledKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Enable or disable comments
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        if let Err(response) = admin_username(kong, self.accounts_database.clone()) {
+            return response;
+        }
+
+        let post_id = if let Some(id) = url_id(kong) {
+            id
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        match self
+            .database
+            .lock()
+            .unwrap()
+            .set_comments_enabled(post_id, self.enabled)
+        {
+            Ok(()) => {
+                server::Response::json(&json!({ "enabled": self.enabled })).with_status_code(200)
+            }
+            Err(_) => ErrorResponse::internal(),
+        }
+    }
+}
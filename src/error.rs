@@ -25,6 +25,20 @@ pub enum KontrollerError {
     ConfigError,
     /// Kpassport error
     Kpassport,
+    /// Invalid, expired or used up invite code
+    Invite,
+    /// Invalid, expired or used password reset code
+    PasswordReset,
+    /// The last admin account can not be removed
+    LastAdmin,
+    /// The type of an uploaded file is not allowed
+    MediaType,
+    /// An uploaded file is too large
+    MediaSize,
+    /// A media file could not be stored or removed
+    MediaStorage,
+    /// The resource was changed since the version the request is based on
+    VersionConflict,
 }
 
 impl std::error::Error for KontrollerError {}
@@ -44,6 +58,13 @@ impl fmt::Display for KontrollerError {
             Self::PasswordVerifyHash => write!(f, "Could not verify password hash"),
             Self::ConfigError => write!(f, "Could not read config file"),
             Self::Kpassport => write!(f, "Kpassport Error"),
+            Self::Invite => write!(f, "Invalid, expired or used up invite code"),
+            Self::PasswordReset => write!(f, "Invalid, expired or used password reset code"),
+            Self::LastAdmin => write!(f, "The last admin account can not be removed"),
+            Self::MediaType => write!(f, "The type of the uploaded file is not allowed"),
+            Self::MediaSize => write!(f, "The uploaded file is too large"),
+            Self::MediaStorage => write!(f, "Could not store or remove the media file"),
+            Self::VersionConflict => write!(f, "The resource was changed by another request"),
         }
     }
 }
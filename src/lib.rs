@@ -2,10 +2,22 @@
 pub mod accounts;
 #[cfg(feature = "blog")]
 pub mod blog;
+#[cfg(feature = "comments")]
+pub mod comments;
 #[cfg(feature = "contact")]
 pub mod contact;
 pub mod error;
 #[cfg(feature = "login")]
 pub mod login;
+#[cfg(feature = "media")]
+pub mod media;
+#[cfg(feature = "accounts")]
+mod migration;
 #[cfg(feature = "newsletter")]
 pub mod newsletter;
+#[cfg(feature = "sitemap")]
+pub mod sitemap;
+#[cfg(all(test, feature = "accounts"))]
+mod test_utils;
+#[cfg(any(feature = "blog", feature = "sitemap"))]
+mod xml;
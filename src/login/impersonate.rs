@@ -0,0 +1,319 @@
+//! # 🎭 Impersonation kontrollers
+//!
+//! Allows an admin account to see the site as another account. The
+//! admin is issued a time-limited `kpassport` for the impersonated
+//! account and every impersonation is written to the audit log.
+//!
+//! > The impersonation `kpassport` is issued for the subject
+//! > `<username>#<token>`, the token is random and stored with the
+//! > impersonation. Only that `kpassport` is checked as the admin by
+//! > [`super::is_admin`], the impersonated account's own sessions are
+//! > not, and it is rejected once the impersonation ended or expired.
+//! > Admin accounts can not be impersonated.
+
+use super::inputs::ImpersonationInput;
+use super::{impersonation, is_admin, kpassport_error, real_username};
+use crate::accounts::{database::Database, AuditEntry, Impersonation, IMPERSONATION_SEPARATOR};
+use crate::error::KontrollerError;
+use chrono::{Duration, Utc};
+use kong::{inputs::UserInput, krypto, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// How long an impersonation lasts if no duration is provided, in minutes
+pub const DEFAULT_IMPERSONATION_MINUTES: i64 = 30;
+/// The longest an impersonation can last, in minutes
+pub const MAX_IMPERSONATION_MINUTES: i64 = 240;
+
+/// Length of the random impersonation tokens
+const TOKEN_LENGTH: usize = 32;
+
+/// Generate a random impersonation token
+fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Issue a kpassport cookie that expires after `max_age` seconds
+fn issue_cookie(
+    username: &str,
+    kong: &Kong,
+    max_age: Option<i64>,
+    body: &ImpersonationResponse,
+) -> server::Response {
+    let cookie = krypto::authentication::Auth::issue_kpassport_cookie(
+        username,
+        &kong.config.hostname,
+        &kong.config.secret_key,
+        &kong.config.auth_cookie_name,
+    );
+
+    match cookie {
+        Ok((name, value)) => {
+            let value = match max_age {
+                Some(max_age) => format!("{value}; Max-Age={max_age}").into(),
+                None => value,
+            };
+
+            let mut response = server::Response::json(body);
+            response.headers.push((name, value));
+            response.status_code = 200;
+            response
+        }
+        Err(_) => ErrorResponse::internal(),
+    }
+}
+
+/// ## 🎭 Impersonation kontroller
+/// Issues an admin a time-limited kpassport for another account
+pub struct ImpersonateKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// Accounts database
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for ImpersonateKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Get input from request
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        let input: Result<ImpersonationInput, server::input::json::JsonError> =
+            server::input::json_input(request);
+
+        if let Ok(input) = input {
+            Some(input.as_json())
+        } else {
+            None
+        }
+    }
+
+    /// Validate request input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        if let Some(input) = input {
+            match ImpersonationInput::from_json_str(input.to_string()) {
+                Ok(input) => {
+                    if input.is_valid().is_ok() {
+                        Ok(Some(input.as_json()))
+                    } else {
+                        Err(())
+                    }
+                }
+                Err(_) => Err(()),
+            }
+        } else {
+            Err(())
+        }
+    }
+
+    /// Start impersonating an account
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let k = if let Some(k) = &kong.kpassport {
+            k
+        } else {
+            return ErrorResponse::unauthorized();
+        };
+
+        match is_admin(k, self.database.clone()) {
+            Ok(true) => (),
+            Ok(false) => return ErrorResponse::unauthorized(),
+            Err(err) => return kpassport_error(err),
+        }
+
+        let admin = match real_username(k, self.database.clone()) {
+            Ok(admin) => admin,
+            Err(err) => return kpassport_error(err),
+        };
+
+        let input = if let Some(input) = &kong.input {
+            match ImpersonationInput::from_json_str(input.to_string()) {
+                Ok(input) => input,
+                Err(_) => return ErrorResponse::bad_request(),
+            }
+        } else {
+            return ErrorResponse::bad_request();
+        };
+
+        if input.username == admin {
+            return ErrorResponse::bad_request();
+        }
+
+        // Find the account to impersonate
+        let target = self
+            .database
+            .lock()
+            .unwrap()
+            .private_get_account_by_username(&input.username);
+
+        match target {
+            Ok(Some(target)) => {
+                // Admin accounts can not be impersonated
+                if target.account_type.as_deref() == Some("admin") {
+                    return ErrorResponse::unauthorized();
+                }
+            }
+            Ok(None) => return ErrorResponse::not_found(),
+            Err(_) => return ErrorResponse::internal(),
+        }
+
+        let minutes = input
+            .minutes
+            .unwrap_or(DEFAULT_IMPERSONATION_MINUTES)
+            .clamp(1, MAX_IMPERSONATION_MINUTES);
+        let started = Utc::now();
+        let impersonation = Impersonation {
+            token: generate_token(),
+            admin: admin.clone(),
+            target: input.username.clone(),
+            started,
+            expires: started + Duration::minutes(minutes),
+            ended: None,
+        };
+
+        let res = self
+            .database
+            .lock()
+            .unwrap()
+            .create_impersonation(&impersonation);
+
+        if let Err(err) = res {
+            return match err {
+                KontrollerError::DbField => ErrorResponse::bad_request(),
+                _ => ErrorResponse::internal(),
+            };
+        }
+
+        let entry = AuditEntry {
+            actor: admin,
+            action: "impersonation_start".to_string(),
+            subject: Some(input.username.clone()),
+            date: started,
+        };
+
+        if self
+            .database
+            .lock()
+            .unwrap()
+            .create_audit_entry(&entry)
+            .is_err()
+        {
+            return ErrorResponse::internal();
+        }
+
+        issue_cookie(
+            &format!(
+                "{}{IMPERSONATION_SEPARATOR}{}",
+                impersonation.target, impersonation.token
+            ),
+            kong,
+            Some(minutes * 60),
+            &ImpersonationResponse {
+                message: "Impersonation started".to_string(),
+                impersonation,
+            },
+        )
+    }
+}
+
+/// ## 🎭 End impersonation kontroller
+/// Ends a running impersonation and gives the admin back their own
+/// kpassport
+pub struct EndImpersonationKontroller {
+    /// Address to kontroller (url path)
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// Accounts database
+    pub database: Arc<Mutex<Database>>,
+}
+
+impl Kontrol for EndImpersonationKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// End impersonation
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let k = if let Some(k) = &kong.kpassport {
+            k
+        } else {
+            return ErrorResponse::unauthorized();
+        };
+
+        let mut impersonation = match impersonation(k, self.database.clone()) {
+            Ok(Some(impersonation)) => impersonation,
+            // The kpassport was not issued for an impersonation
+            Ok(None) => return ErrorResponse::bad_request(),
+            Err(err) => return kpassport_error(err),
+        };
+        let now = Utc::now();
+
+        if self
+            .database
+            .lock()
+            .unwrap()
+            .end_impersonation(&impersonation.token, now)
+            .is_err()
+        {
+            return ErrorResponse::internal();
+        }
+
+        let entry = AuditEntry {
+            actor: impersonation.admin.clone(),
+            action: "impersonation_end".to_string(),
+            subject: Some(impersonation.target.clone()),
+            date: now,
+        };
+
+        if self
+            .database
+            .lock()
+            .unwrap()
+            .create_audit_entry(&entry)
+            .is_err()
+        {
+            return ErrorResponse::internal();
+        }
+
+        impersonation.ended = Some(now);
+
+        issue_cookie(
+            &impersonation.admin.clone(),
+            kong,
+            None,
+            &ImpersonationResponse {
+                message: "Impersonation ended".to_string(),
+                impersonation,
+            },
+        )
+    }
+}
+
+/// Impersonation response message
+#[derive(Serialize)]
+pub struct ImpersonationResponse {
+    /// Message sent as JSON to the admin
+    message: String,
+    /// The impersonation that was started or ended
+    impersonation: Impersonation,
+}
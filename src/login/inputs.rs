@@ -49,3 +49,38 @@ impl UserInput for AccountLoginInput {
         Ok(())
     }
 }
+
+/// Account impersonation input
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ImpersonationInput {
+    /// Username of the account to impersonate
+    pub username: String,
+    /// How long the impersonation lasts, in minutes
+    pub minutes: Option<i64>,
+}
+
+impl ImpersonationInput {
+    /// new generic resource
+    pub fn as_json(&self) -> JsonValue {
+        json!({
+            "username": self.username,
+            "minutes": self.minutes
+        })
+    }
+
+    /// from json
+    pub fn from_json_str(json_str: String) -> Result<ImpersonationInput, JsonError> {
+        let a: ImpersonationInput = json_from_str(&json_str)?;
+        Ok(a)
+    }
+}
+
+impl UserInput for ImpersonationInput {
+    fn is_valid(&self) -> Result<(), ValidationError> {
+        if !Validate::username(&self.username) {
+            return Err(ValidationError::Username);
+        }
+
+        Ok(())
+    }
+}
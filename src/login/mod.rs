@@ -11,18 +11,101 @@
 //! The `login` kontroller depends on the `account` kontroller,
 //! enabling the `login` kontroller automatically enables the
 //! `accounts` kontroller.
+//!
+//! Password hashes created with outdated hashing parameters are
+//! upgraded when the account logs in.
+//!
+//! ## 🎭 Impersonation
+//! Admins can impersonate another account with the
+//! [`impersonate::ImpersonateKontroller`], the admin is issued a
+//! time-limited `kpassport` for the impersonated account. The
+//! `kpassport` is bound to a random token stored with the
+//! impersonation, so only that `kpassport` acts as the admin: while
+//! the impersonation is running [`is_admin`] checks the real admin
+//! identity behind it, see [`real_username`], and once it ended or
+//! expired the `kpassport` is rejected. Kontrollers acting on the
+//! impersonated account use [`session_username`].
+pub mod impersonate;
 pub mod inputs;
 
-use crate::accounts::{database::Database, Account};
+use crate::accounts::{
+    database::Database, policy::needs_rehash, Account, Impersonation, IMPERSONATION_SEPARATOR,
+};
 use crate::error::KontrollerError;
+use chrono::{DateTime, Utc};
 use inputs::AccountLoginInput;
 use kong::krypto::kpassport::Kpassport;
 use kong::{inputs::UserInput, krypto, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
 use serde::Serialize;
 use std::sync::{Arc, Mutex};
 
+/// Get the running impersonation a kpassport subject was issued for,
+/// `None` if the subject is an account's own username. Subjects of
+/// impersonations that ended or expired are rejected.
+fn subject_impersonation(
+    subject: &str,
+    db: &Database,
+    now: DateTime<Utc>,
+) -> Result<Option<Impersonation>, KontrollerError> {
+    // Tokens never contain the separator, usernames of accounts created
+    // before it was reserved can
+    let (target, token) = match subject.rsplit_once(IMPERSONATION_SEPARATOR) {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+
+    if db.private_get_account_by_username(subject)?.is_some() {
+        return Ok(None);
+    }
+
+    match db.get_active_impersonation(token, now)? {
+        Some(impersonation) if impersonation.target == target => Ok(Some(impersonation)),
+        _ => Err(KontrollerError::Kpassport),
+    }
+}
+
+/// Get the running impersonation a kpassport was issued for, if any
+pub fn impersonation(
+    k: &Kpassport,
+    db: Arc<Mutex<Database>>,
+) -> Result<Option<Impersonation>, KontrollerError> {
+    subject_impersonation(&k.content.username, &db.lock().unwrap(), Utc::now())
+}
+
+/// Get the username of the account a kpassport acts as. For an
+/// impersonation kpassport, this is the impersonated account's
+/// username.
+pub fn session_username(
+    k: &Kpassport,
+    db: Arc<Mutex<Database>>,
+) -> Result<String, KontrollerError> {
+    match impersonation(k, db)? {
+        Some(impersonation) => Ok(impersonation.target),
+        None => Ok(k.content.username.clone()),
+    }
+}
+
+/// Get the username of the account really using a kpassport. For an
+/// impersonation kpassport, this is the admin's username.
+pub fn real_username(k: &Kpassport, db: Arc<Mutex<Database>>) -> Result<String, KontrollerError> {
+    match impersonation(k, db)? {
+        Some(impersonation) => Ok(impersonation.admin),
+        None => Ok(k.content.username.clone()),
+    }
+}
+
+/// Response for a kpassport that could not be checked, kpassports of
+/// ended or expired impersonations are unauthorized
+pub fn kpassport_error(err: KontrollerError) -> server::Response {
+    match err {
+        KontrollerError::Kpassport => ErrorResponse::unauthorized(),
+        _ => ErrorResponse::internal(),
+    }
+}
+
+/// Check if the real account behind a kpassport is an admin account
 pub fn is_admin(k: &Kpassport, db: Arc<Mutex<Database>>) -> Result<bool, KontrollerError> {
-    let username = &k.content.username;
+    let username = real_username(k, db.clone())?;
 
     // Find user account in database
     let account = db
@@ -48,6 +131,20 @@ pub fn is_admin(k: &Kpassport, db: Arc<Mutex<Database>>) -> Result<bool, Kontrol
     }
 }
 
+/// Check that the kpassport of a request belongs to an admin, returns
+/// the real admin's username or the error response to send
+pub fn admin_username(kong: &Kong, db: Arc<Mutex<Database>>) -> Result<String, server::Response> {
+    if let Some(k) = &kong.kpassport {
+        match is_admin(k, db.clone()) {
+            Ok(true) => real_username(k, db).map_err(kpassport_error),
+            Ok(false) => Err(ErrorResponse::unauthorized()),
+            Err(err) => Err(kpassport_error(err)),
+        }
+    } else {
+        Err(ErrorResponse::unauthorized())
+    }
+}
+
 /// Login accounts API endpoint handler
 pub struct LoginKontroller {
     pub address: String,
@@ -89,37 +186,7 @@ impl LoginKontroller {
     /// check if user is admin
     pub fn is_admin(&self, kong: &Kong) -> bool {
         if let Some(kpassport) = &kong.kpassport {
-            // get admin from database
-            let admin_email = if let Some(admin_email) = &kong.config.admin_email {
-                admin_email.clone()
-            } else {
-                // admin email not set
-                return false;
-            };
-
-            if let Ok(admin_account) = self
-                .database
-                .lock()
-                .unwrap()
-                .private_get_account_by_email(&admin_email)
-            {
-                if let Some(admin_account) = admin_account {
-                    // check if admin account username matches the username is the kpassport
-                    if admin_account.username == kpassport.content.username {
-                        // user is admin
-                        true
-                    } else {
-                        // user is not admin
-                        false
-                    }
-                } else {
-                    // Admin account not found, for some reason. Cannot check if user is admin
-                    false
-                }
-            } else {
-                // Could not get admin account from database
-                false
-            }
+            is_admin(kpassport, self.database.clone()).unwrap_or(false)
         } else {
             // No kpassport found (user not logged in), cannot check if user is admin
             false
@@ -193,6 +260,20 @@ impl Kontrol for LoginKontroller {
                             match krypto::password::verify(&account.password, &input.password) {
                                 Ok(password_verification) => {
                                     if password_verification {
+                                        // Upgrade the hash if the hashing parameters changed
+                                        if needs_rehash(&account.password) {
+                                            if let Ok(hash) =
+                                                krypto::password::hash(&input.password)
+                                            {
+                                                // the account can still login with the old hash
+                                                let _ = self
+                                                    .database
+                                                    .lock()
+                                                    .unwrap()
+                                                    .update_password(&account.username, &hash);
+                                            }
+                                        }
+
                                         // Password correct, create cookie based sessions
                                         LoginKontroller::cookie_auth(
                                             account,
@@ -233,3 +314,76 @@ pub struct LoginResponse {
     message: String,
     account_type: Option<String>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::accounts::inputs::AccountCreationInput;
+    use crate::test_utils::TempDatabase;
+    use chrono::Duration;
+
+    #[test]
+    fn test_impersonation_subject() {
+        let db_file = TempDatabase::new("LOGIN_IMPERSONATION_TEST_DATABASE.sqlite");
+        let mut db = Database::new(&db_file.path);
+        db.connect().unwrap();
+
+        let started = Utc::now();
+        let impersonation = Impersonation {
+            token: "t0ken".to_string(),
+            admin: "admin".to_string(),
+            target: "alice".to_string(),
+            started,
+            expires: started + Duration::minutes(30),
+            ended: None,
+        };
+        db.create_impersonation(&impersonation).unwrap();
+        let subject = format!("alice{IMPERSONATION_SEPARATOR}t0ken");
+
+        // The impersonated account's own session is not the admin's
+        assert_eq!(subject_impersonation("alice", &db, started).unwrap(), None);
+        assert_eq!(
+            subject_impersonation(&subject, &db, started).unwrap(),
+            Some(impersonation.clone())
+        );
+
+        // The token is bound to the impersonated account
+        assert!(subject_impersonation("bob#t0ken", &db, started).is_err());
+        assert!(subject_impersonation("alice#guessed", &db, started).is_err());
+
+        // Expired impersonations are rejected, even if the cookie is kept
+        assert!(matches!(
+            subject_impersonation(&subject, &db, started + Duration::minutes(31)),
+            Err(KontrollerError::Kpassport)
+        ));
+
+        db.end_impersonation("t0ken", started).unwrap();
+        assert!(subject_impersonation(&subject, &db, started).is_err());
+
+        // Accounts created with the separator in their username still
+        // log in, and can be impersonated
+        let account: Account = AccountCreationInput {
+            username: "carol#1".to_string(),
+            password: "correct horse battery".to_string(),
+            email: None,
+            invite: None,
+        }
+        .into();
+        db.create_account(&account).unwrap();
+        assert_eq!(
+            subject_impersonation("carol#1", &db, started).unwrap(),
+            None
+        );
+
+        let impersonation = Impersonation {
+            token: "t0ken2".to_string(),
+            target: "carol#1".to_string(),
+            ..impersonation
+        };
+        db.create_impersonation(&impersonation).unwrap();
+        assert_eq!(
+            subject_impersonation("carol#1#t0ken2", &db, started).unwrap(),
+            Some(impersonation)
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! # 🗃️ Media storage
+//!
+//! Stores the files uploaded to the __kontrollers__, such as the cover
+//! photos of blog posts.
+//!
+//! > To use the media storage, enable it with the `media` Cargo
+//! > feature. The `blog` kontroller enables it automatically.
+//!
+//! Uploads are buffered in the input of the request as an [`Upload`],
+//! and only processed and stored once the kontroller checked the
+//! account sending them. They are checked against a [`MediaPolicy`]
+//! before they are stored: their type is detected from their data, not from what the
+//! client claims, and must be in the policy's allowlist, and their size
+//! must be within the policy's limit. Stored files get a generated name,
+//! the client's file name is only kept, sanitized, as a hint.
+//!
+//! Files are stored with a [`storage::MediaStorage`], such as the
+//! [`storage::LocalStorage`] which keeps them in a directory of the
+//! local filesystem.
+//!
+//! Images can also be stored without their metadata, along with
+//! smaller variants for responsive pages, see [`variants`].
+//!
+//! ## 🔗 References
+//! The kontrollers using stored files list them as [`MediaReferences`],
+//! such as the covers of the blog posts. Stored files that no
+//! reference uses are orphans: admins can list them, and purge the
+//! orphans older than a grace period, with the [`orphans`] kontroller.
+//! The grace period keeps the files just uploaded for a post that is
+//! still being saved.
+
+#[cfg(feature = "login")]
+pub mod orphans;
+pub mod storage;
+pub mod variants;
+
+use crate::error::KontrollerError;
+use chrono::{DateTime, Utc};
+use kong::server;
+use rand::{distributions::Alphanumeric, Rng};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use storage::{MediaStorage, StoredFile};
+
+/// ## 🖼️ Type of media file
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    /// PNG image
+    Png,
+    /// JPEG image
+    Jpeg,
+    /// GIF image
+    Gif,
+    /// WebP image
+    Webp,
+}
+
+impl MediaType {
+    /// All media types
+    pub const ALL: [MediaType; 4] = [
+        MediaType::Png,
+        MediaType::Jpeg,
+        MediaType::Gif,
+        MediaType::Webp,
+    ];
+
+    /// Name of the type, as stored in the databases
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Png => "png",
+            MediaType::Jpeg => "jpeg",
+            MediaType::Gif => "gif",
+            MediaType::Webp => "webp",
+        }
+    }
+
+    /// Get a type from its name
+    pub fn from_name(name: &str) -> Option<MediaType> {
+        MediaType::ALL
+            .into_iter()
+            .find(|media_type| media_type.as_str() == name)
+    }
+
+    /// Detect the type of a file from its first bytes
+    pub fn sniff(data: &[u8]) -> Option<MediaType> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(MediaType::Png)
+        } else if data.starts_with(b"\xff\xd8\xff") {
+            Some(MediaType::Jpeg)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(MediaType::Gif)
+        } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+            Some(MediaType::Webp)
+        } else {
+            None
+        }
+    }
+
+    /// File extension of the type
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MediaType::Png => "png",
+            MediaType::Jpeg => "jpg",
+            MediaType::Gif => "gif",
+            MediaType::Webp => "webp",
+        }
+    }
+
+    /// Content type of the type
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            MediaType::Png => "image/png",
+            MediaType::Jpeg => "image/jpeg",
+            MediaType::Gif => "image/gif",
+            MediaType::Webp => "image/webp",
+        }
+    }
+}
+
+impl ToSql for MediaType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for MediaType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        MediaType::from_name(value.as_str()?).ok_or(FromSqlError::InvalidType)
+    }
+}
+
+/// ## 📏 Media upload policy
+///
+/// The types and largest size of the files accepted by an upload.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MediaPolicy {
+    /// Types of the files accepted
+    pub allowed: Vec<MediaType>,
+    /// Largest accepted file, in bytes
+    pub max_size: usize,
+}
+
+impl MediaPolicy {
+    /// Accept images of all supported types up to a size, in bytes
+    pub fn images(max_size: usize) -> Self {
+        MediaPolicy {
+            allowed: MediaType::ALL.to_vec(),
+            max_size,
+        }
+    }
+
+    /// Buffer an uploaded file in the input of a request, `None` for the
+    /// empty file browsers send when no file is chosen. Files larger
+    /// than the policy allows are refused before they are buffered.
+    pub fn buffer(
+        &self,
+        file: Option<server::input::post::BufferedFile>,
+    ) -> Result<Option<Upload>, KontrollerError> {
+        match file.filter(|file| !file.data.is_empty()) {
+            Some(file) if file.data.len() > self.max_size => Err(KontrollerError::MediaSize),
+            Some(file) => Ok(Some(Upload {
+                data: file.data,
+                filename: file.filename,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Check an upload against the policy, returns its detected type
+    pub fn check(&self, data: &[u8]) -> Result<MediaType, KontrollerError> {
+        if data.len() > self.max_size {
+            return Err(KontrollerError::MediaSize);
+        }
+
+        match MediaType::sniff(data) {
+            Some(media_type) if self.allowed.contains(&media_type) => Ok(media_type),
+            _ => Err(KontrollerError::MediaType),
+        }
+    }
+}
+
+/// ## 📤 An uploaded file
+///
+/// Kept in the input of the request until the kontroller stores it.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct Upload {
+    /// The data of the file
+    pub data: Vec<u8>,
+    /// The client's file name
+    pub filename: Option<String>,
+}
+
+/// Make a file name hint from a client's file name: lowercase ASCII
+/// letters, digits and dashes, without the extension
+pub fn sanitize_name(file_name: &str) -> String {
+    // Browsers may send a full path
+    let name = file_name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let stem = match name.rsplit_once('.') {
+        Some((stem, _)) => stem,
+        None => name,
+    };
+
+    let mut sanitized = String::new();
+    for c in stem.chars() {
+        if c.is_ascii_alphanumeric() {
+            sanitized.push(c.to_ascii_lowercase());
+        } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+
+    sanitized
+        .chars()
+        .take(40)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string()
+}
+
+/// Generate a unique file name for a media file, the client's file
+/// name is kept as a hint when it has usable characters
+pub fn generate_name(file_name: Option<&str>, media_type: MediaType) -> String {
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    match file_name.map(sanitize_name).filter(|hint| !hint.is_empty()) {
+        Some(hint) => format!("{random}-{hint}.{}", media_type.extension()),
+        None => format!("{random}.{}", media_type.extension()),
+    }
+}
+
+/// Check an uploaded file against a policy and store it in a
+/// directory of the storage under a generated name, returns the URL
+/// of the stored file
+pub fn store_upload(
+    storage: &dyn MediaStorage,
+    policy: &MediaPolicy,
+    directory: &str,
+    file: &Upload,
+) -> Result<String, KontrollerError> {
+    let media_type = policy.check(&file.data)?;
+    let key = format!(
+        "{directory}/{}",
+        generate_name(file.filename.as_deref(), media_type)
+    );
+
+    storage.put(&key, &file.data)
+}
+
+/// ## 🔗 Media references
+///
+/// Lists the URLs of the stored files a kontroller uses.
+pub trait MediaReferences: Send + Sync {
+    /// URLs of the files in use
+    fn media_urls(&self) -> Result<Vec<String>, KontrollerError>;
+}
+
+/// Find the files of a storage that are not used by any of the
+/// references
+pub fn find_orphans(
+    storage: &dyn MediaStorage,
+    references: &[Box<dyn MediaReferences>],
+) -> Result<Vec<StoredFile>, KontrollerError> {
+    let mut used = HashSet::new();
+    for source in references {
+        used.extend(
+            source
+                .media_urls()?
+                .iter()
+                .filter_map(|url| storage.key(url)),
+        );
+    }
+
+    let mut orphans: Vec<StoredFile> = storage
+        .list()?
+        .into_iter()
+        .filter(|file| !used.contains(&file.key))
+        .collect();
+    orphans.sort_by(|a, b| a.key.cmp(&b.key));
+
+    Ok(orphans)
+}
+
+/// Remove the orphaned files last modified before a date from a
+/// storage, returns the removed files
+pub fn purge_orphans(
+    storage: &dyn MediaStorage,
+    references: &[Box<dyn MediaReferences>],
+    before: DateTime<Utc>,
+) -> Result<Vec<StoredFile>, KontrollerError> {
+    let orphans: Vec<StoredFile> = find_orphans(storage, references)?
+        .into_iter()
+        .filter(|file| file.modified < before)
+        .collect();
+
+    for file in &orphans {
+        storage.delete(&file.key)?;
+    }
+
+    Ok(orphans)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_media_policy() {
+        let png = b"\x89PNG\r\n\x1a\n rest of the image";
+        let policy = MediaPolicy::images(1024);
+
+        assert_eq!(policy.check(png).unwrap(), MediaType::Png);
+        assert!(matches!(
+            policy.check(b"<svg onload=alert(1)>"),
+            Err(KontrollerError::MediaType)
+        ));
+        assert!(matches!(
+            MediaPolicy::images(8).check(png),
+            Err(KontrollerError::MediaSize)
+        ));
+
+        let file = |data: &[u8]| server::input::post::BufferedFile {
+            data: data.to_vec(),
+            mime: "image/png".to_string(),
+            filename: Some("photo.png".to_string()),
+        };
+        assert_eq!(policy.buffer(Some(file(b""))).unwrap(), None);
+        assert_eq!(
+            policy.buffer(Some(file(png))).unwrap().unwrap().data,
+            png.to_vec()
+        );
+        assert!(matches!(
+            MediaPolicy::images(8).buffer(Some(file(png))),
+            Err(KontrollerError::MediaSize)
+        ));
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("../../etc/passwd"), "passwd");
+        assert_eq!(
+            sanitize_name("C:\\Users\\Me\\My Photo (1).JPG"),
+            "my-photo-1"
+        );
+        assert_eq!(sanitize_name(".."), "");
+
+        let name = generate_name(Some("My Photo.png"), MediaType::Jpeg);
+        assert!(name.ends_with("-my-photo.jpg"));
+    }
+
+    struct Covers(Vec<String>);
+
+    impl MediaReferences for Covers {
+        fn media_urls(&self) -> Result<Vec<String>, KontrollerError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_orphans() {
+        let root = "MEDIA_TEST_ORPHANS";
+        let _ = std::fs::remove_dir_all(root);
+        let storage = storage::LocalStorage {
+            root: root.to_string(),
+            url_prefix: "uploads".to_string(),
+        };
+
+        let used = storage.put("covers/used.png", b"data").unwrap();
+        storage.put("covers/orphan.png", b"data").unwrap();
+        let references: Vec<Box<dyn MediaReferences>> = vec![Box::new(Covers(vec![
+            used,
+            "https://example.com/elsewhere.png".to_string(),
+        ]))];
+
+        let orphans = find_orphans(&storage, &references).unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].key, "covers/orphan.png");
+
+        // Recent orphans are kept for the grace period
+        let past = Utc::now() - chrono::Duration::hours(1);
+        assert!(purge_orphans(&storage, &references, past)
+            .unwrap()
+            .is_empty());
+
+        let future = Utc::now() + chrono::Duration::hours(1);
+        assert_eq!(
+            purge_orphans(&storage, &references, future).unwrap(),
+            orphans
+        );
+        assert!(find_orphans(&storage, &references).unwrap().is_empty());
+        assert_eq!(storage.list().unwrap().len(), 1);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+//! # 🧹 Orphaned media kontroller
+//!
+//! This __kontroller__ allows admin accounts to list the orphaned files
+//! of a media storage: the stored files that none of the kontroller's
+//! references use, see [`super::MediaReferences`].
+//!
+//! In purge mode, the orphans last modified longer than the grace
+//! period ago are removed from the storage, and the purge is written to
+//! the audit log. Both modes respond with the list of files.
+
+use super::storage::MediaStorage;
+use super::{find_orphans, purge_orphans, MediaReferences};
+use crate::accounts::database::Database as AccountsDatabase;
+use crate::accounts::AuditEntry;
+use crate::login::admin_username;
+use chrono::{Duration, Utc};
+use kong::{server, ErrorResponse, Kong, Kontrol, Method};
+use std::sync::{Arc, Mutex};
+
+/// ## 🧹 Orphaned media kontroller
+pub struct MediaOrphansKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// Accounts database
+    pub accounts_database: Arc<Mutex<AccountsDatabase>>,
+    /// Storage of the media files
+    pub media_storage: Arc<dyn MediaStorage>,
+    /// The kontrollers' references to the stored files
+    pub references: Vec<Box<dyn MediaReferences>>,
+    /// Remove the orphans instead of only listing them
+    pub purge: bool,
+    /// How long orphans are kept before they can be purged
+    pub grace_period: Duration,
+}
+
+impl Kontrol for MediaOrphansKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    /// List or purge the orphaned media files
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let admin = match admin_username(kong, self.accounts_database.clone()) {
+            Ok(admin) => admin,
+            Err(response) => return response,
+        };
+
+        if !self.purge {
+            return match find_orphans(&*self.media_storage, &self.references) {
+                Ok(orphans) => server::Response::json(&orphans).with_status_code(200),
+                Err(_) => ErrorResponse::internal(),
+            };
+        }
+
+        let before = Utc::now() - self.grace_period;
+        let purged = match purge_orphans(&*self.media_storage, &self.references, before) {
+            Ok(purged) => purged,
+            Err(_) => return ErrorResponse::internal(),
+        };
+
+        let entry = AuditEntry {
+            actor: admin,
+            action: "media_purge".to_string(),
+            subject: None,
+            date: Utc::now(),
+        };
+
+        if self
+            .accounts_database
+            .lock()
+            .unwrap()
+            .create_audit_entry(&entry)
+            .is_err()
+        {
+            return ErrorResponse::internal();
+        }
+
+        server::Response::json(&purged).with_status_code(200)
+    }
+}
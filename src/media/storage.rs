@@ -0,0 +1,232 @@
+//! # 🗄️ Media storage backends
+//!
+//! A [`MediaStorage`] stores media files under keys: relative paths
+//! such as `covers/abc.png`, made of plain names. Keys with empty,
+//! hidden or parent directory (`..`) segments are refused, so files can
+//! only be stored and removed inside the storage.
+//!
+//! Cover photos uploaded before the storage were named after the title
+//! of their post, under [`LEGACY_DIRECTORY`]. Their keys are not plain
+//! names, they can still be found, listed and removed but no file is
+//! stored under such a key.
+
+use crate::error::KontrollerError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Directory of the cover photos uploaded before the storage, eg
+/// `blog_photos/<title>/<timestamp>-<filename>`
+pub const LEGACY_DIRECTORY: &str = "blog_photos/";
+
+/// Check if a key is a relative path made of plain names
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && !segment.starts_with('.')
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+}
+
+/// Check if a key is the path of a cover photo uploaded before the
+/// storage, inside [`LEGACY_DIRECTORY`]
+pub fn is_legacy_key(key: &str) -> bool {
+    key.strip_prefix(LEGACY_DIRECTORY).is_some_and(|path| {
+        !path.is_empty()
+            && path.split('/').all(|segment| {
+                !segment.is_empty() && !segment.starts_with('.') && !segment.contains(['\\', '\0'])
+            })
+            && Path::new(path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+    })
+}
+
+/// ## 📄 A file in a media storage
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct StoredFile {
+    /// Key of the file
+    pub key: String,
+    /// URL of the file
+    pub url: String,
+    /// Size of the file, in bytes
+    pub size: u64,
+    /// When the file was last modified
+    pub modified: DateTime<Utc>,
+}
+
+/// ## 🗄️ Stores media files
+pub trait MediaStorage: Send + Sync {
+    /// Store a file under a key, returns the URL of the file
+    fn put(&self, key: &str, data: &[u8]) -> Result<String, KontrollerError>;
+
+    /// Remove the file stored under a key
+    fn delete(&self, key: &str) -> Result<(), KontrollerError>;
+
+    /// URL of the file stored under a key
+    fn url(&self, key: &str) -> String;
+
+    /// Key of the file with a URL, `None` if the URL is not a file of
+    /// the storage
+    fn key(&self, url: &str) -> Option<String>;
+
+    /// All the files in the storage
+    fn list(&self) -> Result<Vec<StoredFile>, KontrollerError>;
+}
+
+/// ## 📁 Local filesystem media storage
+pub struct LocalStorage {
+    /// Directory the files are stored in, eg `./www/uploads`
+    pub root: String,
+    /// URL path the root directory is served at, eg `uploads`
+    pub url_prefix: String,
+}
+
+impl LocalStorage {
+    /// Path of the file stored under a key, or of a legacy cover photo
+    fn path(&self, key: &str) -> Result<PathBuf, KontrollerError> {
+        if is_valid_key(key) || is_legacy_key(key) {
+            Ok(PathBuf::from(&self.root).join(key))
+        } else {
+            Err(KontrollerError::MediaStorage)
+        }
+    }
+
+    /// Add the files in a directory, and its subdirectories, to a list
+    fn list_directory(
+        &self,
+        directory: &Path,
+        prefix: &str,
+        files: &mut Vec<StoredFile>,
+    ) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let key = format!("{prefix}{name}");
+            let metadata = entry.metadata()?;
+
+            if metadata.is_dir() {
+                self.list_directory(&entry.path(), &format!("{key}/"), files)?;
+            } else if is_valid_key(&key) || is_legacy_key(&key) {
+                files.push(StoredFile {
+                    url: self.url(&key),
+                    key,
+                    size: metadata.len(),
+                    modified: metadata.modified()?.into(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MediaStorage for LocalStorage {
+    fn put(&self, key: &str, data: &[u8]) -> Result<String, KontrollerError> {
+        if !is_valid_key(key) {
+            return Err(KontrollerError::MediaStorage);
+        }
+        let path = self.path(key)?;
+
+        // create the directory if it does not exist
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(|_| KontrollerError::MediaStorage)?;
+        }
+        std::fs::write(&path, data).map_err(|_| KontrollerError::MediaStorage)?;
+
+        Ok(self.url(key))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), KontrollerError> {
+        match std::fs::remove_file(self.path(key)?) {
+            Ok(()) => Ok(()),
+            // the file may already have been removed
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(_) => Err(KontrollerError::MediaStorage),
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        if self.url_prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{key}", self.url_prefix.trim_end_matches('/'))
+        }
+    }
+
+    fn key(&self, url: &str) -> Option<String> {
+        let prefix = self.url_prefix.trim_end_matches('/');
+        let key = if prefix.is_empty() {
+            url
+        } else {
+            url.strip_prefix(prefix)?.strip_prefix('/')?
+        };
+
+        Some(key.to_string()).filter(|key| is_valid_key(key) || is_legacy_key(key))
+    }
+
+    fn list(&self) -> Result<Vec<StoredFile>, KontrollerError> {
+        let mut files = vec![];
+
+        match self.list_directory(Path::new(&self.root), "", &mut files) {
+            Ok(()) => Ok(files),
+            // nothing was stored yet
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(files),
+            Err(_) => Err(KontrollerError::MediaStorage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_local_storage() {
+        let root = "MEDIA_TEST_STORAGE";
+        let _ = std::fs::remove_dir_all(root);
+        let storage = LocalStorage {
+            root: root.to_string(),
+            url_prefix: "uploads/".to_string(),
+        };
+
+        let url = storage.put("covers/photo.png", b"data").unwrap();
+        assert_eq!(url, "uploads/covers/photo.png");
+        assert_eq!(
+            std::fs::read(format!("{root}/covers/photo.png")).unwrap(),
+            b"data"
+        );
+        assert_eq!(storage.key(&url).unwrap(), "covers/photo.png");
+        assert_eq!(storage.list().unwrap()[0].key, "covers/photo.png");
+
+        // Keys can not leave the storage
+        assert!(storage.put("../photo.png", b"data").is_err());
+        assert!(storage.put("/etc/photo.png", b"data").is_err());
+        assert!(storage.put("C:/photo.png", b"data").is_err());
+        assert!(storage.put("covers/C:photo.png", b"data").is_err());
+        assert_eq!(storage.key("uploads/../secret"), None);
+
+        // Legacy cover photos are found and removed, but not stored
+        let legacy = "blog_photos/Rust: a journey/1700000000-my photo.png";
+        std::fs::create_dir_all(format!("{root}/blog_photos/Rust: a journey")).unwrap();
+        std::fs::write(format!("{root}/{legacy}"), b"data").unwrap();
+        assert_eq!(storage.key(&format!("uploads/{legacy}")).unwrap(), legacy);
+        assert!(storage
+            .list()
+            .unwrap()
+            .iter()
+            .any(|file| file.key == legacy));
+        assert!(storage.put(legacy, b"data").is_err());
+        storage.delete(legacy).unwrap();
+        assert!(!Path::new(&format!("{root}/{legacy}")).exists());
+        assert!(!is_legacy_key("blog_photos/../secret"));
+        assert!(!is_legacy_key("blog_photos/"));
+
+        storage.delete("covers/photo.png").unwrap();
+        storage.delete("covers/photo.png").unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,472 @@
+//! # 🖼️ Responsive image variants
+//!
+//! Uploaded images are decoded and encoded again before they are
+//! stored, which drops their metadata, such as the EXIF data of photos
+//! with the location they were taken at. The EXIF orientation of JPEG
+//! photos is applied to the pixels first, so photos stay the right way
+//! up. GIF images are encoded again frame by frame, to keep their
+//! animation without their extension blocks, which can hold XMP
+//! metadata. Images are stored with 8 bits per channel.
+//!
+//! Images wider or higher than [`MAX_DIMENSION`] are refused before
+//! they are decoded, so small files with huge dimensions can not use
+//! up the memory and time of the app.
+//!
+//! Smaller variants of the image are stored along with it, see
+//! [`VariantSize`], in the type of the image and as lossy WebP. A
+//! variant is only made when the image is wider than the variant.
+
+use super::storage::MediaStorage;
+use super::{generate_name, MediaPolicy, MediaType, Upload};
+use crate::error::KontrollerError;
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::webp::{WebPEncoder, WebPQuality};
+use image::{
+    imageops::FilterType, AnimationDecoder, ColorType, DynamicImage, ImageDecoder, ImageFormat,
+    ImageOutputFormat,
+};
+use kong::{json, json_from_str};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+/// Largest width and height of an uploaded image, in pixels
+pub const MAX_DIMENSION: u32 = 8192;
+
+/// Most pixels of all the frames of an animated GIF together
+pub const MAX_ANIMATION_PIXELS: u64 = 100_000_000;
+
+/// Quality of the WebP variants, from 0 to 100
+const WEBP_QUALITY: u8 = 80;
+
+/// ## 📐 Size of an image variant
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct VariantSize {
+    /// Name of the variant, eg `thumbnail`
+    pub name: String,
+    /// Width of the variant in pixels, the height keeps the aspect
+    /// ratio of the image
+    pub width: u32,
+}
+
+impl VariantSize {
+    /// New variant size
+    pub fn new(name: &str, width: u32) -> Self {
+        VariantSize {
+            name: name.to_string(),
+            width,
+        }
+    }
+
+    /// The `thumbnail` (320 pixels), `medium` (800 pixels) and `large`
+    /// (1600 pixels) variants
+    pub fn defaults() -> Vec<VariantSize> {
+        vec![
+            VariantSize::new("thumbnail", 320),
+            VariantSize::new("medium", 800),
+            VariantSize::new("large", 1600),
+        ]
+    }
+}
+
+/// ## 🖼️ A stored image variant
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct ImageVariant {
+    /// URL of the variant, in the type of the original image
+    pub url: String,
+    /// URL of the variant as WebP
+    pub webp: String,
+    /// Width of the variant, in pixels
+    pub width: u32,
+    /// Height of the variant, in pixels
+    pub height: u32,
+}
+
+/// ## 🗂️ The variants of an image, by name
+///
+/// Stored in the databases as JSON.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct ImageVariants(pub BTreeMap<String, ImageVariant>);
+
+impl ImageVariants {
+    /// URLs of all the stored files of the variants
+    pub fn urls(&self) -> Vec<String> {
+        let mut urls = vec![];
+        for variant in self.0.values() {
+            urls.push(variant.url.clone());
+            if variant.webp != variant.url {
+                urls.push(variant.webp.clone());
+            }
+        }
+        urls
+    }
+}
+
+impl ToSql for ImageVariants {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(json!(self).to_string().into())
+    }
+}
+
+impl FromSql for ImageVariants {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        json_from_str(value.as_str()?).map_err(|err| FromSqlError::Other(Box::new(err)))
+    }
+}
+
+/// ## 📦 A stored image and its variants
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct StoredImage {
+    /// URL of the image
+    pub url: String,
+    /// Type of the image
+    pub media_type: MediaType,
+    /// Size of the stored image, in bytes
+    pub size: usize,
+    /// Width of the image, in pixels
+    pub width: u32,
+    /// Height of the image, in pixels
+    pub height: u32,
+    /// The variants of the image
+    pub variants: ImageVariants,
+}
+
+/// Format of the `image` crate for a media type
+fn image_format(media_type: MediaType) -> ImageFormat {
+    match media_type {
+        MediaType::Png => ImageFormat::Png,
+        MediaType::Jpeg => ImageFormat::Jpeg,
+        MediaType::Gif => ImageFormat::Gif,
+        MediaType::Webp => ImageFormat::WebP,
+    }
+}
+
+/// Decode an image, images larger than [`MAX_DIMENSION`] are refused
+fn decode(data: &[u8], media_type: MediaType) -> Result<DynamicImage, KontrollerError> {
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+
+    let mut reader = image::io::Reader::with_format(Cursor::new(data), image_format(media_type));
+    reader.limits(limits);
+    let image = reader.decode().map_err(|_| KontrollerError::MediaType)?;
+
+    // The JPEG and WebP encoders only take 8 bits per channel
+    Ok(match image {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_) => image,
+        image if image.color().has_alpha() => DynamicImage::ImageRgba8(image.to_rgba8()),
+        image => DynamicImage::ImageRgb8(image.to_rgb8()),
+    })
+}
+
+/// Encode all the frames of a GIF image again, without its extension
+/// blocks. The animation loops forever.
+fn reencode_gif(data: &[u8]) -> Result<Vec<u8>, KontrollerError> {
+    let decoder = GifDecoder::new(Cursor::new(data)).map_err(|_| KontrollerError::MediaType)?;
+    let (width, height) = decoder.dimensions();
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(KontrollerError::MediaType);
+    }
+
+    let mut frames = vec![];
+    let mut pixels = 0;
+    for frame in decoder.into_frames() {
+        let frame = frame.map_err(|_| KontrollerError::MediaType)?;
+        pixels += u64::from(frame.buffer().width()) * u64::from(frame.buffer().height());
+        if pixels > MAX_ANIMATION_PIXELS {
+            return Err(KontrollerError::MediaSize);
+        }
+        frames.push(frame);
+    }
+
+    let mut data = vec![];
+    {
+        let mut encoder = GifEncoder::new(&mut data);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .and_then(|()| encoder.encode_frames(frames))
+            .map_err(|_| KontrollerError::MediaStorage)?;
+    }
+
+    Ok(data)
+}
+
+/// Encode an image, without any metadata
+fn encode(image: &DynamicImage, media_type: MediaType) -> Result<Vec<u8>, KontrollerError> {
+    let format = match media_type {
+        MediaType::Png => ImageOutputFormat::Png,
+        MediaType::Jpeg => ImageOutputFormat::Jpeg(85),
+        MediaType::Gif => ImageOutputFormat::Gif,
+        MediaType::Webp => {
+            // The `image` crate writes lossless WebP, which is larger
+            // than the JPEG it is meant to replace
+            let (pixels, color) = if image.color().has_alpha() {
+                (image.to_rgba8().into_raw(), ColorType::Rgba8)
+            } else {
+                (image.to_rgb8().into_raw(), ColorType::Rgb8)
+            };
+            let mut data = vec![];
+            #[allow(deprecated)]
+            WebPEncoder::new_with_quality(&mut data, WebPQuality::lossy(WEBP_QUALITY))
+                .encode(&pixels, image.width(), image.height(), color)
+                .map_err(|_| KontrollerError::MediaStorage)?;
+
+            return Ok(data);
+        }
+    };
+
+    let mut data = Cursor::new(vec![]);
+    image
+        .write_to(&mut data, format)
+        .map_err(|_| KontrollerError::MediaStorage)?;
+
+    Ok(data.into_inner())
+}
+
+/// Read the EXIF orientation of a JPEG photo, from 1 to 8
+fn jpeg_orientation(data: &[u8]) -> Option<u16> {
+    // Skip the start of image marker
+    let mut at = 2;
+
+    while data.get(at) == Some(&0xff) {
+        let marker = *data.get(at + 1)?;
+        let length = u16::from_be_bytes([*data.get(at + 2)?, *data.get(at + 3)?]) as usize;
+
+        if marker == 0xe1 {
+            let segment = data.get(at + 4..at + 2 + length)?;
+            if let Some(tiff) = segment.strip_prefix(b"Exif\0\0") {
+                return tiff_orientation(tiff);
+            }
+        } else if marker == 0xda {
+            // The image data starts, no more metadata
+            return None;
+        }
+
+        at += 2 + length;
+    }
+
+    None
+}
+
+/// Read the orientation tag of the first directory of TIFF data
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(0..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| -> Option<u16> {
+        let bytes = [*tiff.get(at)?, *tiff.get(at + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let u32_at = |at: usize| -> Option<u32> {
+        let bytes = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    let directory = u32_at(4)? as usize;
+    for entry in 0..u16_at(directory)? as usize {
+        let entry = directory + 2 + entry * 12;
+        if u16_at(entry)? == 0x0112 {
+            return u16_at(entry + 8);
+        }
+    }
+
+    None
+}
+
+/// Turn an image the way its EXIF orientation says
+fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Check an uploaded image against a policy and store it without its
+/// metadata in a directory of the storage, along with its variants
+pub fn store_image(
+    storage: &dyn MediaStorage,
+    policy: &MediaPolicy,
+    directory: &str,
+    file: &Upload,
+    sizes: &[VariantSize],
+) -> Result<StoredImage, KontrollerError> {
+    let media_type = policy.check(&file.data)?;
+    let name = generate_name(file.filename.as_deref(), media_type);
+    let stem = name
+        .strip_suffix(&format!(".{}", media_type.extension()))
+        .unwrap_or(&name);
+
+    let image = decode(&file.data, media_type)?;
+    let image = match jpeg_orientation(&file.data).filter(|_| media_type == MediaType::Jpeg) {
+        Some(orientation) => apply_orientation(image, orientation),
+        None => image,
+    };
+
+    let data = if media_type == MediaType::Gif {
+        reencode_gif(&file.data)?
+    } else {
+        encode(&image, media_type)?
+    };
+    let url = storage.put(&format!("{directory}/{name}"), &data)?;
+
+    let mut variants = BTreeMap::new();
+    for size in sizes.iter().filter(|size| size.width < image.width()) {
+        let resized = image.resize(size.width, u32::MAX, FilterType::Lanczos3);
+        let key = format!("{directory}/{stem}-{}", size.name);
+
+        let variant_url = storage.put(
+            &format!("{key}.{}", media_type.extension()),
+            &encode(&resized, media_type)?,
+        )?;
+        let webp = if media_type == MediaType::Webp {
+            variant_url.clone()
+        } else {
+            storage.put(&format!("{key}.webp"), &encode(&resized, MediaType::Webp)?)?
+        };
+
+        variants.insert(
+            size.name.clone(),
+            ImageVariant {
+                url: variant_url,
+                webp,
+                width: resized.width(),
+                height: resized.height(),
+            },
+        );
+    }
+
+    Ok(StoredImage {
+        url,
+        media_type,
+        size: data.len(),
+        width: image.width(),
+        height: image.height(),
+        variants: ImageVariants(variants),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::media::storage::LocalStorage;
+
+    /// An EXIF segment with the orientation of a photo taken with the
+    /// camera turned right
+    const EXIF_ROTATED: &[u8] = b"\xff\xe1\x00\x22Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0";
+
+    fn upload(data: Vec<u8>) -> Upload {
+        Upload {
+            data,
+            filename: Some("Holiday.jpg".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_store_image() {
+        let root = "MEDIA_TEST_VARIANTS";
+        let _ = std::fs::remove_dir_all(root);
+        let storage = LocalStorage {
+            root: root.to_string(),
+            url_prefix: "uploads".to_string(),
+        };
+        let policy = MediaPolicy::images(1024 * 1024);
+        let sizes = [VariantSize::new("small", 20), VariantSize::new("huge", 400)];
+
+        let jpeg = encode(&DynamicImage::new_rgb8(80, 40), MediaType::Jpeg).unwrap();
+        let stored = store_image(&storage, &policy, "covers", &upload(jpeg), &sizes).unwrap();
+        assert_eq!((stored.width, stored.height), (80, 40));
+
+        // Images are not scaled up
+        assert_eq!(stored.variants.0.len(), 1);
+        let small = &stored.variants.0["small"];
+        assert_eq!((small.width, small.height), (20, 10));
+        assert!(small.url.ends_with("-holiday-small.jpg"));
+        assert!(small.webp.ends_with("-holiday-small.webp"));
+        assert_eq!(storage.list().unwrap().len(), 3);
+
+        // The EXIF data is dropped, its orientation is kept
+        let jpeg = encode(&DynamicImage::new_rgb8(80, 40), MediaType::Jpeg).unwrap();
+        let photo = [&jpeg[..2], EXIF_ROTATED, &jpeg[2..]].concat();
+        assert_eq!(jpeg_orientation(&photo), Some(6));
+
+        let stored = store_image(&storage, &policy, "covers", &upload(photo), &[]).unwrap();
+        assert_eq!((stored.width, stored.height), (40, 80));
+        let key = storage.key(&stored.url).unwrap();
+        let data = std::fs::read(format!("{root}/{key}")).unwrap();
+        assert_eq!(jpeg_orientation(&data), None);
+
+        // Images with huge dimensions are not decoded
+        let wide = encode(
+            &DynamicImage::new_luma8(MAX_DIMENSION + 1, 1),
+            MediaType::Png,
+        )
+        .unwrap();
+        assert!(matches!(
+            store_image(&storage, &policy, "covers", &upload(wide), &[]),
+            Err(KontrollerError::MediaType)
+        ));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_store_image_encodings() {
+        let root = "MEDIA_TEST_ENCODINGS";
+        let _ = std::fs::remove_dir_all(root);
+        let storage = LocalStorage {
+            root: root.to_string(),
+            url_prefix: "uploads".to_string(),
+        };
+        let policy = MediaPolicy::images(1024 * 1024);
+        let sizes = [VariantSize::new("small", 20)];
+        let read =
+            |url: &str| std::fs::read(format!("{root}/{}", storage.key(url).unwrap())).unwrap();
+
+        // 16 bit images are stored with 8 bits per channel
+        let png = encode(&DynamicImage::new_rgb16(80, 40), MediaType::Png).unwrap();
+        let stored = store_image(&storage, &policy, "covers", &upload(png), &sizes).unwrap();
+        let image = image::load_from_memory(&read(&stored.url)).unwrap();
+        assert_eq!(image.color(), ColorType::Rgb8);
+
+        // The WebP variants are lossy
+        let webp = read(&stored.variants.0["small"].webp);
+        assert_eq!(&webp[12..16], b"VP8 ");
+
+        // The extension blocks of GIF images are dropped, the frames kept
+        let frames = (0..2).map(|_| image::Frame::new(image::RgbaImage::new(30, 20)));
+        let mut gif = vec![];
+        GifEncoder::new(&mut gif).encode_frames(frames).unwrap();
+        let xmp = b"\x21\xff\x0bXMP DataXMP\x0aGPS 52,4.3\x00";
+        let gif = [&gif[..gif.len() - 1], xmp, b"\x3b"].concat();
+        assert_eq!(MediaType::sniff(&gif), Some(MediaType::Gif));
+
+        let stored = store_image(&storage, &policy, "covers", &upload(gif), &sizes).unwrap();
+        let data = read(&stored.url);
+        assert!(!data.windows(3).any(|window| window == b"XMP"));
+        let decoder = GifDecoder::new(Cursor::new(data)).unwrap();
+        assert_eq!(decoder.into_frames().count(), 2);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,38 @@
+//! # 🧳 Database migrations
+//!
+//! Helpers used by the __kontrollers__ to upgrade the tables of
+//! databases created by older versions. `CREATE TABLE IF NOT EXISTS`
+//! does not add new columns to an existing table.
+
+use crate::error::KontrollerError;
+use rusqlite::Connection;
+
+/// Add a column to a table if the table does not have it yet.
+/// `definition` is the column's type and constraints, eg `TEXT`.
+pub(crate) fn add_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), KontrollerError> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({table})"))
+        .map_err(|_| KontrollerError::DbSQL)?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|_| KontrollerError::DbSQL)?;
+
+    for name in columns {
+        if name.map_err(|_| KontrollerError::DbField)? == column {
+            return Ok(());
+        }
+    }
+
+    conn.execute(
+        &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+        (),
+    )
+    .map_err(|_| KontrollerError::DbTableCreation)?;
+
+    Ok(())
+}
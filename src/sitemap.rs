@@ -0,0 +1,206 @@
+//! # 🗺️ Sitemap kontroller
+//!
+//! The `sitemap` __kontroller__ serves a `sitemap.xml` listing the
+//! public pages of the site, so search engines can find them.
+//!
+//! > To use the sitemap kontroller, enable it with the `sitemap` Cargo
+//! > feature.
+//!
+//! The pages are contributed by [`SitemapSource`]s, such as the blog's
+//! published posts or the accounts' public profiles. Other kontrollers
+//! can contribute pages by implementing [`SitemapSource`].
+//!
+//! A sitemap can have at most 50 000 URLs. When there are more URLs
+//! than the kontroller's `max_urls`, the sitemap is split in pages
+//! read with the `page` query parameter, and the sitemap itself is a
+//! sitemap index listing the pages.
+
+use crate::error::KontrollerError;
+use crate::xml::escape;
+use chrono::{DateTime, SecondsFormat, Utc};
+use kong::{json, server, ErrorResponse, JsonValue, Kong, Kontrol, Method};
+
+/// ## 🔗 A page listed in the sitemap
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SitemapUrl {
+    /// Path of the page, relative to the site URL, eg `/blog/hello`
+    pub path: String,
+    /// When the page was last modified
+    pub lastmod: Option<DateTime<Utc>>,
+}
+
+/// ## 🧩 Contributes pages to the sitemap
+pub trait SitemapSource: Send + Sync {
+    /// The pages to list in the sitemap
+    fn sitemap_urls(&self) -> Result<Vec<SitemapUrl>, KontrollerError>;
+}
+
+/// ## 🗺️ Sitemap kontroller
+pub struct SitemapKontroller {
+    /// Address to kontroller
+    pub address: String,
+    /// HTTP method supported by the kontroller
+    pub method: Method,
+    /// Base URL of the site, eg `https://example.com`
+    pub site_url: String,
+    /// The sources of the pages listed in the sitemap
+    pub sources: Vec<Box<dyn SitemapSource>>,
+    /// Largest number of URLs in a sitemap, at most 50 000
+    pub max_urls: usize,
+}
+
+impl SitemapKontroller {
+    /// Largest number of URLs in a sitemap allowed by the sitemap
+    /// protocol
+    pub const MAX_URLS: usize = 50_000;
+
+    /// Absolute URL of a path on the site
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.site_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    /// Number of URLs per sitemap page
+    fn page_size(&self) -> usize {
+        self.max_urls.clamp(1, Self::MAX_URLS)
+    }
+
+    /// Build a sitemap
+    pub fn urlset(&self, urls: &[SitemapUrl]) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+
+        for url in urls {
+            xml.push_str("<url>");
+            xml.push_str(&format!("<loc>{}</loc>", escape(&self.url(&url.path))));
+            if let Some(lastmod) = url.lastmod {
+                xml.push_str(&format!(
+                    "<lastmod>{}</lastmod>",
+                    lastmod.to_rfc3339_opts(SecondsFormat::Secs, true)
+                ));
+            }
+            xml.push_str("</url>\n");
+        }
+
+        xml.push_str("</urlset>\n");
+        xml
+    }
+
+    /// Build a sitemap index listing the pages of the sitemap
+    pub fn index(&self, pages: usize) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+
+        for page in 1..=pages {
+            let loc = format!("{}?page={page}", self.url(&self.address));
+            xml.push_str(&format!("<sitemap><loc>{}</loc></sitemap>\n", escape(&loc)));
+        }
+
+        xml.push_str("</sitemapindex>\n");
+        xml
+    }
+}
+
+impl Kontrol for SitemapKontroller {
+    /// Endpoint's address
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// Method supported by endpoint
+    fn method(&self) -> Method {
+        self.method
+    }
+
+    fn get_input(&self, request: &server::Request) -> Option<JsonValue> {
+        match request.get_param("page") {
+            Some(page) => Some(json!({ "page": page.parse::<usize>().ok()? })),
+            None => Some(json!({ "page": null })),
+        }
+    }
+
+    /// Validate user input
+    fn validate(&self, input: Option<JsonValue>) -> Result<Option<JsonValue>, ()> {
+        match input.as_ref().map(|input| &input["page"]) {
+            Some(JsonValue::Null) => Ok(input),
+            Some(page) if page.as_u64().is_some_and(|page| page >= 1) => Ok(input),
+            _ => Err(()),
+        }
+    }
+
+    /// Get sitemap
+    fn kontrol(&self, kong: &Kong) -> server::Response {
+        let page = kong
+            .input
+            .as_ref()
+            .and_then(|input| input["page"].as_u64())
+            .map(|page| page as usize);
+
+        let mut urls = vec![];
+        for source in &self.sources {
+            match source.sitemap_urls() {
+                Ok(source_urls) => urls.extend(source_urls),
+                Err(_) => return ErrorResponse::internal(),
+            }
+        }
+
+        let page_size = self.page_size();
+        let pages = urls.len().div_ceil(page_size);
+
+        let xml = match page {
+            Some(page) if page > pages.max(1) => return ErrorResponse::not_found(),
+            Some(page) => {
+                let start = (page - 1) * page_size;
+                let end = urls.len().min(start + page_size);
+                self.urlset(&urls[start..end])
+            }
+            None if pages > 1 => self.index(pages),
+            None => self.urlset(&urls),
+        };
+
+        server::Response::from_data("application/xml; charset=utf-8", xml).with_status_code(200)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Pages(usize);
+
+    impl SitemapSource for Pages {
+        fn sitemap_urls(&self) -> Result<Vec<SitemapUrl>, KontrollerError> {
+            Ok((0..self.0)
+                .map(|i| SitemapUrl {
+                    path: format!("/page/{i}"),
+                    lastmod: None,
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_sitemap() {
+        let kontroller = SitemapKontroller {
+            address: "/sitemap.xml".to_string(),
+            method: Method::Get,
+            site_url: "https://example.com/".to_string(),
+            sources: vec![Box::new(Pages(3))],
+            max_urls: 2,
+        };
+
+        let urls = kontroller.sources[0].sitemap_urls().unwrap();
+        let urlset = kontroller.urlset(&urls);
+        assert!(urlset.contains("<url><loc>https://example.com/page/0</loc></url>"));
+
+        let index = kontroller.index(2);
+        assert!(index.contains("<loc>https://example.com/sitemap.xml?page=2</loc>"));
+    }
+}
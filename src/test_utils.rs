@@ -0,0 +1,53 @@
+//! # 🧪 Test fixtures
+//!
+//! Shared by the tests of the kontrollers.
+
+#[cfg(feature = "blog")]
+use crate::blog::{DatabaseBlogPostInput, PostStatus};
+
+/// ## 🧪 Temporary database file
+///
+/// Removes the database file when dropped, and when created in case a
+/// failed test left it behind.
+pub struct TempDatabase {
+    /// Path of the database file
+    pub path: String,
+}
+
+impl TempDatabase {
+    /// Use a database file for the length of a test
+    pub fn new(path: &str) -> Self {
+        let _ = std::fs::remove_file(path);
+
+        TempDatabase {
+            path: path.to_string(),
+        }
+    }
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A published blog post with a title and content, and none of the
+/// optional fields
+#[cfg(feature = "blog")]
+pub fn blog_input() -> DatabaseBlogPostInput {
+    DatabaseBlogPostInput {
+        title: "Title".to_string(),
+        subtitle: None,
+        overview: None,
+        author: None,
+        cover: None,
+        cover_variants: None,
+        content: "Content".to_string(),
+        date: None,
+        slug: None,
+        status: PostStatus::Published,
+        tags: None,
+        co_authors: None,
+        format: None,
+    }
+}